@@ -0,0 +1,69 @@
+/*
+cargo bench -p burpsuite-kit --features parallel --bench parse
+*/
+
+use std::fs;
+
+use burpsuite_kit::http_history::{
+    items::{ItemRefs, Items},
+    parallel::ParallelItems,
+};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+const ITEMS_LEN: usize = 5000;
+
+/// An export of `ITEMS_LEN` items, repeated from the test files.
+fn document() -> Vec<u8> {
+    let mut items = vec![];
+    for name in [
+        "burpsuite_community_v2021.3.2.xml",
+        "burpsuite_community_v2020.12.1.xml",
+    ] {
+        let xml = fs::read_to_string(format!("tests/http_history_files/{name}")).unwrap();
+        let mut rest = xml.as_str();
+        while let Some(start) = rest.find("<item>") {
+            let end = rest[start..].find("</item>").unwrap() + start + "</item>".len();
+            items.push(rest[start..end].to_owned());
+            rest = &rest[end..];
+        }
+    }
+
+    let mut document = "<?xml version=\"1.0\"?>\n<items burpVersion=\"2021.3.2\" exportTime=\"Wed Mar 31 13:07:44 CST 2021\">\n".to_owned();
+    for item in items.iter().cycle().take(ITEMS_LEN) {
+        document.push_str("  ");
+        document.push_str(item);
+        document.push('\n');
+    }
+    document.push_str("</items>\n");
+    document.into_bytes()
+}
+
+fn parse(c: &mut Criterion) {
+    let document = document();
+
+    let mut group = c.benchmark_group("parse");
+    group.throughput(Throughput::Bytes(document.len() as u64));
+    group.sample_size(10);
+    group.bench_function("sequential", |b| {
+        b.iter(|| {
+            let items = Items::from_reader(black_box(&document[..])).unwrap();
+            assert_eq!(items.filter(|x| x.is_ok()).count(), ITEMS_LEN);
+        })
+    });
+    group.bench_function("borrowed", |b| {
+        b.iter(|| {
+            let items = ItemRefs::from_slice(black_box(&document[..])).unwrap();
+            assert_eq!(items.filter(|x| x.is_ok()).count(), ITEMS_LEN);
+        })
+    });
+    group.bench_function("parallel", |b| {
+        b.iter(|| {
+            let items = ParallelItems::from_reader(black_box(&document[..])).unwrap();
+            assert_eq!(items.filter(|x| x.is_ok()).count(), ITEMS_LEN);
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
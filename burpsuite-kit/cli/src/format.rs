@@ -0,0 +1,389 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Error as IoError, Write},
+    path::Path,
+};
+
+use base64::DecodeError;
+use burpsuite_kit::http_history::{
+    compression::{self, Compression, CompressionError},
+    csv::{CsvExportError, CsvWriter},
+    item::Item,
+    items::{ItemParseError, Items, ItemsAttr, ItemsParseError},
+    jsonl::{JsonlError, JsonlReader, JsonlWriter, PayloadFormat},
+    merge::PartWriter,
+    mitmproxy::{MitmError, MitmFlowReader, MitmFlowWriter},
+    parquet::{ParquetExportError, ParquetExportOptions, ParquetItemsWriter},
+    pcap::{self, PcapError},
+    warc::{WarcError, WarcReader, WarcWriter},
+    writer::{ItemsWriteError, ItemsWriter},
+    zap::{self, ZapImportError, ZapRawReader},
+};
+use chrono::Utc;
+use clap::ValueEnum;
+
+/// Path meaning stdin or stdout.
+pub const STDIO_PATH: &str = "-";
+
+#[derive(thiserror::Error, Debug)]
+pub enum CliError {
+    #[error("IoError {0}")]
+    IoError(#[from] IoError),
+    #[error("FormatUnknown {0}, use --from or --to")]
+    FormatUnknown(String),
+    #[error("FormatUnsupported {0}")]
+    FormatUnsupported(&'static str),
+    #[error("ItemsParseError {0}")]
+    ItemsParseError(#[from] ItemsParseError),
+    #[error("ItemParseError {0}")]
+    ItemParseError(#[from] ItemParseError),
+    #[error("ItemsWriteError {0}")]
+    ItemsWriteError(#[from] ItemsWriteError),
+    #[error("JsonlError {0}")]
+    JsonlError(#[from] JsonlError),
+    #[error("MitmError {0}")]
+    MitmError(#[from] MitmError),
+    #[error("ZapImportError {0}")]
+    ZapImportError(#[from] ZapImportError),
+    #[error("PcapError {0}")]
+    PcapError(#[from] PcapError),
+    #[error("WarcError {0}")]
+    WarcError(#[from] WarcError),
+    #[error("CsvExportError {0}")]
+    CsvExportError(#[from] CsvExportError),
+    #[error("ParquetExportError {0}")]
+    ParquetExportError(#[from] ParquetExportError),
+    #[error("PayloadDecodeFailed {0}")]
+    PayloadDecodeFailed(#[from] DecodeError),
+    #[error("CompressionError {0}")]
+    CompressionError(#[from] CompressionError),
+    #[error("RegexError {0}")]
+    RegexError(#[from] regex::Error),
+    #[error("ItemNotFound {0}")]
+    ItemNotFound(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// Burp Suite "Save items" XML.
+    Xml,
+    Jsonl,
+    Csv,
+    Parquet,
+    /// mitmproxy flow dump.
+    Mitmproxy,
+    /// HAR exported by OWASP ZAP.
+    Har,
+    /// OWASP ZAP "Export Messages to File".
+    ZapRaw,
+    /// pcap or pcapng capture of plaintext HTTP.
+    Pcap,
+    Warc,
+}
+
+impl Format {
+    /// Format named by the extension of `path`, before any compression extension
+    /// such as in `history.xml.gz`.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let path = uncompressed_path(path);
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        Some(match extension.as_str() {
+            "xml" => Self::Xml,
+            "jsonl" | "ndjson" => Self::Jsonl,
+            "csv" => Self::Csv,
+            "parquet" => Self::Parquet,
+            "mitm" | "flow" | "flows" => Self::Mitmproxy,
+            "har" => Self::Har,
+            "pcap" | "pcapng" | "cap" => Self::Pcap,
+            "warc" => Self::Warc,
+            _ => return None,
+        })
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Xml => "xml",
+            Self::Jsonl => "jsonl",
+            Self::Csv => "csv",
+            Self::Parquet => "parquet",
+            Self::Mitmproxy => "flows",
+            Self::Har => "har",
+            Self::ZapRaw => "txt",
+            Self::Pcap => "pcap",
+            Self::Warc => "warc",
+        }
+    }
+
+    /// Reason for formats that can only be written.
+    fn output_only(self) -> Option<&'static str> {
+        match self {
+            Self::Csv => Some("csv can only be written"),
+            Self::Parquet => Some("parquet can only be written"),
+            _ => None,
+        }
+    }
+
+    /// Reason for formats that can only be read.
+    fn input_only(self) -> Option<&'static str> {
+        match self {
+            Self::Har => Some("har can only be read"),
+            Self::ZapRaw => Some("zap-raw can only be read"),
+            Self::Pcap => Some("pcap can only be read"),
+            _ => None,
+        }
+    }
+
+    /// `format` when given, else from the extension. stdin defaults to XML.
+    pub fn resolve(format: Option<Self>, path: &Path, is_input: bool) -> Result<Self, CliError> {
+        if let Some(x) = format {
+            return Ok(x);
+        }
+        if is_input && path == Path::new(STDIO_PATH) {
+            return Ok(Self::Xml);
+        }
+        Self::from_path(path).ok_or_else(|| CliError::FormatUnknown(path.display().to_string()))
+    }
+}
+
+/// `path` without its compression extension, if any.
+pub fn uncompressed_path(path: &Path) -> &Path {
+    match (Compression::from_path(path), path.file_stem()) {
+        (Compression::None, _) | (_, None) => path,
+        (_, Some(x)) => Path::new(x),
+    }
+}
+
+//
+pub struct Source {
+    /// Header of the export, when the format has one.
+    pub attr: Option<ItemsAttr>,
+    pub items: Box<dyn Iterator<Item = Result<Item, CliError>>>,
+}
+
+/// Open `path`, decompressing it when it starts with a known magic number.
+fn open(path: &Path) -> Result<Box<dyn BufRead + Send>, CliError> {
+    let reader: Box<dyn BufRead + Send> = if path == Path::new(STDIO_PATH) {
+        Box::new(BufReader::new(io::stdin()))
+    } else {
+        Box::new(BufReader::new(File::open(path)?))
+    };
+    Ok(compression::decompress(reader)?)
+}
+
+pub fn read(path: &Path, format: Option<Format>) -> Result<Source, CliError> {
+    let format = Format::resolve(format, path, true)?;
+    if let Some(x) = format.output_only() {
+        return Err(CliError::FormatUnsupported(x));
+    }
+    let reader = open(path)?;
+    Ok(match format {
+        Format::Xml => {
+            let items = Items::from_reader(reader)?;
+            Source {
+                attr: Some(items.attr.to_owned()),
+                items: Box::new(items.map(|x| x.map_err(Into::into))),
+            }
+        }
+        Format::Jsonl => {
+            let items = JsonlReader::from_reader(reader)?;
+            Source {
+                attr: items.attr.to_owned(),
+                items: Box::new(items.map(|x| x.map_err(Into::into))),
+            }
+        }
+        Format::Mitmproxy => Source {
+            attr: None,
+            items: Box::new(MitmFlowReader::new(reader).map(|x| x.map_err(Into::into))),
+        },
+        Format::Har => Source {
+            attr: None,
+            items: Box::new(zap::items_from_har(reader)?.into_iter().map(Ok)),
+        },
+        Format::ZapRaw => Source {
+            attr: None,
+            items: Box::new(ZapRawReader::new(reader).map(|x| x.map_err(Into::into))),
+        },
+        Format::Pcap => Source {
+            attr: None,
+            items: Box::new(pcap::items_from_pcap(reader)?.into_iter().map(Ok)),
+        },
+        Format::Warc => Source {
+            attr: None,
+            items: Box::new(WarcReader::new(reader).map(|x| x.map_err(Into::into))),
+        },
+        Format::Csv | Format::Parquet => unreachable!(),
+    })
+}
+
+/// Header for formats that need one when the input had none.
+pub fn default_attr() -> ItemsAttr {
+    ItemsAttr {
+        burp_version: format!("burpsuite-kit {}", env!("CARGO_PKG_VERSION")),
+        export_time: Utc::now().naive_utc(),
+    }
+}
+
+//
+type Output = BufWriter<Box<dyn Write + Send>>;
+
+pub enum Sink {
+    Xml(ItemsWriter<Output>),
+    Jsonl(JsonlWriter<Output>),
+    Csv(Box<CsvWriter<Output>>, usize),
+    Parquet(Box<ParquetItemsWriter<Output>>),
+    Mitmproxy(MitmFlowWriter<Output>),
+    Warc(WarcWriter<Output>),
+}
+
+impl Sink {
+    pub fn create(
+        path: &Path,
+        format: Option<Format>,
+        attr: &ItemsAttr,
+        payload_format: PayloadFormat,
+    ) -> Result<Self, CliError> {
+        let format = Format::resolve(format, path, false)?;
+        if let Some(x) = format.input_only() {
+            return Err(CliError::FormatUnsupported(x));
+        }
+        let writer: Box<dyn Write + Send> = if path == Path::new(STDIO_PATH) {
+            Box::new(io::stdout())
+        } else {
+            Box::new(File::create(path)?)
+        };
+        let writer = BufWriter::new(writer);
+
+        Ok(match format {
+            Format::Xml => Self::Xml(ItemsWriter::new(writer, attr)?),
+            Format::Jsonl => {
+                let mut writer = JsonlWriter::new(writer, payload_format);
+                writer.write_attr(attr)?;
+                Self::Jsonl(writer)
+            }
+            Format::Csv => Self::Csv(Box::new(CsvWriter::new(writer)), 0),
+            Format::Parquet => Self::Parquet(Box::new(ParquetItemsWriter::new(
+                writer,
+                &ParquetExportOptions::default(),
+            )?)),
+            Format::Mitmproxy => Self::Mitmproxy(MitmFlowWriter::new(writer)),
+            Format::Warc => Self::Warc(WarcWriter::new(writer)),
+            Format::Har | Format::ZapRaw | Format::Pcap => unreachable!(),
+        })
+    }
+
+    pub fn write_item(&mut self, item: &Item) -> Result<(), CliError> {
+        match self {
+            Self::Xml(x) => x.write_item(item)?,
+            Self::Jsonl(x) => x.write_item(item)?,
+            Self::Csv(x, index) => {
+                x.write_item(*index, item)?;
+                *index += 1;
+            }
+            Self::Parquet(x) => x.write_item(item)?,
+            Self::Mitmproxy(x) => x.write_item(item)?,
+            Self::Warc(x) => x.write_item(item)?,
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<(), CliError> {
+        let mut writer = match self {
+            Self::Xml(mut x) => {
+                x.finish()?;
+                x.into_inner()
+            }
+            Self::Jsonl(x) => x.into_inner(),
+            Self::Csv(x, _) => x.into_inner()?,
+            Self::Parquet(x) => x.finish()?,
+            Self::Mitmproxy(x) => x.into_inner(),
+            Self::Warc(x) => x.into_inner(),
+        };
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl PartWriter for Sink {
+    type Output = ();
+    type Error = CliError;
+
+    fn write_item(&mut self, item: &Item) -> Result<(), Self::Error> {
+        Sink::write_item(self, item)
+    }
+
+    fn finish_part(self) -> Result<Self::Output, Self::Error> {
+        self.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{env, fs};
+
+    use burpsuite_kit::http_history::compression::{CompressionOptions, Encoder};
+
+    #[test]
+    fn test_format() {
+        assert_eq!(Format::from_path(Path::new("a/b.XML")), Some(Format::Xml));
+        assert_eq!(Format::from_path(Path::new("b.pcapng")), Some(Format::Pcap));
+        assert_eq!(Format::from_path(Path::new("b.txt")), None);
+        assert_eq!(
+            Format::from_path(Path::new("a/b.xml.gz")),
+            Some(Format::Xml)
+        );
+        assert_eq!(Format::from_path(Path::new("b.gz")), None);
+        assert!(matches!(
+            Format::resolve(None, Path::new(STDIO_PATH), true),
+            Ok(Format::Xml)
+        ));
+        assert!(matches!(
+            Format::resolve(None, Path::new(STDIO_PATH), false),
+            Err(CliError::FormatUnknown(_))
+        ));
+    }
+
+    #[test]
+    fn test_convert() -> Result<(), Box<dyn std::error::Error>> {
+        let input = Path::new("../tests/http_history_files/burpsuite_community_v2021.3.2.xml");
+        let dir = env::temp_dir().join(format!("burpsuite_kit_cli_{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+
+        for name in ["a.jsonl", "a.warc", "a.flows"] {
+            let path = dir.join(name);
+            let source = read(input, None)?;
+            let attr = source.attr.ok_or("attr missing")?;
+            let mut sink = Sink::create(&path, None, &attr, PayloadFormat::Base64)?;
+            for item in source.items {
+                sink.write_item(&item?)?;
+            }
+            sink.finish()?;
+
+            let items = read(&path, None)?.items.collect::<Result<Vec<_>, _>>()?;
+            assert_eq!(items.len(), 2, "{name}");
+            assert_eq!(items[0].url, "http://httpbin.org/get?foo=bar", "{name}");
+        }
+
+        assert!(matches!(
+            read(&dir.join("a.csv"), None),
+            Err(CliError::FormatUnsupported(_))
+        ));
+
+        for compression in [Compression::Gzip, Compression::Zstd] {
+            let options = CompressionOptions {
+                compression,
+                level: None,
+            };
+            let mut encoder = Encoder::new(vec![], &options)?;
+            encoder.write_all(&fs::read(input)?)?;
+            let path = dir.join(format!("a.xml.{}", compression.extension().unwrap_or("")));
+            fs::write(&path, encoder.finish()?)?;
+            assert_eq!(read(&path, None)?.items.count(), 2);
+            assert_eq!(read(&path, Some(Format::Xml))?.items.count(), 2);
+        }
+
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
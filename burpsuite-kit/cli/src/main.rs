@@ -0,0 +1,776 @@
+/*
+cargo run -p burpsuite-kit-cli -- info burpsuite-kit/tests/http_history_files/burpsuite_community_v2021.3.2.xml
+*/
+
+mod format;
+
+use std::{
+    collections::BTreeMap,
+    io::{self, ErrorKind as IoErrorKind, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use burpsuite_kit::http_history::{
+    item::Item,
+    jsonl::PayloadFormat,
+    merge::{merge_attrs, Dedupe, Deduper, MergedItems, SplitBy, SplitWriter},
+    message::Message,
+};
+use chrono::Duration;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use regex::bytes::RegexBuilder;
+use serde_json::Value;
+
+use crate::format::{default_attr, read, CliError, Format, Sink};
+
+/// Success.
+const EXIT_OK: u8 = 0;
+/// `grep` matched nothing or `validate` found problems.
+const EXIT_NEGATIVE: u8 = 1;
+/// Invalid usage, or reading or writing failed. clap also exits with 2 on usage errors.
+const EXIT_ERROR: u8 = 2;
+
+/// Longest matched line printed by `grep`.
+const GREP_LINE_MAX_LEN: usize = 200;
+
+#[derive(Parser)]
+#[command(
+    name = "burpsuite-kit",
+    version,
+    about = "Inspect and convert Burp Suite HTTP history exports",
+    after_help = "Exit codes: 0 success, 1 no grep match or validation problems, 2 error."
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Export header and item counts.
+    Info {
+        file: PathBuf,
+        #[arg(long, value_enum)]
+        from: Option<Format>,
+    },
+    /// One line per item, numbered from 1 like Burp.
+    List {
+        file: PathBuf,
+        #[arg(long, value_enum)]
+        from: Option<Format>,
+        #[command(flatten)]
+        filter: Filter,
+    },
+    /// Decoded request and response of item N, counting from 1.
+    Show {
+        n: usize,
+        file: PathBuf,
+        #[arg(long, value_enum)]
+        from: Option<Format>,
+        #[arg(long, value_enum, default_value_t = Part::Both)]
+        part: Part,
+        /// Write bytes as captured instead of pretty-printing bodies.
+        #[arg(long)]
+        raw: bool,
+    },
+    /// Convert between formats, picked from the extensions unless given.
+    Convert {
+        input: PathBuf,
+        /// `-` for stdout, which needs `--to`.
+        output: PathBuf,
+        #[arg(long, value_enum)]
+        from: Option<Format>,
+        #[arg(long, value_enum)]
+        to: Option<Format>,
+        /// How JSONL output stores messages.
+        #[arg(long, value_enum, default_value_t = Payload::Base64)]
+        payload: Payload,
+    },
+    /// Search decoded message bodies with a regex.
+    Grep {
+        pattern: String,
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        #[arg(long, value_enum)]
+        from: Option<Format>,
+        #[arg(short = 'i', long)]
+        ignore_case: bool,
+        #[arg(long, value_enum, default_value_t = Part::Both)]
+        part: Part,
+    },
+    /// Split one export into several, by host, time window or in chunks of N items.
+    Split {
+        input: PathBuf,
+        #[arg(long)]
+        out_dir: PathBuf,
+        #[arg(long, value_enum)]
+        from: Option<Format>,
+        #[arg(long, value_enum, default_value_t = Format::Xml)]
+        to: Format,
+        #[arg(
+            long,
+            conflicts_with_all = ["chunk", "window"],
+            required_unless_present_any = ["chunk", "window"]
+        )]
+        by_host: bool,
+        #[arg(long, conflicts_with = "window")]
+        chunk: Option<usize>,
+        /// Window length in seconds, items are expected ordered by time.
+        #[arg(long)]
+        window: Option<u32>,
+    },
+    /// Merge exports into one ordered by time, keeping the latest header.
+    Merge {
+        output: PathBuf,
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+        #[arg(long, value_enum)]
+        from: Option<Format>,
+        #[arg(long, value_enum)]
+        to: Option<Format>,
+        /// Drop items with the same request and response (exact) or the same method,
+        /// URL and request body (near).
+        #[arg(long, value_enum, default_value_t = Duplicates::Keep)]
+        duplicates: Duplicates,
+    },
+    /// Check that every item parses and its messages decode.
+    Validate {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        #[arg(long, value_enum)]
+        from: Option<Format>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Part {
+    Request,
+    Response,
+    Both,
+}
+
+impl Part {
+    fn has_request(self) -> bool {
+        self != Self::Response
+    }
+
+    fn has_response(self) -> bool {
+        self != Self::Request
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Payload {
+    Base64,
+    Parsed,
+}
+
+impl From<Payload> for PayloadFormat {
+    fn from(x: Payload) -> Self {
+        match x {
+            Payload::Base64 => Self::Base64,
+            Payload::Parsed => Self::Parsed,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Duplicates {
+    Keep,
+    Exact,
+    Near,
+}
+
+impl From<Duplicates> for Dedupe {
+    fn from(x: Duplicates) -> Self {
+        match x {
+            Duplicates::Keep => Self::None,
+            Duplicates::Exact => Self::Exact,
+            Duplicates::Near => Self::Near,
+        }
+    }
+}
+
+#[derive(Args)]
+struct Filter {
+    /// Host containing this, case-insensitive.
+    #[arg(long)]
+    host: Option<String>,
+    #[arg(long)]
+    method: Option<String>,
+    /// Exact code like `404`, or class like `4xx`.
+    #[arg(long)]
+    status: Option<String>,
+    /// Burp MIME type label, like `JSON` or `HTML`.
+    #[arg(long)]
+    mimetype: Option<String>,
+    /// URL containing this.
+    #[arg(long)]
+    url: Option<String>,
+}
+
+impl Filter {
+    fn matches(&self, item: &Item) -> bool {
+        if let Some(x) = &self.host {
+            if !item
+                .host
+                .1
+                .to_ascii_lowercase()
+                .contains(&x.to_ascii_lowercase())
+            {
+                return false;
+            }
+        }
+        if let Some(x) = &self.method {
+            if !item.method.as_str().eq_ignore_ascii_case(x) {
+                return false;
+            }
+        }
+        if let Some(x) = &self.status {
+            let status = item.status.as_u16().to_string();
+            let is_match = match x.to_ascii_lowercase().strip_suffix("xx") {
+                Some(class) => status.starts_with(class),
+                None => status == *x,
+            };
+            if !is_match {
+                return false;
+            }
+        }
+        if let Some(x) = &self.mimetype {
+            if !item.mimetype.eq_ignore_ascii_case(x) {
+                return false;
+            }
+        }
+        if let Some(x) = &self.url {
+            if !item.url.contains(x.as_str()) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli.command) {
+        Ok(code) => ExitCode::from(code),
+        // Output piped into `head` and the like.
+        Err(CliError::IoError(err)) if err.kind() == IoErrorKind::BrokenPipe => {
+            ExitCode::from(EXIT_OK)
+        }
+        Err(err) => {
+            eprintln!("burpsuite-kit: {err}");
+            ExitCode::from(EXIT_ERROR)
+        }
+    }
+}
+
+fn run(command: Command) -> Result<u8, CliError> {
+    let mut out = io::stdout().lock();
+    match command {
+        Command::Info { file, from } => info(&mut out, &file, from),
+        Command::List { file, from, filter } => list(&mut out, &file, from, &filter),
+        Command::Show {
+            n,
+            file,
+            from,
+            part,
+            raw,
+        } => show(&mut out, n, &file, from, part, raw),
+        Command::Convert {
+            input,
+            output,
+            from,
+            to,
+            payload,
+        } => {
+            drop(out);
+            merge(&output, &[input], from, to, payload.into(), Dedupe::None)
+        }
+        Command::Grep {
+            pattern,
+            files,
+            from,
+            ignore_case,
+            part,
+        } => grep(&mut out, &pattern, &files, from, ignore_case, part),
+        Command::Split {
+            input,
+            out_dir,
+            from,
+            to,
+            by_host,
+            chunk,
+            window,
+        } => {
+            let by = match (by_host, chunk, window) {
+                (true, _, _) => SplitBy::Host,
+                (_, Some(n), _) => SplitBy::Count(n),
+                (_, _, Some(secs)) => SplitBy::Window(Duration::seconds(secs.into())),
+                _ => SplitBy::Host,
+            };
+            split(&input, &out_dir, from, to, by)
+        }
+        Command::Merge {
+            output,
+            inputs,
+            from,
+            to,
+            duplicates,
+        } => {
+            drop(out);
+            merge(
+                &output,
+                &inputs,
+                from,
+                to,
+                PayloadFormat::Base64,
+                duplicates.into(),
+            )
+        }
+        Command::Validate { files, from } => validate(&mut out, &files, from),
+    }
+}
+
+/// Report an unreadable item on stderr and skip it, keeping the numbering.
+fn warn_item(n: usize, err: &CliError) {
+    eprintln!("burpsuite-kit: item {n}: {err}");
+}
+
+fn info(out: &mut impl Write, file: &Path, from: Option<Format>) -> Result<u8, CliError> {
+    let source = read(file, from)?;
+    match &source.attr {
+        Some(attr) => {
+            writeln!(out, "burp_version: {}", attr.burp_version)?;
+            writeln!(out, "export_time: {}", attr.export_time)?;
+        }
+        None => {
+            writeln!(out, "burp_version: -")?;
+            writeln!(out, "export_time: -")?;
+        }
+    }
+
+    let mut count = 0;
+    let mut invalid = 0;
+    let mut hosts: BTreeMap<String, usize> = BTreeMap::new();
+    let mut methods: BTreeMap<String, usize> = BTreeMap::new();
+    let mut statuses: BTreeMap<String, usize> = BTreeMap::new();
+    for item in source.items {
+        match item {
+            Ok(item) => {
+                count += 1;
+                *hosts.entry(item.host.1.to_owned()).or_default() += 1;
+                *methods.entry(item.method.to_string()).or_default() += 1;
+                *statuses
+                    .entry(format!("{}xx", item.status.as_u16() / 100))
+                    .or_default() += 1;
+            }
+            Err(_) => invalid += 1,
+        }
+    }
+
+    let join = |counts: &BTreeMap<String, usize>| {
+        counts
+            .iter()
+            .map(|(k, v)| format!("{k} {v}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    writeln!(out, "items: {count}")?;
+    writeln!(out, "invalid_items: {invalid}")?;
+    writeln!(out, "hosts: {}", join(&hosts))?;
+    writeln!(out, "methods: {}", join(&methods))?;
+    writeln!(out, "statuses: {}", join(&statuses))?;
+    Ok(EXIT_OK)
+}
+
+fn list(
+    out: &mut impl Write,
+    file: &Path,
+    from: Option<Format>,
+    filter: &Filter,
+) -> Result<u8, CliError> {
+    writeln!(
+        out,
+        "{:>5}  {:<7}  {:>6}  {:>8}  {:<6}  URL",
+        "#", "METHOD", "STATUS", "LENGTH", "MIME"
+    )?;
+    for (i, item) in read(file, from)?.items.enumerate() {
+        let item = match item {
+            Ok(x) => x,
+            Err(err) => {
+                warn_item(i + 1, &err);
+                continue;
+            }
+        };
+        if !filter.matches(&item) {
+            continue;
+        }
+        writeln!(
+            out,
+            "{:>5}  {:<7}  {:>6}  {:>8}  {:<6}  {}",
+            i + 1,
+            item.method,
+            item.status.as_u16(),
+            item.response_length,
+            item.mimetype,
+            item.url
+        )?;
+    }
+    Ok(EXIT_OK)
+}
+
+fn show(
+    out: &mut impl Write,
+    n: usize,
+    file: &Path,
+    from: Option<Format>,
+    part: Part,
+    raw: bool,
+) -> Result<u8, CliError> {
+    let item = read(file, from)?
+        .items
+        .nth(n.checked_sub(1).ok_or(CliError::ItemNotFound(n))?)
+        .ok_or(CliError::ItemNotFound(n))??;
+
+    let mut messages = vec![];
+    if part.has_request() {
+        messages.push(item.request_bytes()?);
+    }
+    if part.has_response() {
+        messages.push(item.response_bytes()?);
+    }
+    for (i, bytes) in messages.iter().enumerate() {
+        if i > 0 {
+            writeln!(out)?;
+        }
+        if raw {
+            out.write_all(bytes)?;
+        } else {
+            write_pretty(out, bytes)?;
+        }
+    }
+    Ok(EXIT_OK)
+}
+
+/// Head as text, JSON bodies indented, binary bodies summarized.
+fn write_pretty(out: &mut impl Write, bytes: &[u8]) -> Result<(), CliError> {
+    let message = match Message::parse(bytes) {
+        Ok(x) => x,
+        Err(_) => {
+            writeln!(out, "{}", String::from_utf8_lossy(bytes))?;
+            return Ok(());
+        }
+    };
+    write!(
+        out,
+        "{}",
+        String::from_utf8_lossy(&bytes[..message.body_offset])
+    )?;
+
+    let body = message.body;
+    if body.is_empty() {
+        return Ok(());
+    }
+    if let Ok(json) = serde_json::from_slice::<Value>(body) {
+        let pretty = serde_json::to_string_pretty(&json).map_err(io::Error::from)?;
+        writeln!(out, "{pretty}")?;
+    } else if let Ok(text) = std::str::from_utf8(body) {
+        writeln!(out, "{text}")?;
+    } else {
+        writeln!(out, "[{} bytes of binary body]", body.len())?;
+    }
+    Ok(())
+}
+
+fn grep(
+    out: &mut impl Write,
+    pattern: &str,
+    files: &[PathBuf],
+    from: Option<Format>,
+    ignore_case: bool,
+    part: Part,
+) -> Result<u8, CliError> {
+    let re = RegexBuilder::new(pattern)
+        .case_insensitive(ignore_case)
+        .build()?;
+    let mut is_matched = false;
+
+    for file in files {
+        for (i, item) in read(file, from)?.items.enumerate() {
+            let item = match item {
+                Ok(x) => x,
+                Err(err) => {
+                    warn_item(i + 1, &err);
+                    continue;
+                }
+            };
+
+            let mut messages = vec![];
+            if part.has_request() {
+                messages.push(("request", item.request_bytes()));
+            }
+            if part.has_response() {
+                messages.push(("response", item.response_bytes()));
+            }
+            for (name, bytes) in messages {
+                let bytes = match bytes {
+                    Ok(x) => x,
+                    Err(err) => {
+                        warn_item(i + 1, &err.into());
+                        continue;
+                    }
+                };
+                let body = match Message::parse(&bytes) {
+                    Ok(x) => x.body,
+                    Err(_) => continue,
+                };
+                for line in body.split(|b| *b == b'\n') {
+                    if !re.is_match(line) {
+                        continue;
+                    }
+                    is_matched = true;
+                    let line = String::from_utf8_lossy(line);
+                    let line: String = line.trim().chars().take(GREP_LINE_MAX_LEN).collect();
+                    writeln!(
+                        out,
+                        "{}:{}: {name} {} {}: {line}",
+                        file.display(),
+                        i + 1,
+                        item.method,
+                        item.url
+                    )?;
+                }
+            }
+        }
+    }
+
+    Ok(if is_matched { EXIT_OK } else { EXIT_NEGATIVE })
+}
+
+fn split(
+    input: &Path,
+    out_dir: &Path,
+    from: Option<Format>,
+    to: Format,
+    by: SplitBy,
+) -> Result<u8, CliError> {
+    let source = read(input, from)?;
+    let attr = source.attr.unwrap_or_else(default_attr);
+    let stem = format::uncompressed_path(input)
+        .file_stem()
+        .and_then(|x| x.to_str())
+        .filter(|x| *x != format::STDIO_PATH)
+        .unwrap_or("items");
+    std::fs::create_dir_all(out_dir)?;
+
+    let mut writer = SplitWriter::new(by, |key: &str| {
+        let path = out_dir.join(format!("{stem}_{key}.{}", to.extension()));
+        Sink::create(&path, Some(to), &attr, PayloadFormat::Base64)
+    });
+    for item in source.items {
+        writer.write_item(&item?)?;
+    }
+    writer.finish()?;
+    Ok(EXIT_OK)
+}
+
+/// Write the items of all `inputs` to `output` ordered by time, also used by `convert`.
+fn merge(
+    output: &Path,
+    inputs: &[PathBuf],
+    from: Option<Format>,
+    to: Option<Format>,
+    payload_format: PayloadFormat,
+    dedupe: Dedupe,
+) -> Result<u8, CliError> {
+    let sources = inputs
+        .iter()
+        .map(|x| read(x, from))
+        .collect::<Result<Vec<_>, _>>()?;
+    let attr =
+        merge_attrs(sources.iter().filter_map(|x| x.attr.as_ref())).unwrap_or_else(default_attr);
+
+    let mut sink = Sink::create(output, to, &attr, payload_format)?;
+    let mut deduper = Deduper::new(dedupe);
+    for item in MergedItems::new(sources.into_iter().map(|x| x.items)) {
+        let item = item?;
+        if !deduper.is_duplicate(&item) {
+            sink.write_item(&item)?;
+        }
+    }
+    sink.finish()?;
+    if deduper.dropped > 0 {
+        eprintln!("burpsuite-kit: {} duplicate items dropped", deduper.dropped);
+    }
+    Ok(EXIT_OK)
+}
+
+fn validate(out: &mut impl Write, files: &[PathBuf], from: Option<Format>) -> Result<u8, CliError> {
+    let mut problems_total = 0;
+    for file in files {
+        let source = match read(file, from) {
+            Ok(x) => x,
+            Err(err) => {
+                writeln!(out, "{}: {err}", file.display())?;
+                problems_total += 1;
+                continue;
+            }
+        };
+
+        let mut count = 0;
+        let mut problems = 0;
+        for (i, item) in source.items.enumerate() {
+            count += 1;
+            for problem in item_problems(item) {
+                writeln!(out, "{}: item {}: {problem}", file.display(), i + 1)?;
+                problems += 1;
+            }
+        }
+        writeln!(
+            out,
+            "{}: {count} items, {problems} problems",
+            file.display()
+        )?;
+        problems_total += problems;
+    }
+    Ok(if problems_total == 0 {
+        EXIT_OK
+    } else {
+        EXIT_NEGATIVE
+    })
+}
+
+fn item_problems(item: Result<Item, CliError>) -> Vec<String> {
+    let item = match item {
+        Ok(x) => x,
+        Err(err) => return vec![err.to_string()],
+    };
+
+    let mut problems = vec![];
+    match item.request_bytes() {
+        Ok(bytes) => {
+            if let Err(err) = Message::parse(&bytes) {
+                problems.push(format!("request {err}"));
+            }
+        }
+        Err(err) => problems.push(format!("request {err}")),
+    }
+    match item.response_bytes() {
+        Ok(bytes) => {
+            if !bytes.is_empty() {
+                if let Err(err) = Message::parse(&bytes) {
+                    problems.push(format!("response {err}"));
+                }
+            }
+            if bytes.len() != item.response_length as usize {
+                problems.push(format!(
+                    "responselength {} but response has {} bytes",
+                    item.response_length,
+                    bytes.len()
+                ));
+            }
+        }
+        Err(err) => problems.push(format!("response {err}")),
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = "../tests/http_history_files/burpsuite_community_v2021.3.2.xml";
+
+    #[test]
+    fn test_commands() -> Result<(), Box<dyn std::error::Error>> {
+        let mut out = vec![];
+        assert_eq!(info(&mut out, Path::new(FILE), None)?, EXIT_OK);
+        let text = String::from_utf8(out)?;
+        assert!(text.contains("burp_version: 2021.3.2\n"));
+        assert!(text.contains("items: 2\n"));
+        assert!(text.contains("methods: GET 1, POST 1\n"));
+
+        let mut out = vec![];
+        let filter = Filter {
+            host: None,
+            method: Some("post".to_owned()),
+            status: Some("2xx".to_owned()),
+            mimetype: None,
+            url: None,
+        };
+        list(&mut out, Path::new(FILE), None, &filter)?;
+        let text = String::from_utf8(out)?;
+        assert_eq!(text.lines().count(), 2);
+        assert!(text
+            .lines()
+            .nth(1)
+            .unwrap_or_default()
+            .starts_with("    2  POST"));
+
+        let mut out = vec![];
+        show(&mut out, 1, Path::new(FILE), None, Part::Request, false)?;
+        assert!(String::from_utf8(out)?.starts_with("GET /get?foo=bar HTTP/1.1\r\n"));
+        assert!(matches!(
+            show(&mut vec![], 9, Path::new(FILE), None, Part::Both, false),
+            Err(CliError::ItemNotFound(9))
+        ));
+
+        let files = [PathBuf::from(FILE)];
+        let mut out = vec![];
+        assert_eq!(
+            grep(&mut out, "GUNICORN", &files, None, true, Part::Both)?,
+            EXIT_NEGATIVE
+        );
+        assert_eq!(
+            grep(&mut out, "\"foo\"", &files, None, false, Part::Response)?,
+            EXIT_OK
+        );
+        assert!(
+            String::from_utf8(out)?.contains(":1: response GET http://httpbin.org/get?foo=bar:")
+        );
+
+        let mut out = vec![];
+        validate(&mut out, &files, None)?;
+        assert!(String::from_utf8(out)?.ends_with("2 items, 0 problems\n"));
+
+        let dir =
+            std::env::temp_dir().join(format!("burpsuite_kit_cli_merge_{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let output = dir.join("merged.xml");
+        let inputs = [PathBuf::from(FILE), PathBuf::from(FILE)];
+        for (dedupe, len) in [(Dedupe::None, 4), (Dedupe::Exact, 2)] {
+            merge(&output, &inputs, None, None, PayloadFormat::Base64, dedupe)?;
+            assert_eq!(read(&output, None)?.items.count(), len);
+        }
+        split(&output, &dir, None, Format::Xml, SplitBy::Count(1))?;
+        assert_eq!(read(&dir.join("merged_0002.xml"), None)?.items.count(), 1);
+        std::fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_corrupt_item() -> Result<(), Box<dyn std::error::Error>> {
+        let xml = std::fs::read_to_string(FILE)?.replacen("<port>80</port>", "<port>abc</port>", 1);
+        let path = std::env::temp_dir().join(format!(
+            "burpsuite_kit_cli_corrupt_{}.xml",
+            std::process::id()
+        ));
+        std::fs::write(&path, xml)?;
+
+        let mut out = vec![];
+        assert_eq!(validate(&mut out, &[path.to_owned()], None)?, EXIT_NEGATIVE);
+        assert!(String::from_utf8(out)?.ends_with("2 items, 1 problems\n"));
+
+        let mut out = vec![];
+        info(&mut out, &path, None)?;
+        let text = String::from_utf8(out)?;
+        assert!(text.contains("items: 1\ninvalid_items: 1\n"));
+        assert!(text.contains("methods: POST 1\n"));
+        std::fs::remove_file(&path)?;
+
+        Ok(())
+    }
+}
@@ -5,7 +5,7 @@ RUST_LOG=debug cargo run -p burpsuite-kit-demo --bin burpsuite_kit_demo_http_his
 use std::{
     env, error,
     fs::File,
-    io::{BufReader, Cursor, Error as IoError, ErrorKind as IoErrorKind},
+    io::{BufReader, Cursor, Error as IoError},
     path::PathBuf,
     str,
 };
@@ -49,9 +49,7 @@ fn main() -> Result<(), Box<dyn error::Error>> {
                 if item.status.is_success() {
                     Ok(item)
                 } else {
-                    Err(ItemParseError::XmlError(
-                        IoError::new(IoErrorKind::Other, "").into(),
-                    ))
+                    Err(ItemParseError::XmlError(IoError::other("").into()))
                 }
             })
             .ok()
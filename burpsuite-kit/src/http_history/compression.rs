@@ -0,0 +1,341 @@
+use std::{
+    io::{BufRead, Cursor, Error as IoError, Read as _, Write},
+    path::Path,
+};
+
+#[cfg(any(feature = "gzip", feature = "zstd", feature = "bzip2", feature = "xz"))]
+use std::io::BufReader;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+const BZIP2_MAGIC: &[u8] = b"BZh";
+const XZ_MAGIC: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+
+/// Longest magic number.
+const MAGIC_LEN: usize = 6;
+
+#[derive(thiserror::Error, Debug)]
+pub enum CompressionError {
+    #[error("IoError {0:?}")]
+    IoError(#[from] IoError),
+    #[error("FeatureDisabled {0:?}")]
+    FeatureDisabled(Compression),
+    #[error("LevelInvalid {0:?} {1}")]
+    LevelInvalid(Compression, u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+    Bzip2,
+    Xz,
+}
+
+impl Compression {
+    /// Compression of a stream starting with `bytes`.
+    pub fn detect(bytes: &[u8]) -> Self {
+        if bytes.starts_with(GZIP_MAGIC) {
+            Self::Gzip
+        } else if bytes.starts_with(ZSTD_MAGIC) {
+            Self::Zstd
+        } else if bytes.starts_with(BZIP2_MAGIC) {
+            Self::Bzip2
+        } else if bytes.starts_with(XZ_MAGIC) {
+            Self::Xz
+        } else {
+            Self::None
+        }
+    }
+
+    /// Whether `level` is accepted by the codec, gzip and xz 0-9, bzip2 1-9 and
+    /// zstd per `zstd::compression_level_range`.
+    pub fn is_level_valid(&self, level: u32) -> bool {
+        match self {
+            Self::None => true,
+            Self::Gzip | Self::Xz => level <= 9,
+            Self::Bzip2 => (1..=9).contains(&level),
+            #[cfg(feature = "zstd")]
+            Self::Zstd => i32::try_from(level)
+                .map(|x| zstd::compression_level_range().contains(&x))
+                .unwrap_or(false),
+            #[cfg(not(feature = "zstd"))]
+            Self::Zstd => true,
+        }
+    }
+
+    /// Compression named by the extension of `path`, such as `history.xml.gz`.
+    pub fn from_path(path: impl AsRef<Path>) -> Self {
+        match path.as_ref().extension().and_then(|x| x.to_str()) {
+            Some("gz") => Self::Gzip,
+            Some("zst") => Self::Zstd,
+            Some("bz2") => Self::Bzip2,
+            Some("xz") => Self::Xz,
+            _ => Self::None,
+        }
+    }
+
+    pub fn extension(&self) -> Option<&'static str> {
+        match self {
+            Self::None => None,
+            Self::Gzip => Some("gz"),
+            Self::Zstd => Some("zst"),
+            Self::Bzip2 => Some("bz2"),
+            Self::Xz => Some("xz"),
+        }
+    }
+}
+
+/// Wrap `reader` in the decoder its magic bytes call for, or return it as is.
+pub fn decompress<'a, R>(mut reader: R) -> Result<Box<dyn BufRead + Send + 'a>, CompressionError>
+where
+    R: BufRead + Send + 'a,
+{
+    let mut head = Vec::with_capacity(MAGIC_LEN);
+    (&mut reader)
+        .take(MAGIC_LEN as u64)
+        .read_to_end(&mut head)?;
+    let compression = Compression::detect(&head);
+    let reader = Cursor::new(head).chain(reader);
+
+    match compression {
+        Compression::None => Ok(Box::new(reader)),
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => Ok(Box::new(BufReader::new(
+            flate2::bufread::MultiGzDecoder::new(reader),
+        ))),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => Ok(Box::new(BufReader::new(zstd::Decoder::with_buffer(
+            reader,
+        )?))),
+        #[cfg(feature = "bzip2")]
+        Compression::Bzip2 => Ok(Box::new(BufReader::new(
+            bzip2::bufread::MultiBzDecoder::new(reader),
+        ))),
+        #[cfg(feature = "xz")]
+        Compression::Xz => Ok(Box::new(BufReader::new(
+            xz2::bufread::XzDecoder::new_multi_decoder(reader),
+        ))),
+        #[allow(unreachable_patterns)]
+        x => Err(CompressionError::FeatureDisabled(x)),
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CompressionOptions {
+    pub compression: Compression,
+    /// Level of the codec, its default when unset.
+    pub level: Option<u32>,
+}
+
+/// Writer compressing its output as set by [`CompressionOptions`].
+pub enum Encoder<W>
+where
+    W: Write,
+{
+    None(W),
+    #[cfg(feature = "gzip")]
+    Gzip(flate2::write::GzEncoder<W>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::Encoder<'static, W>),
+    #[cfg(feature = "bzip2")]
+    Bzip2(bzip2::write::BzEncoder<W>),
+    #[cfg(feature = "xz")]
+    Xz(xz2::write::XzEncoder<W>),
+}
+
+impl<W> Encoder<W>
+where
+    W: Write,
+{
+    pub fn new(writer: W, options: &CompressionOptions) -> Result<Self, CompressionError> {
+        #[allow(unused_variables)]
+        let level = options.level;
+        if let Some(x) = level {
+            if !options.compression.is_level_valid(x) {
+                return Err(CompressionError::LevelInvalid(options.compression, x));
+            }
+        }
+        match options.compression {
+            Compression::None => Ok(Self::None(writer)),
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => Ok(Self::Gzip(flate2::write::GzEncoder::new(
+                writer,
+                level.map(flate2::Compression::new).unwrap_or_default(),
+            ))),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Ok(Self::Zstd(zstd::Encoder::new(
+                writer,
+                // 0 is the zstd default.
+                level.map(|x| x as i32).unwrap_or(0),
+            )?)),
+            #[cfg(feature = "bzip2")]
+            Compression::Bzip2 => Ok(Self::Bzip2(bzip2::write::BzEncoder::new(
+                writer,
+                level.map(bzip2::Compression::new).unwrap_or_default(),
+            ))),
+            #[cfg(feature = "xz")]
+            Compression::Xz => Ok(Self::Xz(xz2::write::XzEncoder::new(
+                writer,
+                level.unwrap_or(6),
+            ))),
+            #[allow(unreachable_patterns)]
+            x => Err(CompressionError::FeatureDisabled(x)),
+        }
+    }
+
+    /// Write the end of the compressed stream and return the inner writer.
+    pub fn finish(self) -> Result<W, CompressionError> {
+        match self {
+            Self::None(x) => Ok(x),
+            #[cfg(feature = "gzip")]
+            Self::Gzip(x) => Ok(x.finish()?),
+            #[cfg(feature = "zstd")]
+            Self::Zstd(x) => Ok(x.finish()?),
+            #[cfg(feature = "bzip2")]
+            Self::Bzip2(x) => Ok(x.finish()?),
+            #[cfg(feature = "xz")]
+            Self::Xz(x) => Ok(x.finish()?),
+        }
+    }
+}
+
+impl<W> Write for Encoder<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize, IoError> {
+        match self {
+            Self::None(x) => x.write(buf),
+            #[cfg(feature = "gzip")]
+            Self::Gzip(x) => x.write(buf),
+            #[cfg(feature = "zstd")]
+            Self::Zstd(x) => x.write(buf),
+            #[cfg(feature = "bzip2")]
+            Self::Bzip2(x) => x.write(buf),
+            #[cfg(feature = "xz")]
+            Self::Xz(x) => x.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), IoError> {
+        match self {
+            Self::None(x) => x.flush(),
+            #[cfg(feature = "gzip")]
+            Self::Gzip(x) => x.flush(),
+            #[cfg(feature = "zstd")]
+            Self::Zstd(x) => x.flush(),
+            #[cfg(feature = "bzip2")]
+            Self::Bzip2(x) => x.flush(),
+            #[cfg(feature = "xz")]
+            Self::Xz(x) => x.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{env, fs};
+
+    use crate::http_history::{items::Items, writer::ItemsWriter};
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(Compression::detect(b"\x1f\x8b\x08"), Compression::Gzip);
+        assert_eq!(Compression::detect(b"\x28\xb5\x2f\xfd"), Compression::Zstd);
+        assert_eq!(Compression::detect(b"BZh9"), Compression::Bzip2);
+        assert_eq!(Compression::detect(b"\xfd7zXZ\x00"), Compression::Xz);
+        assert_eq!(Compression::detect(b"<?xml"), Compression::None);
+        assert_eq!(Compression::detect(b""), Compression::None);
+        assert_eq!(Compression::from_path("a.xml.zst"), Compression::Zstd);
+        assert_eq!(Compression::from_path("a.xml"), Compression::None);
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+        let bytes = fs::read("tests/http_history_files/burpsuite_community_v2021.3.2.xml")?;
+        let items = Items::from_reader(&bytes[..])?;
+        let attr = items.attr.to_owned();
+        let items = items.collect::<Result<Vec<_>, _>>()?;
+
+        for compression in [
+            Compression::None,
+            #[cfg(feature = "gzip")]
+            Compression::Gzip,
+            #[cfg(feature = "zstd")]
+            Compression::Zstd,
+            #[cfg(feature = "bzip2")]
+            Compression::Bzip2,
+            #[cfg(feature = "xz")]
+            Compression::Xz,
+        ] {
+            let options = CompressionOptions {
+                compression,
+                level: None,
+            };
+            let mut writer = ItemsWriter::with_compression(vec![], &attr, &options)?;
+            for item in items.iter() {
+                writer.write_item(item)?;
+            }
+            let compressed = writer.finish_compressed()?;
+            assert_eq!(Compression::detect(&compressed), compression);
+
+            let path = env::temp_dir().join(format!(
+                "burpsuite_kit_compression_{}.xml.{}",
+                std::process::id(),
+                compression.extension().unwrap_or("raw")
+            ));
+            fs::write(&path, &compressed)?;
+            let written = Items::from_path(&path)?.collect::<Result<Vec<_>, _>>()?;
+            fs::remove_file(&path)?;
+            assert_eq!(written.len(), items.len());
+            assert_eq!(written[1].response.1, items[1].response.1);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_level_invalid() {
+        for (compression, level) in [
+            (Compression::Gzip, 10),
+            (Compression::Bzip2, 0),
+            (Compression::Bzip2, 10),
+            (Compression::Xz, 10),
+            #[cfg(feature = "zstd")]
+            (Compression::Zstd, 1000),
+        ] {
+            let options = CompressionOptions {
+                compression,
+                level: Some(level),
+            };
+            assert!(matches!(
+                Encoder::new(vec![], &options),
+                Err(CompressionError::LevelInvalid(x, y)) if x == compression && y == level
+            ));
+        }
+        assert!(Compression::Bzip2.is_level_valid(9));
+        assert!(Compression::Xz.is_level_valid(0));
+    }
+
+    #[cfg(not(feature = "xz"))]
+    #[test]
+    fn test_feature_disabled() {
+        let options = CompressionOptions {
+            compression: Compression::Xz,
+            level: None,
+        };
+        assert!(matches!(
+            Encoder::new(vec![], &options),
+            Err(CompressionError::FeatureDisabled(Compression::Xz))
+        ));
+        assert!(matches!(
+            decompress(&b"\xfd7zXZ\x00"[..]),
+            Err(CompressionError::FeatureDisabled(Compression::Xz))
+        ));
+    }
+}
@@ -0,0 +1,240 @@
+use std::io::Write;
+
+use http::uri::Scheme;
+use once_cell::sync::Lazy;
+use regex::bytes::Regex;
+use strum::{Display, EnumIter, EnumString, IntoEnumIterator as _};
+
+use super::{item::Item, message::Message};
+
+/// Format of the Time column in Burp's HTTP history table.
+const TIME_FORMAT: &str = "%T %d %b %Y";
+
+const CHECK_MARK: &str = "\u{2714}";
+
+static TITLE_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)<title[^>]*>(.*?)</title>").expect("invalid regex"));
+
+/// Columns of Burp's HTTP history table, in Burp's default order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString, EnumIter)]
+pub enum Column {
+    #[strum(serialize = "#")]
+    Number,
+    Host,
+    Method,
+    #[strum(serialize = "URL")]
+    Url,
+    Params,
+    /// Burp does not export whether a request was edited, always empty.
+    Edited,
+    Status,
+    Length,
+    #[strum(serialize = "MIME type")]
+    MimeType,
+    Extension,
+    Title,
+    Comment,
+    #[strum(serialize = "TLS")]
+    Tls,
+    #[strum(serialize = "IP")]
+    Ip,
+    Cookies,
+    Time,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum CsvExportError {
+    #[error("CsvError {0}")]
+    CsvError(#[from] ::csv::Error),
+}
+
+/// Writes one CSV row per item with the selected columns, header first.
+pub struct CsvWriter<W>
+where
+    W: Write,
+{
+    writer: ::csv::Writer<W>,
+    columns: Vec<Column>,
+    is_header_written: bool,
+}
+
+impl<W> CsvWriter<W>
+where
+    W: Write,
+{
+    /// All columns in Burp's default order.
+    pub fn new(writer: W) -> Self {
+        Self::with_columns(writer, Column::iter().collect())
+    }
+
+    pub fn with_columns(writer: W, columns: Vec<Column>) -> Self {
+        Self {
+            writer: ::csv::Writer::from_writer(writer),
+            columns,
+            is_header_written: false,
+        }
+    }
+
+    /// `item_index` is zero-based, the `#` column counts from 1 like Burp.
+    pub fn write_item(&mut self, item_index: usize, item: &Item) -> Result<(), CsvExportError> {
+        self.write_header()?;
+
+        let request = item.request_bytes().ok();
+        let response = item.response_bytes().ok();
+        let row = Row {
+            request: request.as_deref().and_then(|x| Message::parse(x).ok()),
+            response: response.as_deref().and_then(|x| Message::parse(x).ok()),
+        };
+        self.writer.write_record(
+            self.columns
+                .iter()
+                .map(|column| row.value(*column, item_index, item)),
+        )?;
+        Ok(())
+    }
+
+    /// Also writes the header when there were no items.
+    pub fn into_inner(mut self) -> Result<W, CsvExportError> {
+        self.write_header()?;
+        self.writer.flush().map_err(::csv::Error::from)?;
+        self.writer
+            .into_inner()
+            .map_err(|err| CsvExportError::CsvError(err.into_error().into()))
+    }
+
+    fn write_header(&mut self) -> Result<(), CsvExportError> {
+        if !self.is_header_written {
+            self.writer
+                .write_record(self.columns.iter().map(|x| x.to_string()))?;
+            self.is_header_written = true;
+        }
+        Ok(())
+    }
+}
+
+/// Decoded messages of an item, parsed once per row.
+struct Row<'a> {
+    request: Option<Message<'a>>,
+    response: Option<Message<'a>>,
+}
+
+impl Row<'_> {
+    fn value(&self, column: Column, item_index: usize, item: &Item) -> String {
+        let check = |x: bool| if x { CHECK_MARK } else { "" }.to_owned();
+        match column {
+            Column::Number => (item_index + 1).to_string(),
+            Column::Host => {
+                let default_port = (item.protocol == Scheme::HTTP && item.port == 80)
+                    || (item.protocol == Scheme::HTTPS && item.port == 443);
+                if default_port {
+                    format!("{}://{}", item.protocol, item.host.1)
+                } else {
+                    format!("{}://{}:{}", item.protocol, item.host.1, item.port)
+                }
+            }
+            Column::Method => item.method.to_string(),
+            Column::Url => item.path.to_owned(),
+            Column::Params => check(self.has_params(item)),
+            Column::Edited => String::new(),
+            Column::Status => item.status.as_u16().to_string(),
+            Column::Length => item.response_length.to_string(),
+            Column::MimeType => item.mimetype.to_owned(),
+            Column::Extension => item.extension.to_owned().unwrap_or_default(),
+            Column::Title => self.title().unwrap_or_default(),
+            Column::Comment => item.comment.to_owned().unwrap_or_default(),
+            Column::Tls => check(item.protocol == Scheme::HTTPS),
+            Column::Ip => String::from_utf8_lossy(&item.host.0.ip).into_owned(),
+            Column::Cookies => self.cookies(),
+            Column::Time => item.time.format(TIME_FORMAT).to_string(),
+        }
+    }
+
+    /// URL query or request body parameters, as Burp marks them.
+    fn has_params(&self, item: &Item) -> bool {
+        if item.path.split_once('?').map(|(_, x)| !x.is_empty()) == Some(true) {
+            return true;
+        }
+        self.request
+            .as_ref()
+            .map(|x| !x.body.is_empty())
+            .unwrap_or_default()
+    }
+
+    fn title(&self) -> Option<String> {
+        let message = self.response.as_ref()?;
+        let title = TITLE_RE.captures(message.body)?.get(1)?;
+        Some(
+            String::from_utf8_lossy(title.as_bytes())
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" "),
+        )
+    }
+
+    /// Cookies set by the response, `name=value` joined by `; `.
+    fn cookies(&self) -> String {
+        let message = match self.response.as_ref() {
+            Some(x) => x,
+            None => return String::new(),
+        };
+        message
+            .headers_all("set-cookie")
+            .map(|x| {
+                let cookie = String::from_utf8_lossy(x);
+                cookie
+                    .split(';')
+                    .next()
+                    .unwrap_or_default()
+                    .trim()
+                    .to_owned()
+            })
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{fs::File, io::BufReader};
+
+    use crate::http_history::items::Items;
+
+    #[test]
+    fn test_write() -> Result<(), Box<dyn std::error::Error>> {
+        let file = File::open("tests/http_history_files/burpsuite_community_v2021.3.2.xml")?;
+        let mut writer = CsvWriter::new(vec![]);
+        for (i, item) in Items::from_reader(BufReader::new(file))?.enumerate() {
+            writer.write_item(i, &item?)?;
+        }
+        let csv = String::from_utf8(writer.into_inner()?)?;
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[0],
+            "#,Host,Method,URL,Params,Edited,Status,Length,MIME type,Extension,Title,Comment,TLS,IP,Cookies,Time"
+        );
+        assert!(lines[1].starts_with("1,http://httpbin.org,GET,/get?foo=bar,\u{2714},,200,"));
+        assert!(lines[2].starts_with("2,https://httpbin.org,POST,/post,\u{2714},,200,"));
+        assert!(lines[2].contains(",\u{2714},34.199.75.4,,"));
+
+        let mut item = Item::default();
+        item.set_response_bytes(
+            "HTTP/1.1 200 OK\r\nSet-Cookie: a=1; HttpOnly\r\nSet-Cookie: b=2\r\n\r\n<html><TITLE>\n Hello,\n world </TITLE>",
+        );
+        let mut writer =
+            CsvWriter::with_columns(vec![], vec![Column::Cookies, Column::Title, Column::Number]);
+        writer.write_item(9, &item)?;
+        assert_eq!(
+            String::from_utf8(writer.into_inner()?)?,
+            "Cookies,Title,#\na=1; b=2,\"Hello, world\",10\n"
+        );
+
+        let writer = CsvWriter::with_columns(vec![], vec![Column::Number, Column::Host]);
+        assert_eq!(String::from_utf8(writer.into_inner()?)?, "#,Host\n");
+
+        assert_eq!("MIME type".parse::<Column>()?, Column::MimeType);
+
+        Ok(())
+    }
+}
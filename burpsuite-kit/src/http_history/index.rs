@@ -0,0 +1,696 @@
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::{BufRead, BufReader, BufWriter, Error as IoError, ErrorKind, Read, Seek, SeekFrom, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    str::FromStr as _,
+};
+
+use chrono::{DateTime, NaiveDateTime};
+use http::{Method, StatusCode};
+
+use super::{
+    item::Item,
+    items::{ItemParseError, Items, ItemsAttr, ItemsParseError},
+};
+
+/// First line of a sidecar index file.
+pub const INDEX_MAGIC: &str = "burpsuite-kit-index 2";
+/// Appended to the export path to name its sidecar index file.
+pub const INDEX_EXTENSION: &str = "idx";
+
+const ITEMS_START: &[u8] = b"<items";
+const ITEMS_END: &[u8] = b"</items>";
+const ITEM_START: &[u8] = b"<item>";
+const ITEM_END: &[u8] = b"</item>";
+const CDATA_START: &[u8] = b"<![CDATA[";
+const CDATA_END: &[u8] = b"]]>";
+const COMMENT_START: &[u8] = b"<!--";
+const COMMENT_END: &[u8] = b"-->";
+
+/// Longest markup the scanner needs to see at once.
+const LOOKAHEAD: usize = 9;
+
+/// Longer item ranges are treated as a corrupt index.
+const MAX_ITEM_LEN: u64 = 1024 * 1024 * 1024;
+
+/// Bytes from the start and from the end of the export hashed into its fingerprint.
+const FINGERPRINT_LEN: u64 = 1024;
+
+#[derive(thiserror::Error, Debug)]
+pub enum IndexError {
+    #[error("IoError {0:?}")]
+    IoError(#[from] IoError),
+    #[error("ItemsParseError {0}")]
+    ItemsParseError(#[from] ItemsParseError),
+    #[error("ItemParseError {0}")]
+    ItemParseError(#[from] ItemParseError),
+    #[error("ItemsTagMissing")]
+    ItemsTagMissing,
+    #[error("ItemMissing {0}")]
+    ItemMissing(usize),
+    #[error("IndexInvalid {0}")]
+    IndexInvalid(String),
+    #[error("IndexStale")]
+    IndexStale,
+}
+
+//
+/// Byte range of every `<item>` element of a Burp XML export, so single items can be
+/// parsed on demand instead of iterating the whole document.
+///
+/// Found by scanning markup only, `<item>` inside CDATA sections or comments is ignored.
+#[derive(Debug, Clone)]
+pub struct ItemsIndex {
+    pub attr: ItemsAttr,
+    /// Document up to and including the `<items>` start tag.
+    pub header: Vec<u8>,
+    pub ranges: Vec<Range<u64>>,
+}
+
+impl ItemsIndex {
+    pub fn build<R>(mut reader: R) -> Result<Self, IndexError>
+    where
+        R: BufRead,
+    {
+        let header = read_header(&mut reader)?;
+        let attr = Items::from_reader(&header[..])?.attr;
+
+        let mut scanner = Scanner::default();
+        let mut buf = vec![];
+        let mut base = header.len() as u64;
+        loop {
+            let chunk = reader.fill_buf()?;
+            let is_eof = chunk.is_empty();
+            buf.extend_from_slice(chunk);
+            let n = chunk.len();
+            reader.consume(n);
+
+            let consumed = scanner.scan(&buf, base, is_eof);
+            buf.drain(..consumed);
+            base += consumed as u64;
+            if is_eof {
+                break;
+            }
+        }
+
+        Ok(Self {
+            attr,
+            header,
+            ranges: scanner.ranges,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Parse the bytes of one `<item>` element.
+    pub fn parse_item(&self, bytes: &[u8]) -> Result<Item, IndexError> {
+        parse_item(&self.header, bytes)
+    }
+
+    /// Index from known item ranges, only the header is read from `reader`.
+    pub fn from_ranges<R>(mut reader: R, ranges: Vec<Range<u64>>) -> Result<Self, IndexError>
+    where
+        R: BufRead,
+    {
+        let header = read_header(&mut reader)?;
+        let attr = Items::from_reader(&header[..])?.attr;
+        Ok(Self {
+            attr,
+            header,
+            ranges,
+        })
+    }
+
+    /// Seek to item `n` of the indexed document and parse it.
+    pub fn read_item<R>(&self, reader: &mut R, n: usize) -> Result<Item, IndexError>
+    where
+        R: Read + Seek,
+    {
+        let range = self.ranges.get(n).ok_or(IndexError::ItemMissing(n))?;
+        let len = range
+            .end
+            .checked_sub(range.start)
+            .filter(|x| *x <= MAX_ITEM_LEN)
+            .ok_or_else(|| IndexError::IndexInvalid(format!("item {n} range {range:?}")))?;
+        reader.seek(SeekFrom::Start(range.start))?;
+        let mut bytes = vec![0; len as usize];
+        reader.read_exact(&mut bytes)?;
+        self.parse_item(&bytes)
+    }
+}
+
+/// The first item of `bytes`, parsed as a document after `header`.
+pub(crate) fn parse_item(header: &[u8], bytes: &[u8]) -> Result<Item, IndexError> {
+    match parse_items(header, bytes)?.next() {
+        Some(item) => Ok(item?),
+        None => Err(IndexError::ItemParseError(ItemParseError::UnexpectedEof)),
+    }
+}
+
+/// Items of a run of `<item>` elements, parsed as a document after `header`.
+pub(crate) fn parse_items<'a>(
+    header: &'a [u8],
+    bytes: &'a [u8],
+) -> Result<Items<impl BufRead + 'a>, IndexError> {
+    let document = header.chain(bytes).chain(ITEMS_END);
+    Ok(Items::from_reader(BufReader::new(document))?)
+}
+
+/// Bytes up to the end of the `<items ...>` start tag.
+pub(crate) fn read_header<R: BufRead>(reader: &mut R) -> Result<Vec<u8>, IndexError> {
+    let mut header = vec![];
+    loop {
+        let start = header.len();
+        if reader.read_until(b'>', &mut header)? == 0 {
+            return Err(IndexError::ItemsTagMissing);
+        }
+        let tag = match header[start..].iter().rposition(|b| *b == b'<') {
+            Some(p) => &header[start + p..],
+            None => continue,
+        };
+        if tag.starts_with(ITEMS_START)
+            && matches!(
+                tag.get(ITEMS_START.len()),
+                Some(b' ' | b'\t' | b'\r' | b'\n' | b'>')
+            )
+        {
+            return Ok(header);
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Scanner {
+    /// End marker of the CDATA section or comment being skipped.
+    skip_until: Option<&'static [u8]>,
+    pub(crate) item_start: Option<u64>,
+    pub(crate) ranges: Vec<Range<u64>>,
+}
+
+impl Scanner {
+    /// Scan `buf`, starting at absolute offset `base`, and return how many bytes are
+    /// done with. The rest may hold an incomplete marker and is scanned again.
+    pub(crate) fn scan(&mut self, buf: &[u8], base: u64, is_eof: bool) -> usize {
+        let mut i = 0;
+        loop {
+            if let Some(end) = self.skip_until {
+                match find(&buf[i..], end) {
+                    Some(p) => {
+                        i += p + end.len();
+                        self.skip_until = None;
+                        continue;
+                    }
+                    None if is_eof => return buf.len(),
+                    None => return buf.len().saturating_sub(end.len() - 1).max(i),
+                }
+            }
+
+            let p = match buf[i..].iter().position(|b| *b == b'<') {
+                Some(p) => i + p,
+                None => return buf.len(),
+            };
+            let rest = &buf[p..];
+            if rest.len() < LOOKAHEAD && !is_eof {
+                return p;
+            }
+
+            if rest.starts_with(CDATA_START) {
+                self.skip_until = Some(CDATA_END);
+                i = p + CDATA_START.len();
+            } else if rest.starts_with(COMMENT_START) {
+                self.skip_until = Some(COMMENT_END);
+                i = p + COMMENT_START.len();
+            } else if rest.starts_with(ITEM_START) {
+                self.item_start = Some(base + p as u64);
+                i = p + ITEM_START.len();
+            } else if rest.starts_with(ITEM_END) {
+                if let Some(start) = self.item_start.take() {
+                    self.ranges.push(start..base + (p + ITEM_END.len()) as u64);
+                }
+                i = p + ITEM_END.len();
+            } else {
+                i = p + 1;
+            }
+        }
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    let mut i = 0;
+    while let Some(p) = haystack[i..].iter().position(|b| *b == needle[0]) {
+        if haystack[i + p..].starts_with(needle) {
+            return Some(i + p);
+        }
+        i += p + 1;
+    }
+    None
+}
+
+//
+/// Metadata kept in the index for every item, enough to select items without parsing
+/// them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ItemMeta {
+    pub time: NaiveDateTime,
+    pub host: String,
+    pub status: StatusCode,
+    pub method: Method,
+}
+
+impl ItemMeta {
+    pub fn new(item: &Item) -> Self {
+        Self {
+            time: item.time,
+            host: item.host.1.to_owned(),
+            status: item.status,
+            method: item.method.to_owned(),
+        }
+    }
+}
+
+//
+/// A Burp XML export opened together with its index, items are fetched by seeking
+/// directly to their byte range.
+///
+/// The index is saved as a sidecar file next to the export, see [`index_path`], and
+/// reused as long as the export length and fingerprint are unchanged.
+#[derive(Debug)]
+pub struct HistoryFile<R = File> {
+    pub index: ItemsIndex,
+    pub metas: Vec<ItemMeta>,
+    /// Length of the export, a sidecar index written for another length is stale.
+    source_len: u64,
+    /// Hash of the export, see [`fingerprint`], a sidecar index written for another one is
+    /// stale.
+    fingerprint: u64,
+    /// Why [`HistoryFile::open`] could not save the sidecar index, e.g. on a read-only
+    /// share. The index is then only kept in memory.
+    pub sidecar_error: Option<IndexError>,
+    reader: R,
+}
+
+impl HistoryFile<File> {
+    /// Open `path`, loading its sidecar index or building and saving it when it is
+    /// missing, stale or unreadable. Failing to save it is not an error, see
+    /// [`HistoryFile::sidecar_error`].
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, IndexError> {
+        let path = path.as_ref();
+        let sidecar = index_path(path);
+        let file = File::open(path)?;
+        if let Ok(index_file) = File::open(&sidecar) {
+            if let Ok(history_file) = Self::load(file, BufReader::new(index_file)) {
+                return Ok(history_file);
+            }
+        }
+
+        let mut history_file = Self::build(File::open(path)?)?;
+        let saved = File::create(&sidecar)
+            .map_err(IndexError::from)
+            .and_then(|x| {
+                let mut writer = BufWriter::new(x);
+                history_file.write_index(&mut writer)?;
+                Ok(writer.flush()?)
+            });
+        history_file.sidecar_error = saved.err();
+        Ok(history_file)
+    }
+
+    /// Remove the sidecar index of `path`, if any.
+    pub fn remove_index(path: impl AsRef<Path>) -> Result<(), IndexError> {
+        match fs::remove_file(index_path(path.as_ref())) {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<R> HistoryFile<R>
+where
+    R: Read + Seek,
+{
+    /// Index `reader`, every item is parsed once to collect its metadata.
+    pub fn build(mut reader: R) -> Result<Self, IndexError> {
+        let source_len = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(0))?;
+        let index = ItemsIndex::build(BufReader::new(&mut reader))?;
+        let metas = (0..index.len())
+            .map(|n| index.read_item(&mut reader, n).map(|x| ItemMeta::new(&x)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let fingerprint = fingerprint(&mut reader, &index.header, source_len)?;
+        Ok(Self {
+            index,
+            metas,
+            source_len,
+            fingerprint,
+            sidecar_error: None,
+            reader,
+        })
+    }
+
+    /// Open `reader` with an index previously written by [`HistoryFile::write_index`].
+    pub fn load<I>(mut reader: R, index_reader: I) -> Result<Self, IndexError>
+    where
+        I: BufRead,
+    {
+        let source_len = reader.seek(SeekFrom::End(0))?;
+        let (indexed_len, indexed_fingerprint, entries) = read_index(index_reader)?;
+        if indexed_len != source_len {
+            return Err(IndexError::IndexStale);
+        }
+        let (ranges, metas) = entries.into_iter().unzip();
+
+        reader.seek(SeekFrom::Start(0))?;
+        let index = ItemsIndex::from_ranges(BufReader::new(&mut reader), ranges)?;
+        let fingerprint = fingerprint(&mut reader, &index.header, source_len)?;
+        if indexed_fingerprint != fingerprint {
+            return Err(IndexError::IndexStale);
+        }
+        Ok(Self {
+            index,
+            metas,
+            source_len,
+            fingerprint,
+            sidecar_error: None,
+            reader,
+        })
+    }
+
+    /// Write the index: a magic line, the export length and fingerprint, then one line
+    /// per item with its byte range, time in milliseconds, status, method and host, tab
+    /// separated.
+    pub fn write_index<W>(&self, mut writer: W) -> Result<(), IndexError>
+    where
+        W: Write,
+    {
+        writeln!(writer, "{INDEX_MAGIC}")?;
+        writeln!(writer, "{}\t{:016x}", self.source_len, self.fingerprint)?;
+        for (range, meta) in self.index.ranges.iter().zip(self.metas.iter()) {
+            writeln!(
+                writer,
+                "{}\t{}\t{}\t{}\t{}\t{}",
+                range.start,
+                range.end,
+                meta.time.and_utc().timestamp_millis(),
+                meta.status.as_u16(),
+                meta.method,
+                meta.host
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Parse item `n`.
+    pub fn get(&mut self, n: usize) -> Result<Item, IndexError> {
+        self.index.read_item(&mut self.reader, n)
+    }
+
+    /// Positions of the items whose metadata matches `predicate`.
+    pub fn positions<P>(&self, mut predicate: P) -> Vec<usize>
+    where
+        P: FnMut(&ItemMeta) -> bool,
+    {
+        self.metas
+            .iter()
+            .enumerate()
+            .filter(|(_, x)| predicate(x))
+            .map(|(n, _)| n)
+            .collect()
+    }
+
+    /// Parse the items whose metadata matches `predicate`, in document order.
+    pub fn find<P>(&mut self, predicate: P) -> Result<Vec<Item>, IndexError>
+    where
+        P: FnMut(&ItemMeta) -> bool,
+    {
+        self.positions(predicate)
+            .into_iter()
+            .map(|n| self.get(n))
+            .collect()
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+/// Sidecar index path of an export, `history.xml` gives `history.xml.idx`.
+pub fn index_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(".");
+    name.push(INDEX_EXTENSION);
+    PathBuf::from(name)
+}
+
+type IndexEntry = (Range<u64>, ItemMeta);
+
+/// FNV-1a hash of the header and of the first and last KB of an export, so another
+/// export of the same length is not mistaken for the indexed one.
+fn fingerprint<R>(reader: &mut R, header: &[u8], source_len: u64) -> Result<u64, IoError>
+where
+    R: Read + Seek,
+{
+    let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+    let mut update = |bytes: &[u8]| {
+        for b in bytes {
+            hash = (hash ^ *b as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    };
+    update(header);
+    let mut bytes = vec![];
+    for start in [0, source_len.saturating_sub(FINGERPRINT_LEN)] {
+        reader.seek(SeekFrom::Start(start))?;
+        bytes.clear();
+        (&mut *reader)
+            .take(FINGERPRINT_LEN)
+            .read_to_end(&mut bytes)?;
+        update(&bytes);
+    }
+    Ok(hash)
+}
+
+/// Export length, fingerprint and entries of a sidecar index.
+fn read_index<R>(reader: R) -> Result<(u64, u64, Vec<IndexEntry>), IndexError>
+where
+    R: BufRead,
+{
+    let invalid = |n: usize| IndexError::IndexInvalid(format!("line {}", n + 1));
+
+    let mut lines = reader.lines().enumerate();
+    let magic = lines.next().map(|(_, x)| x).transpose()?;
+    if magic.as_deref() != Some(INDEX_MAGIC) {
+        return Err(invalid(0));
+    }
+    let (source_len, fingerprint) = match lines.next() {
+        Some((n, line)) => {
+            let line = line?;
+            let (source_len, fingerprint) = line.split_once('\t').ok_or_else(|| invalid(n))?;
+            (
+                source_len.parse::<u64>().map_err(|_| invalid(n))?,
+                u64::from_str_radix(fingerprint, 16).map_err(|_| invalid(n))?,
+            )
+        }
+        None => return Err(invalid(1)),
+    };
+
+    let mut entries = vec![];
+    for (n, line) in lines {
+        let line = line?;
+        let fields: Vec<&str> = line.splitn(6, '\t').collect();
+        let [start, end, time, status, method, host] = fields[..] else {
+            return Err(invalid(n));
+        };
+        let start = start.parse::<u64>().map_err(|_| invalid(n))?;
+        let end = end.parse::<u64>().map_err(|_| invalid(n))?;
+        // Ranges are ascending and within the export.
+        let previous_end = entries
+            .last()
+            .map(|(x, _): &IndexEntry| x.end)
+            .unwrap_or_default();
+        if start > end || end > source_len || start < previous_end {
+            return Err(invalid(n));
+        }
+        let time = time
+            .parse::<i64>()
+            .ok()
+            .and_then(DateTime::from_timestamp_millis)
+            .ok_or_else(|| invalid(n))?
+            .naive_utc();
+        let status = status
+            .parse::<u16>()
+            .ok()
+            .and_then(|x| StatusCode::from_u16(x).ok())
+            .ok_or_else(|| invalid(n))?;
+        let method = Method::from_str(method).map_err(|_| invalid(n))?;
+        entries.push((
+            start..end,
+            ItemMeta {
+                time,
+                status,
+                method,
+                host: host.to_owned(),
+            },
+        ));
+    }
+    Ok((source_len, fingerprint, entries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+
+    #[test]
+    fn test_index() -> Result<(), Box<dyn std::error::Error>> {
+        let path = "tests/http_history_files/burpsuite_community_v2021.3.2.xml";
+        let bytes = fs::read(path)?;
+        // A small buffer puts markers across chunk boundaries.
+        let index = ItemsIndex::build(BufReader::with_capacity(7, &bytes[..]))?;
+        assert_eq!(index.attr.burp_version, "2021.3.2");
+        assert_eq!(index.len(), 2);
+        for range in index.ranges.iter() {
+            let item = &bytes[range.start as usize..range.end as usize];
+            assert!(item.starts_with(b"<item>") && item.ends_with(b"</item>"));
+        }
+
+        let items = Items::from_reader(BufReader::new(File::open(path)?))?
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut file = File::open(path)?;
+        let item = index.read_item(&mut file, 1)?;
+        assert_eq!(item.url, items[1].url);
+        assert_eq!(item.request.1, items[1].request.1);
+        assert!(matches!(
+            index.read_item(&mut file, 2),
+            Err(IndexError::ItemMissing(2))
+        ));
+
+        let index = ItemsIndex::from_ranges(
+            &bytes[..],
+            vec![Range {
+                start: 100,
+                end: 50,
+            }],
+        )?;
+        assert!(matches!(
+            index.read_item(&mut file, 0),
+            Err(IndexError::IndexInvalid(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_index_cdata() -> Result<(), Box<dyn std::error::Error>> {
+        let xml = "<?xml version=\"1.0\"?>\n<!-- <item> -->\n<items burpVersion=\"1\" exportTime=\"Wed Mar 31 13:07:44 CST 2021\">\n<item><url><![CDATA[</item><item>]]></url></item>\n<item></item>\n</items>\n";
+        let index = ItemsIndex::build(BufReader::with_capacity(3, xml.as_bytes()))?;
+        assert_eq!(index.len(), 2);
+        let first = &index.ranges[0];
+        assert_eq!(
+            &xml[first.start as usize..first.end as usize],
+            "<item><url><![CDATA[</item><item>]]></url></item>"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_history_file() -> Result<(), Box<dyn std::error::Error>> {
+        let path = env::temp_dir().join(format!("burpsuite_kit_index_{}.xml", std::process::id()));
+        fs::copy(
+            "tests/http_history_files/burpsuite_community_v2021.3.2.xml",
+            &path,
+        )?;
+        HistoryFile::remove_index(&path)?;
+
+        let mut history_file = HistoryFile::open(&path)?;
+        assert_eq!(history_file.len(), 2);
+        assert!(index_path(&path).exists());
+        let item = history_file.get(1)?;
+        assert_eq!(history_file.metas[1], ItemMeta::new(&item));
+
+        // Reopened from the sidecar index.
+        let mut history_file = HistoryFile::open(&path)?;
+        assert_eq!(history_file.metas[1], ItemMeta::new(&item));
+        let items = history_file.find(|x| x.method == Method::POST)?;
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].url, item.url);
+        let positions = history_file.positions(|x| x.time == item.time);
+        assert_eq!(positions, vec![1]);
+
+        let mut index = vec![];
+        history_file.write_index(&mut index)?;
+        assert!(matches!(
+            HistoryFile::load(File::open(&path)?, &index[..4]),
+            Err(IndexError::IndexInvalid(_))
+        ));
+        // Entries out of order.
+        let text = String::from_utf8(index.to_owned())?;
+        let mut lines: Vec<&str> = text.lines().collect();
+        lines.swap(2, 3);
+        assert!(matches!(
+            HistoryFile::load(File::open(&path)?, lines.join("\n").as_bytes()),
+            Err(IndexError::IndexInvalid(_))
+        ));
+        // Another export of the same length.
+        let bytes = fs::read(&path)?;
+        fs::write(
+            &path,
+            String::from_utf8(bytes)?.replace("13:07:44", "13:07:45"),
+        )?;
+        assert!(matches!(
+            HistoryFile::load(File::open(&path)?, &index[..]),
+            Err(IndexError::IndexStale)
+        ));
+        fs::write(&path, b"<items></items>")?;
+        assert!(matches!(
+            HistoryFile::load(File::open(&path)?, &index[..]),
+            Err(IndexError::IndexStale)
+        ));
+
+        HistoryFile::remove_index(&path)?;
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_history_file_sidecar_error() -> Result<(), Box<dyn std::error::Error>> {
+        let path = env::temp_dir().join(format!(
+            "burpsuite_kit_index_sidecar_{}.xml",
+            std::process::id()
+        ));
+        fs::copy(
+            "tests/http_history_files/burpsuite_community_v2021.3.2.xml",
+            &path,
+        )?;
+        // A directory in the way of the sidecar index, like an unwritable share.
+        fs::create_dir_all(index_path(&path))?;
+
+        let mut history_file = HistoryFile::open(&path)?;
+        assert!(matches!(
+            history_file.sidecar_error,
+            Some(IndexError::IoError(_))
+        ));
+        assert_eq!(history_file.len(), 2);
+        assert!(history_file.get(1).is_ok());
+
+        fs::remove_dir(index_path(&path))?;
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+}
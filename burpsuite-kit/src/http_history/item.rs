@@ -1,15 +1,19 @@
 use std::{
-    collections::HashSet,
+    borrow::Cow,
+    collections::{BTreeMap, HashSet},
     convert::TryFrom,
     iter::Iterator,
     str::{self, FromStr as _},
 };
 
-use chrono::NaiveDateTime;
-use http::{uri::Scheme, Method, StatusCode};
+use base64::{engine::general_purpose, DecodeError, Engine as _};
+use chrono::{DateTime, NaiveDateTime};
+use http::{uri::Scheme, Method, StatusCode, Uri};
 use once_cell::sync::Lazy;
 use strum::{Display, EnumIter, EnumString, IntoEnumIterator as _};
 
+use super::message::{Message, MessageParseError};
+
 #[derive(Clone, Debug)]
 pub struct Item {
     pub time: NaiveDateTime,
@@ -26,12 +30,18 @@ pub struct Item {
     pub mimetype: String,
     pub response: (ItemResponseAttr, Vec<u8>),
     pub comment: Option<String>,
+    /// Elements and attributes outside the Burp DTD, keyed `element` or
+    /// `element@attribute`, as kept by [`SchemaMode::Tolerant`](super::items::SchemaMode).
+    ///
+    /// Kept by JSONL exports. Not written back by
+    /// [`ItemsWriter`](super::writer::ItemsWriter) nor stored by the `sqlite` `Store`.
+    pub extra: BTreeMap<String, String>,
 }
 
 impl Default for Item {
     fn default() -> Self {
         Self {
-            time: NaiveDateTime::from_timestamp_opt(0, 0).expect(""),
+            time: DateTime::UNIX_EPOCH.naive_utc(),
             url: Default::default(),
             host: Default::default(),
             port: Default::default(),
@@ -45,7 +55,180 @@ impl Default for Item {
             mimetype: Default::default(),
             response: Default::default(),
             comment: Default::default(),
+            extra: Default::default(),
+        }
+    }
+}
+
+impl Item {
+    /// Raw request bytes, base64-decoded when `request.0.base64` is set.
+    pub fn request_bytes(&self) -> Result<Cow<'_, [u8]>, DecodeError> {
+        decode_payload(self.request.0.base64, &self.request.1)
+    }
+
+    /// Raw response bytes, base64-decoded when `response.0.base64` is set.
+    pub fn response_bytes(&self) -> Result<Cow<'_, [u8]>, DecodeError> {
+        decode_payload(self.response.0.base64, &self.response.1)
+    }
+
+    /// Replace the request, stored base64-encoded.
+    pub fn set_request_bytes(&mut self, bytes: impl AsRef<[u8]>) {
+        self.request = (
+            ItemRequestAttr { base64: true },
+            general_purpose::STANDARD.encode(bytes).into_bytes(),
+        );
+    }
+
+    /// Replace the response, stored base64-encoded.
+    pub fn set_response_bytes(&mut self, bytes: impl AsRef<[u8]>) {
+        self.response = (
+            ItemResponseAttr { base64: true },
+            general_purpose::STANDARD.encode(bytes).into_bytes(),
+        );
+    }
+
+    /// Build an item from a captured exchange, deriving `url`, `method`, `path`,
+    /// `extension`, `status`, `response_length` and `mimetype` the way Burp does.
+    ///
+    /// `response` may be empty when none was received. `time`, `host.0.ip` and
+    /// `comment` are left to the caller.
+    pub fn from_messages(
+        protocol: Scheme,
+        host: &str,
+        port: u16,
+        request: &[u8],
+        response: &[u8],
+    ) -> Result<Self, MessageParseError> {
+        let request_message = Message::parse(request)?;
+        let (method, target, _) = request_message.request_line().ok_or_else(|| {
+            MessageParseError::StartLineInvalid(request_message.start_line.to_owned())
+        })?;
+        // Absolute-form targets, as sent to a proxy.
+        let path = match target.find("://") {
+            Some(n) => match target[n + 3..].find('/') {
+                Some(m) => &target[n + 3 + m..],
+                None => "/",
+            },
+            None => target,
+        };
+
+        let is_default_port =
+            (protocol == Scheme::HTTP && port == 80) || (protocol == Scheme::HTTPS && port == 443);
+        let url = if is_default_port {
+            format!("{protocol}://{host}{path}")
+        } else {
+            format!("{protocol}://{host}:{port}{path}")
+        };
+
+        let mut item = Self {
+            url,
+            host: (Default::default(), host.to_owned()),
+            port,
+            protocol,
+            method: Method::from_bytes(method.as_bytes())
+                .map_err(|err| MessageParseError::StartLineInvalid(err.to_string()))?,
+            path: path.to_owned(),
+            extension: path_extension(path),
+            response_length: response.len() as u32,
+            ..Default::default()
+        };
+        item.set_request_bytes(request);
+
+        if !response.is_empty() {
+            let response_message = Message::parse(response)?;
+            item.status = response_message
+                .status_code()
+                .and_then(|x| StatusCode::from_u16(x).ok())
+                .ok_or_else(|| {
+                    MessageParseError::StartLineInvalid(response_message.start_line.to_owned())
+                })?;
+            item.mimetype = mimetype(&response_message);
+            item.set_response_bytes(response);
         }
+
+        Ok(item)
+    }
+}
+
+/// File extension of the last path segment, `None` as Burp's `null`.
+pub fn path_extension(path: &str) -> Option<String> {
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    let segment = path.rsplit('/').next().unwrap_or_default();
+    match segment.rsplit_once('.') {
+        Some((name, extension))
+            if !name.is_empty()
+                && !extension.is_empty()
+                && extension.bytes().all(|b| b.is_ascii_alphanumeric()) =>
+        {
+            Some(extension.to_owned())
+        }
+        _ => None,
+    }
+}
+
+/// `(protocol, host, port, origin-form target)` of an absolute http or https URL.
+pub fn split_url(url: &str) -> Option<(Scheme, String, u16, String)> {
+    let uri: Uri = url.parse().ok()?;
+    let protocol = match uri.scheme() {
+        Some(x) if *x == Scheme::HTTPS => Scheme::HTTPS,
+        Some(x) if *x == Scheme::HTTP => Scheme::HTTP,
+        _ => return None,
+    };
+    let host = uri.host()?.to_owned();
+    let port = uri
+        .port_u16()
+        .unwrap_or(if protocol == Scheme::HTTPS { 443 } else { 80 });
+    let target = uri
+        .path_and_query()
+        .map(|x| x.as_str())
+        .filter(|x| !x.is_empty())
+        .unwrap_or("/")
+        .to_owned();
+    Some((protocol, host, port, target))
+}
+
+/// Burp's MIME type label for a response, from `Content-Type` or the body.
+pub fn mimetype(response: &Message<'_>) -> String {
+    let mime_type = response.mime_type().unwrap_or_default();
+    let label = match mime_type.as_str() {
+        "" => {
+            let body = response.body.trim_ascii_start();
+            let lowercase = body[..body.len().min(16)].to_ascii_lowercase();
+            if body.is_empty() {
+                ""
+            } else if lowercase.starts_with(b"<!doctype html") || lowercase.starts_with(b"<html") {
+                "HTML"
+            } else if lowercase.starts_with(b"<?xml") {
+                "XML"
+            } else if body.starts_with(b"{") || body.starts_with(b"[") {
+                "JSON"
+            } else if str::from_utf8(body).is_ok() {
+                "text"
+            } else {
+                "app"
+            }
+        }
+        "text/html" | "application/xhtml+xml" => "HTML",
+        "text/css" => "CSS",
+        "image/png" => "PNG",
+        "image/jpeg" | "image/jpg" => "JPEG",
+        "image/gif" => "GIF",
+        "image/svg+xml" => "XML",
+        x if x.contains("json") => "JSON",
+        x if x.contains("javascript") || x.contains("ecmascript") => "script",
+        x if x.ends_with("/xml") || x.ends_with("+xml") => "XML",
+        x if x.starts_with("image/") => "image",
+        x if x.starts_with("text/") => "text",
+        _ => "app",
+    };
+    label.to_owned()
+}
+
+pub(super) fn decode_payload(base64: bool, bytes: &[u8]) -> Result<Cow<'_, [u8]>, DecodeError> {
+    if base64 {
+        general_purpose::STANDARD.decode(bytes).map(Cow::Owned)
+    } else {
+        Ok(Cow::Borrowed(bytes))
     }
 }
 
@@ -55,11 +238,13 @@ pub struct ItemHostAttr {
 }
 
 #[derive(Default, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ItemRequestAttr {
     pub base64: bool,
 }
 
 #[derive(Default, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ItemResponseAttr {
     pub base64: bool,
 }
@@ -97,3 +282,52 @@ impl TryFrom<&[u8]> for Tag {
 }
 
 pub(super) static TAG_SET: Lazy<HashSet<Tag>> = Lazy::new(|| Tag::iter().collect());
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{fs::File, io::BufReader};
+
+    use crate::http_history::items::Items;
+
+    #[test]
+    fn test_from_messages() -> Result<(), Box<dyn std::error::Error>> {
+        let file = File::open("tests/http_history_files/burpsuite_community_v2021.3.2.xml")?;
+        for item in Items::from_reader(BufReader::new(file))? {
+            let item = item?;
+            let built = Item::from_messages(
+                item.protocol.to_owned(),
+                &item.host.1,
+                item.port,
+                &item.request_bytes()?,
+                &item.response_bytes()?,
+            )?;
+            assert_eq!(built.url, item.url);
+            assert_eq!(built.method, item.method);
+            assert_eq!(built.path, item.path);
+            assert_eq!(built.extension, item.extension);
+            assert_eq!(built.status, item.status);
+            assert_eq!(built.response_length, item.response_length);
+            assert_eq!(built.mimetype, item.mimetype);
+        }
+
+        assert_eq!(path_extension("/a/b.min.js?x=1.2"), Some("js".to_owned()));
+        assert_eq!(path_extension("/a.b/c"), None);
+        assert_eq!(path_extension("/.env"), None);
+
+        let item = Item::from_messages(
+            Scheme::HTTP,
+            "example.com",
+            8080,
+            b"GET http://example.com:8080/x.png HTTP/1.1\r\n\r\n",
+            b"",
+        )?;
+        assert_eq!(item.url, "http://example.com:8080/x.png");
+        assert_eq!(item.path, "/x.png");
+        assert_eq!(item.extension.as_deref(), Some("png"));
+        assert!(item.response.1.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,158 @@
+use std::{borrow::Cow, collections::BTreeMap};
+
+use base64::DecodeError;
+use chrono::{DateTime, NaiveDateTime};
+use http::{uri::Scheme, Method, StatusCode};
+
+use super::item::{decode_payload, Item, ItemHostAttr, ItemRequestAttr, ItemResponseAttr};
+
+//
+/// An [`Item`] borrowing its strings and payloads from the document it was parsed
+/// from, see [`ItemRefs`](super::items::ItemRefs).
+///
+/// Values only own their data when XML unescaping was required.
+#[derive(Clone, Debug)]
+pub struct ItemRef<'a> {
+    pub time: NaiveDateTime,
+    pub url: Cow<'a, str>,
+    pub host: (ItemHostAttr, Cow<'a, str>),
+    pub port: u16,
+    pub protocol: Scheme,
+    pub method: Method,
+    pub path: Cow<'a, str>,
+    pub extension: Option<Cow<'a, str>>,
+    pub request: (ItemRequestAttr, Cow<'a, [u8]>),
+    pub status: StatusCode,
+    pub response_length: u32,
+    pub mimetype: Cow<'a, str>,
+    pub response: (ItemResponseAttr, Cow<'a, [u8]>),
+    pub comment: Option<Cow<'a, str>>,
+    pub extra: BTreeMap<String, String>,
+}
+
+impl Default for ItemRef<'_> {
+    fn default() -> Self {
+        Self {
+            time: DateTime::UNIX_EPOCH.naive_utc(),
+            url: Default::default(),
+            host: Default::default(),
+            port: Default::default(),
+            protocol: Scheme::HTTP,
+            method: Default::default(),
+            path: Default::default(),
+            extension: Default::default(),
+            request: Default::default(),
+            status: Default::default(),
+            response_length: Default::default(),
+            mimetype: Default::default(),
+            response: Default::default(),
+            comment: Default::default(),
+            extra: Default::default(),
+        }
+    }
+}
+
+impl ItemRef<'_> {
+    /// Raw request bytes, base64-decoded when `request.0.base64` is set.
+    pub fn request_bytes(&self) -> Result<Cow<'_, [u8]>, DecodeError> {
+        decode_payload(self.request.0.base64, &self.request.1)
+    }
+
+    /// Raw response bytes, base64-decoded when `response.0.base64` is set.
+    pub fn response_bytes(&self) -> Result<Cow<'_, [u8]>, DecodeError> {
+        decode_payload(self.response.0.base64, &self.response.1)
+    }
+
+    pub fn into_owned(self) -> Item {
+        Item {
+            time: self.time,
+            url: self.url.into_owned(),
+            host: (self.host.0, self.host.1.into_owned()),
+            port: self.port,
+            protocol: self.protocol,
+            method: self.method,
+            path: self.path.into_owned(),
+            extension: self.extension.map(Cow::into_owned),
+            request: (self.request.0, self.request.1.into_owned()),
+            status: self.status,
+            response_length: self.response_length,
+            mimetype: self.mimetype.into_owned(),
+            response: (self.response.0, self.response.1.into_owned()),
+            comment: self.comment.map(Cow::into_owned),
+            extra: self.extra,
+        }
+    }
+}
+
+impl From<ItemRef<'_>> for Item {
+    fn from(item: ItemRef<'_>) -> Self {
+        item.into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{fs, io::BufReader};
+
+    use crate::http_history::items::{ItemRefs, Items};
+
+    #[test]
+    fn test_item_refs() -> Result<(), Box<dyn std::error::Error>> {
+        for name in [
+            "burpsuite_community_v1.7.36.xml",
+            "burpsuite_community_v2020.12.1.xml",
+            "burpsuite_community_v2021.3.2.xml",
+        ] {
+            let bytes = fs::read(format!("tests/http_history_files/{name}"))?;
+            let items =
+                Items::from_reader(BufReader::new(&bytes[..]))?.collect::<Result<Vec<_>, _>>()?;
+            let item_refs = ItemRefs::from_slice(&bytes)?.collect::<Result<Vec<_>, _>>()?;
+            assert_eq!(item_refs.len(), items.len());
+
+            for (item_ref, item) in item_refs.into_iter().zip(items.iter()) {
+                assert!(matches!(item_ref.url, Cow::Borrowed(_)));
+                assert!(matches!(item_ref.response.1, Cow::Borrowed(_)));
+                assert_eq!(item_ref.response_bytes()?, item.response_bytes()?);
+
+                let owned = item_ref.into_owned();
+                assert_eq!(owned.time, item.time);
+                assert_eq!(owned.url, item.url);
+                assert_eq!(owned.host.0.ip, item.host.0.ip);
+                assert_eq!(owned.host.1, item.host.1);
+                assert_eq!(owned.request.1, item.request.1);
+                assert_eq!(owned.status, item.status);
+                assert_eq!(owned.comment, item.comment);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_item_refs_unescape() -> Result<(), Box<dyn std::error::Error>> {
+        let xml = fs::read_to_string("tests/http_history_files/burpsuite_community_v2021.3.2.xml")?;
+        let xml = xml.replacen("<mimetype>", "<mimetype>a&amp;b", 1).replacen(
+            "<url><![CDATA[",
+            "<url><![CDATA[&amp;",
+            1,
+        );
+
+        let item_ref = ItemRefs::from_slice(xml.as_bytes())?
+            .next()
+            .ok_or("item missing")??;
+        assert!(matches!(item_ref.mimetype, Cow::Owned(_)));
+        assert!(item_ref.mimetype.starts_with("a&b"));
+        assert!(matches!(item_ref.url, Cow::Borrowed(_)));
+        assert!(item_ref.url.starts_with("&amp;"));
+
+        let item = Items::from_reader(xml.as_bytes())?
+            .next()
+            .ok_or("item missing")??;
+        assert_eq!(item.mimetype, item_ref.mimetype);
+        assert_eq!(item.url, item_ref.url);
+
+        Ok(())
+    }
+}
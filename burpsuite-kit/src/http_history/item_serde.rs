@@ -0,0 +1,414 @@
+//! `serde` support for items.
+//!
+//! `Item` serializes its request and response as `[attr, data]` pairs, where `data`
+//! is the decoded payload in the chosen byte encoding and `attr.base64` is kept so
+//! the stored form is rebuilt on deserialize. The `Serialize`/`Deserialize` impls
+//! use base64; pick another encoding with `#[serde(with = "...")]`:
+//!
+//! ```ignore
+//! #[derive(serde::Serialize, serde::Deserialize)]
+//! struct Record {
+//!     #[serde(with = "burpsuite_kit::http_history::item_serde::utf8")]
+//!     item: Item,
+//! }
+//! ```
+
+use core::{fmt, marker::PhantomData, str::FromStr as _};
+use std::{borrow::Cow, collections::BTreeMap};
+
+use ::base64::{engine::general_purpose, Engine as _};
+use chrono::NaiveDateTime;
+use http::{uri::Scheme, Method, StatusCode};
+use serde::{
+    de::{self, Deserializer, SeqAccess, Visitor},
+    ser::{self, Serializer},
+    Deserialize, Serialize,
+};
+
+use super::item::{decode_payload, Item, ItemHostAttr, ItemRequestAttr, ItemResponseAttr, Tag};
+
+/// How a decoded payload is written.
+pub trait BytesEncoding {
+    fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error>;
+    fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error>;
+}
+
+/// Standard base64 string.
+pub enum Base64 {}
+
+/// UTF-8 string, serializing a non UTF-8 payload is an error.
+pub enum Utf8 {}
+
+/// Native bytes, a sequence of numbers in formats without a bytes type.
+pub enum Array {}
+
+impl BytesEncoding for Base64 {
+    fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&general_purpose::STANDARD.encode(bytes))
+    }
+
+    fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let s = Cow::<str>::deserialize(deserializer)?;
+        general_purpose::STANDARD
+            .decode(s.as_bytes())
+            .map_err(de::Error::custom)
+    }
+}
+
+impl BytesEncoding for Utf8 {
+    fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(core::str::from_utf8(bytes).map_err(ser::Error::custom)?)
+    }
+
+    fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        String::deserialize(deserializer).map(String::into_bytes)
+    }
+}
+
+impl BytesEncoding for Array {
+    fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(bytes)
+    }
+
+    fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        struct BytesVisitor;
+
+        impl<'de> Visitor<'de> for BytesVisitor {
+            type Value = Vec<u8>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("bytes or a sequence of bytes")
+            }
+
+            fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+                Ok(v.to_vec())
+            }
+
+            fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+                Ok(v)
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+                while let Some(b) = seq.next_element()? {
+                    bytes.push(b);
+                }
+                Ok(bytes)
+            }
+        }
+
+        deserializer.deserialize_byte_buf(BytesVisitor)
+    }
+}
+
+//
+struct Payload<'a, E> {
+    bytes: Cow<'a, [u8]>,
+    encoding: PhantomData<E>,
+}
+
+impl<E: BytesEncoding> Serialize for Payload<'_, E> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        E::serialize(&self.bytes, serializer)
+    }
+}
+
+impl<'de, E: BytesEncoding> Deserialize<'de> for Payload<'_, E> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self {
+            bytes: Cow::Owned(E::deserialize(deserializer)?),
+            encoding: PhantomData,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound(serialize = "E: BytesEncoding", deserialize = "E: BytesEncoding"))]
+#[serde(rename = "Item")]
+struct ItemRepr<'a, E> {
+    time: NaiveDateTime,
+    url: Cow<'a, str>,
+    host: (Cow<'a, ItemHostAttr>, Cow<'a, str>),
+    port: u16,
+    protocol: Cow<'a, str>,
+    method: Cow<'a, str>,
+    path: Cow<'a, str>,
+    extension: Option<Cow<'a, str>>,
+    request: (ItemRequestAttr, Payload<'a, E>),
+    status: u16,
+    response_length: u32,
+    mimetype: Cow<'a, str>,
+    response: (ItemResponseAttr, Payload<'a, E>),
+    comment: Option<Cow<'a, str>>,
+    #[serde(default)]
+    extra: Cow<'a, BTreeMap<String, String>>,
+}
+
+fn serialize_with<E: BytesEncoding, S: Serializer>(
+    item: &Item,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let payload = |base64, bytes| -> Result<Payload<'_, E>, S::Error> {
+        Ok(Payload {
+            bytes: decode_payload(base64, bytes).map_err(ser::Error::custom)?,
+            encoding: PhantomData,
+        })
+    };
+
+    ItemRepr {
+        time: item.time,
+        url: Cow::Borrowed(&item.url),
+        host: (Cow::Borrowed(&item.host.0), Cow::Borrowed(&item.host.1)),
+        port: item.port,
+        protocol: Cow::Borrowed(item.protocol.as_str()),
+        method: Cow::Borrowed(item.method.as_str()),
+        path: Cow::Borrowed(&item.path),
+        extension: item.extension.as_deref().map(Cow::Borrowed),
+        request: (
+            item.request.0.to_owned(),
+            payload(item.request.0.base64, &item.request.1)?,
+        ),
+        status: item.status.as_u16(),
+        response_length: item.response_length,
+        mimetype: Cow::Borrowed(&item.mimetype),
+        response: (
+            item.response.0.to_owned(),
+            payload(item.response.0.base64, &item.response.1)?,
+        ),
+        comment: item.comment.as_deref().map(Cow::Borrowed),
+        extra: Cow::Borrowed(&item.extra),
+    }
+    .serialize(serializer)
+}
+
+fn deserialize_with<'de, E: BytesEncoding, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Item, D::Error> {
+    let repr = ItemRepr::<'static, E>::deserialize(deserializer)?;
+    let payload = |base64, payload: Payload<'_, E>| {
+        if base64 {
+            general_purpose::STANDARD.encode(payload.bytes).into_bytes()
+        } else {
+            payload.bytes.into_owned()
+        }
+    };
+
+    Ok(Item {
+        time: repr.time,
+        url: repr.url.into_owned(),
+        host: (repr.host.0.into_owned(), repr.host.1.into_owned()),
+        port: repr.port,
+        protocol: Scheme::from_str(&repr.protocol).map_err(de::Error::custom)?,
+        method: Method::from_str(&repr.method).map_err(de::Error::custom)?,
+        path: repr.path.into_owned(),
+        extension: repr.extension.map(Cow::into_owned),
+        status: StatusCode::from_u16(repr.status).map_err(de::Error::custom)?,
+        response_length: repr.response_length,
+        mimetype: repr.mimetype.into_owned(),
+        request: (
+            repr.request.0.to_owned(),
+            payload(repr.request.0.base64, repr.request.1),
+        ),
+        response: (
+            repr.response.0.to_owned(),
+            payload(repr.response.0.base64, repr.response.1),
+        ),
+        comment: repr.comment.map(Cow::into_owned),
+        extra: repr.extra.into_owned(),
+    })
+}
+
+macro_rules! with_module {
+    ($name:ident, $encoding:ty) => {
+        /// `#[serde(with = "...")]` module for `Item`.
+        pub mod $name {
+            use super::*;
+
+            pub fn serialize<S: Serializer>(item: &Item, serializer: S) -> Result<S::Ok, S::Error> {
+                serialize_with::<$encoding, S>(item, serializer)
+            }
+
+            pub fn deserialize<'de, D: Deserializer<'de>>(
+                deserializer: D,
+            ) -> Result<Item, D::Error> {
+                deserialize_with::<$encoding, D>(deserializer)
+            }
+        }
+    };
+}
+
+with_module!(base64, Base64);
+with_module!(utf8, Utf8);
+with_module!(array, Array);
+
+impl Serialize for Item {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_with::<Base64, S>(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Item {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_with::<Base64, D>(deserializer)
+    }
+}
+
+//
+impl Serialize for ItemHostAttr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        #[serde(rename = "ItemHostAttr")]
+        struct Repr<'a> {
+            ip: Cow<'a, str>,
+        }
+
+        Repr {
+            ip: String::from_utf8_lossy(&self.ip),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ItemHostAttr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(rename = "ItemHostAttr")]
+        struct Repr {
+            ip: String,
+        }
+
+        Repr::deserialize(deserializer).map(|x| Self {
+            ip: x.ip.into_bytes(),
+        })
+    }
+}
+
+impl Serialize for Tag {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Tag {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = Cow::<str>::deserialize(deserializer)?;
+        Self::from_str(&s).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    use crate::http_history::items::ItemsAttr;
+
+    #[derive(Serialize, Deserialize)]
+    struct Record {
+        #[serde(with = "utf8")]
+        item: Item,
+    }
+
+    struct SerdeArray<'a>(&'a Item);
+
+    impl Serialize for SerdeArray<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            array::serialize(self.0, serializer)
+        }
+    }
+
+    fn item() -> Item {
+        let mut item = Item {
+            url: "http://example.com/".to_owned(),
+            host: (
+                ItemHostAttr {
+                    ip: b"93.184.216.34".to_vec(),
+                },
+                "example.com".to_owned(),
+            ),
+            port: 80,
+            method: Method::POST,
+            path: "/".to_owned(),
+            status: StatusCode::CREATED,
+            ..Default::default()
+        };
+        item.set_request_bytes("POST / HTTP/1.1\r\nHost: example.com\r\n\r\nab");
+        item.response = (
+            ItemResponseAttr { base64: false },
+            b"HTTP/1.1 201 Created\r\n\r\n".to_vec(),
+        );
+        item
+    }
+
+    #[test]
+    fn test_item() -> Result<(), Box<dyn std::error::Error>> {
+        let item = item();
+
+        let value = serde_json::to_value(&item)?;
+        assert_eq!(value["time"], "1970-01-01T00:00:00");
+        assert_eq!(
+            value["host"],
+            json!([{ "ip": "93.184.216.34" }, "example.com"])
+        );
+        assert_eq!(value["protocol"], "http");
+        assert_eq!(value["method"], "POST");
+        assert_eq!(value["status"], 201);
+        assert_eq!(value["request"][0], json!({ "base64": true }));
+        assert_eq!(value["request"][1], core::str::from_utf8(&item.request.1)?);
+        assert_eq!(value["response"][1], "SFRUUC8xLjEgMjAxIENyZWF0ZWQNCg0K");
+
+        let de: Item = serde_json::from_value(value)?;
+        assert_eq!(de.request.1, item.request.1);
+        assert!(!de.response.0.base64);
+        assert_eq!(de.response.1, item.response.1);
+        assert_eq!(de.status, item.status);
+
+        let value = serde_json::to_value(Record {
+            item: item.to_owned(),
+        })?;
+        assert_eq!(
+            value["item"]["request"][1],
+            "POST / HTTP/1.1\r\nHost: example.com\r\n\r\nab"
+        );
+        let de: Record = serde_json::from_value(value)?;
+        assert_eq!(de.item.request.1, item.request.1);
+
+        let mut binary = item;
+        binary.set_response_bytes(b"\xff");
+        assert!(serde_json::to_value(Record {
+            item: binary.to_owned()
+        })
+        .is_err());
+
+        let value = serde_json::to_value(SerdeArray(&binary))?;
+        assert_eq!(value["response"][1], json!([255]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_attr_and_tag() -> Result<(), Box<dyn std::error::Error>> {
+        let attr = ItemsAttr {
+            burp_version: "2021.3.2".to_owned(),
+            export_time: Item::default().time,
+        };
+        let value = serde_json::to_value(&attr)?;
+        assert_eq!(
+            value,
+            json!({ "burp_version": "2021.3.2", "export_time": "1970-01-01T00:00:00" })
+        );
+        assert_eq!(
+            serde_json::from_value::<ItemsAttr>(value)?.burp_version,
+            "2021.3.2"
+        );
+
+        assert_eq!(serde_json::to_value(Tag::ResponseLength)?, "responselength");
+        assert_eq!(
+            serde_json::from_value::<Tag>(json!("mimetype"))?,
+            Tag::Mimetype
+        );
+
+        Ok(())
+    }
+}
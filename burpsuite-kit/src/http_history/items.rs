@@ -1,9 +1,16 @@
 use core::{
     iter::Iterator,
+    mem,
     num::ParseIntError,
     str::{self, ParseBoolError},
 };
-use std::{collections::HashSet, io::BufRead};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::{BufRead, BufReader, Error as IoError},
+    path::Path,
+};
 
 use chrono::NaiveDateTime;
 use http::{
@@ -11,11 +18,16 @@ use http::{
     Method, StatusCode,
 };
 use quick_xml::{
-    events::{attributes::Attribute, Event},
+    events::{attributes::Attribute, BytesStart, BytesText, Event},
     Error, Reader,
 };
+use strum::IntoEnumIterator as _;
 
-use super::item::{Item, Tag as ItemTag, TAG_SET as ITEM_TAG_SET};
+use super::{
+    compression::{decompress, CompressionError},
+    item::{Item, Tag as ItemTag, TAG_SET as ITEM_TAG_SET},
+    item_ref::ItemRef,
+};
 
 //
 pub struct Items<R>
@@ -26,18 +38,67 @@ where
 
     reader: Reader<R>,
     buf: Vec<u8>,
-    state: State,
-    item: Item,
-    processed_item_tags: HashSet<ItemTag>,
-    is_eof: bool,
+    parser: ItemParser<'static>,
+}
+
+//
+/// Items of a fully buffered or memory-mapped document, borrowing from it.
+///
+/// Yields [`ItemRef`]s, which only allocate for values that need XML unescaping.
+pub struct ItemRefs<'a> {
+    pub attr: ItemsAttr,
+
+    reader: Reader<&'a [u8]>,
+    parser: ItemParser<'a>,
 }
 
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ItemsAttr {
     pub burp_version: String,
     pub export_time: NaiveDateTime,
 }
 
-#[derive(PartialEq, Debug)]
+//
+/// How closely items must follow the Burp DTD, see [`SchemaPolicy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SchemaMode {
+    /// Every element of the DTD, in its order, and no other element or attribute.
+    Strict,
+    /// Every element of the DTD, in any order, and only `comment` may be empty.
+    /// Unknown elements are rejected and unknown attributes ignored.
+    #[default]
+    Standard,
+    /// Unknown elements and attributes are kept in `Item::extra`, missing or empty
+    /// elements and attributes keep their default value.
+    Tolerant,
+}
+
+//
+/// Schema validation of [`Items`] and [`ItemRefs`].
+#[derive(Debug, Clone, Default)]
+pub struct SchemaPolicy {
+    pub mode: SchemaMode,
+    /// Values used for elements missing or empty in an item, written as in the document,
+    /// e.g. `null` for `extension`. Ignored in [`SchemaMode::Strict`].
+    pub defaults: HashMap<ItemTag, String>,
+}
+
+impl SchemaPolicy {
+    pub fn new(mode: SchemaMode) -> Self {
+        Self {
+            mode,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_default(mut self, tag: ItemTag, value: impl Into<String>) -> Self {
+        self.defaults.insert(tag, value.into());
+        self
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
 enum State {
     Idle,
     WaitTag,
@@ -56,6 +117,10 @@ pub enum ItemsParseError {
     AttrMissing(String),
     #[error("AttrInvalid {0} {1}")]
     AttrInvalid(String, String),
+    #[error("IoError {0:?}")]
+    IoError(#[from] IoError),
+    #[error("CompressionError {0}")]
+    CompressionError(#[from] CompressionError),
 }
 
 impl<R> Items<R>
@@ -68,58 +133,7 @@ where
         let mut buf = Vec::new();
         let attr = loop {
             match reader.read_event_into(&mut buf) {
-                Ok(Event::Start(e)) => match e.name().as_ref() {
-                    b"items" => {
-                        let attrs: Vec<Attribute<'_>> =
-                            e.attributes().filter_map(|ret| ret.ok()).collect();
-
-                        let burp_version = attrs
-                            .iter()
-                            .find(|a| a.key.as_ref() == b"burpVersion")
-                            .map(|x| x.value.clone())
-                            .ok_or_else(|| {
-                                ItemsParseError::AttrMissing("burpVersion".to_owned())
-                            })?;
-
-                        let burp_version = str::from_utf8(burp_version.as_ref())
-                            .map(|x| x.to_owned())
-                            .map_err(|err| {
-                                ItemsParseError::AttrInvalid(
-                                    "burpVersion".to_owned(),
-                                    err.to_string(),
-                                )
-                            })?;
-
-                        let export_time = attrs
-                            .iter()
-                            .find(|a| a.key.as_ref() == b"exportTime")
-                            .map(|x| x.value.clone())
-                            .ok_or_else(|| ItemsParseError::AttrMissing("exportTime".to_owned()))?;
-
-                        let export_time = NaiveDateTime::parse_from_str(
-                            str::from_utf8(export_time.as_ref()).map_err(|err| {
-                                ItemsParseError::AttrInvalid(
-                                    "exportTime".to_owned(),
-                                    err.to_string(),
-                                )
-                            })?,
-                            "%a %b %d %T %Z %Y",
-                        )
-                        .map_err(|err| {
-                            ItemsParseError::AttrInvalid("exportTime".to_owned(), err.to_string())
-                        })?;
-
-                        break ItemsAttr {
-                            burp_version,
-                            export_time,
-                        };
-                    }
-                    _ => {
-                        return Err(ItemsParseError::UnknownTag(
-                            e.name().into_inner().to_owned(),
-                        ))
-                    }
-                },
+                Ok(Event::Start(e)) => break items_attr(&e)?,
                 Err(err) => return Err(ItemsParseError::XmlError(err)),
                 Ok(Event::Eof) => return Err(ItemsParseError::UnexpectedEof),
                 _ => {}
@@ -132,12 +146,88 @@ where
             attr,
             reader,
             buf,
-            state: State::Idle,
-            item: Default::default(),
-            processed_item_tags: HashSet::new(),
-            is_eof: false,
+            parser: Default::default(),
         })
     }
+
+    pub fn with_policy(mut self, policy: SchemaPolicy) -> Self {
+        self.parser.policy = policy;
+        self
+    }
+}
+
+impl Items<Box<dyn BufRead + Send>> {
+    /// Open an export, decompressed when it starts with gzip, zstd, bzip2 or xz magic
+    /// bytes and the matching feature is enabled.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, ItemsParseError> {
+        let file = File::open(path)?;
+        Self::from_reader(decompress(BufReader::new(file))?)
+    }
+}
+
+impl<'a> ItemRefs<'a> {
+    pub fn from_slice(bytes: &'a [u8]) -> Result<Self, ItemsParseError> {
+        let mut reader = Reader::from_reader(bytes);
+
+        let attr = loop {
+            match reader.read_event() {
+                Ok(Event::Start(e)) => break items_attr(&e)?,
+                Err(err) => return Err(ItemsParseError::XmlError(err)),
+                Ok(Event::Eof) => return Err(ItemsParseError::UnexpectedEof),
+                _ => {}
+            }
+        };
+
+        Ok(Self {
+            attr,
+            reader,
+            parser: Default::default(),
+        })
+    }
+
+    pub fn with_policy(mut self, policy: SchemaPolicy) -> Self {
+        self.parser.policy = policy;
+        self
+    }
+}
+
+fn items_attr(e: &BytesStart<'_>) -> Result<ItemsAttr, ItemsParseError> {
+    if e.name().as_ref() != b"items" {
+        return Err(ItemsParseError::UnknownTag(
+            e.name().into_inner().to_owned(),
+        ));
+    }
+
+    let attrs: Vec<Attribute<'_>> = e.attributes().filter_map(|ret| ret.ok()).collect();
+
+    let burp_version = attrs
+        .iter()
+        .find(|a| a.key.as_ref() == b"burpVersion")
+        .map(|x| x.value.clone())
+        .ok_or_else(|| ItemsParseError::AttrMissing("burpVersion".to_owned()))?;
+
+    let burp_version = str::from_utf8(burp_version.as_ref())
+        .map(|x| x.to_owned())
+        .map_err(|err| ItemsParseError::AttrInvalid("burpVersion".to_owned(), err.to_string()))?;
+
+    let export_time = attrs
+        .iter()
+        .find(|a| a.key.as_ref() == b"exportTime")
+        .map(|x| x.value.clone())
+        .ok_or_else(|| ItemsParseError::AttrMissing("exportTime".to_owned()))?;
+
+    let export_time = NaiveDateTime::parse_from_str(
+        str::from_utf8(export_time.as_ref()).map_err(|err| {
+            ItemsParseError::AttrInvalid("exportTime".to_owned(), err.to_string())
+        })?,
+        "%a %b %d %T %Z %Y",
+    )
+    .map_err(|err| ItemsParseError::AttrInvalid("exportTime".to_owned(), err.to_string()))?;
+
+    Ok(ItemsAttr {
+        burp_version,
+        export_time,
+    })
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -154,6 +244,10 @@ pub enum ItemParseError {
     SomeTagsMissing(HashSet<ItemTag>),
     #[error("DuplicateTag {0:?}")]
     DuplicateTag(ItemTag),
+    #[error("TagOutOfOrder {0:?}")]
+    TagOutOfOrder(ItemTag),
+    #[error("AttrUnknown {0}")]
+    AttrUnknown(String),
     #[error("TagAttrMissing {0:?} {1}")]
     TagAttrMissing(ItemTag, String),
     #[error("TagAttrInvalid {0:?} {1} {2}")]
@@ -170,418 +264,470 @@ where
 {
     fn item(&mut self) -> Result<Item, ItemParseError> {
         loop {
-            match self.reader.read_event_into(&mut self.buf) {
-                Ok(Event::Start(e)) => match e.name().as_ref() {
-                    b"item" => {
-                        if State::Idle != self.state {
+            let event = self.reader.read_event_into(&mut self.buf)?.into_owned();
+            self.buf.clear();
+
+            if let Some(item) = self.parser.on_event(event)? {
+                return Ok(item.into_owned());
+            }
+        }
+    }
+}
+
+impl<'a> ItemRefs<'a> {
+    fn item(&mut self) -> Result<ItemRef<'a>, ItemParseError> {
+        loop {
+            let event = self.reader.read_event()?;
+
+            if let Some(item) = self.parser.on_event(event)? {
+                return Ok(item);
+            }
+        }
+    }
+}
+
+/// State of the item being parsed, fed the events of either reader.
+struct ItemParser<'a> {
+    policy: SchemaPolicy,
+    state: State,
+    item: ItemRef<'a>,
+    processed_item_tags: HashSet<ItemTag>,
+    /// Elements of the item started so far, to check the DTD order.
+    tag_count: usize,
+    /// Content of the element being read, set on its end tag.
+    value: Option<Cow<'a, [u8]>>,
+    extra_element: Option<ExtraElement>,
+    /// Whether the rest of a failed item is skipped, up to its `</item>`.
+    is_skipping: bool,
+    is_eof: bool,
+}
+
+/// Element outside the DTD, read up to its end tag in [`SchemaMode::Tolerant`].
+struct ExtraElement {
+    name: String,
+    depth: usize,
+    value: Vec<u8>,
+    /// Whether it is inside an item, otherwise it is skipped.
+    is_kept: bool,
+}
+
+impl Default for ItemParser<'_> {
+    fn default() -> Self {
+        Self {
+            policy: Default::default(),
+            state: State::Idle,
+            item: Default::default(),
+            processed_item_tags: HashSet::new(),
+            tag_count: 0,
+            value: None,
+            extra_element: None,
+            is_skipping: false,
+            is_eof: false,
+        }
+    }
+}
+
+impl<'a> ItemParser<'a> {
+    /// Handle one event, returning the item its `</item>` completes.
+    ///
+    /// After an item fails, its remaining events are skipped so the next item
+    /// parses from a clean state.
+    fn on_event(&mut self, event: Event<'a>) -> Result<Option<ItemRef<'a>>, ItemParseError> {
+        let (is_item_start, is_item_end) = match &event {
+            Event::Start(e) => (e.name().as_ref() == b"item", false),
+            Event::Empty(e) => (false, e.name().as_ref() == b"item"),
+            Event::End(e) => (false, e.name().as_ref() == b"item"),
+            _ => (false, false),
+        };
+
+        if self.is_skipping {
+            match event {
+                Event::Eof => return Err(ItemParseError::UnexpectedEof),
+                _ if is_item_end => self.is_skipping = false,
+                _ => {}
+            }
+            return Ok(None);
+        }
+
+        let is_in_item = is_item_start || self.state != State::Idle;
+        let result = self.on_item_event(event);
+        if let Err(err) = &result {
+            if !matches!(err, ItemParseError::UnexpectedEof) {
+                self.state = State::Idle;
+                self.processed_item_tags.clear();
+                self.value = None;
+                self.extra_element = None;
+                self.is_skipping = is_in_item && !is_item_end;
+            }
+        }
+        result
+    }
+
+    fn on_item_event(&mut self, event: Event<'a>) -> Result<Option<ItemRef<'a>>, ItemParseError> {
+        match event {
+            Event::Start(e) => self.on_start(&e)?,
+            Event::Empty(e) => {
+                self.on_start(&e)?;
+                return self.on_end(e.name().as_ref());
+            }
+            Event::End(e) => return self.on_end(e.name().as_ref()),
+            Event::Text(e) => self.on_text(e)?,
+            Event::CData(e) => self.on_value(e.into_inner()),
+            Event::Eof => return Err(ItemParseError::UnexpectedEof),
+            _ => {}
+        }
+
+        Ok(None)
+    }
+
+    fn on_start(&mut self, e: &BytesStart<'_>) -> Result<(), ItemParseError> {
+        if let Some(extra) = self.extra_element.as_mut() {
+            extra.depth += 1;
+            return Ok(());
+        }
+
+        if e.name().as_ref() == b"item" {
+            if State::Idle != self.state {
+                return Err(ItemParseError::StateMismatch(format!(
+                    "expect {:?} but current {:?}",
+                    State::Idle,
+                    self.state
+                )));
+            }
+
+            self.item = Default::default();
+            self.tag_count = 0;
+            self.on_attrs(e, "item", &[])?;
+            self.state = State::WaitTag;
+            return Ok(());
+        }
+
+        let tag = match ItemTag::try_from(e.name().as_ref()) {
+            Ok(x) => x,
+            Err(_) if self.policy.mode == SchemaMode::Tolerant => {
+                let name = String::from_utf8_lossy(e.name().as_ref()).into_owned();
+                let is_kept = self.state != State::Idle;
+                if is_kept {
+                    self.on_attrs(e, &name, &[])?;
+                }
+                self.extra_element = Some(ExtraElement {
+                    name,
+                    depth: 0,
+                    value: vec![],
+                    is_kept,
+                });
+                return Ok(());
+            }
+            Err(_) => return Err(ItemParseError::UnknownTag(e.name().into_inner().to_owned())),
+        };
+        match self.state {
+            State::Idle | State::WaitTagValue(_) => {
+                return Err(ItemParseError::StateMismatch(format!(
+                    "expect not {:?}",
+                    self.state
+                )));
+            }
+            State::WaitTag => {
+                if self.processed_item_tags.contains(&tag) {
+                    return Err(ItemParseError::DuplicateTag(tag));
+                }
+                if self.policy.mode == SchemaMode::Strict
+                    && ItemTag::iter().nth(self.tag_count) != Some(tag)
+                {
+                    return Err(ItemParseError::TagOutOfOrder(tag));
+                }
+                self.tag_count += 1;
+
+                match tag {
+                    ItemTag::Host => {
+                        self.item.host.0.ip = self.attr_or_default(tag_attr(e, tag, "ip"))?;
+                        self.on_attrs(e, "host", &["ip"])?;
+                    }
+                    ItemTag::Request => {
+                        self.item.request.0.base64 =
+                            self.attr_or_default(tag_attr_bool(e, tag, "base64"))?;
+                        self.on_attrs(e, "request", &["base64"])?;
+                    }
+                    ItemTag::Response => {
+                        self.item.response.0.base64 =
+                            self.attr_or_default(tag_attr_bool(e, tag, "base64"))?;
+                        self.on_attrs(e, "response", &["base64"])?;
+                    }
+                    _ => self.on_attrs(e, &tag.to_string(), &[])?,
+                }
+
+                self.value = None;
+                self.state = State::WaitTagValue(tag)
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check the attributes outside the DTD, `known` are those it declares.
+    fn on_attrs(
+        &mut self,
+        e: &BytesStart<'_>,
+        element: &str,
+        known: &[&str],
+    ) -> Result<(), ItemParseError> {
+        if self.policy.mode == SchemaMode::Standard {
+            return Ok(());
+        }
+
+        for attr in e.attributes().filter_map(|ret| ret.ok()) {
+            if known.iter().any(|x| x.as_bytes() == attr.key.as_ref()) {
+                continue;
+            }
+            let key = format!("{element}@{}", String::from_utf8_lossy(attr.key.as_ref()));
+            if self.policy.mode == SchemaMode::Strict {
+                return Err(ItemParseError::AttrUnknown(key));
+            }
+            let value = match attr.unescape_value() {
+                Ok(x) => x.into_owned(),
+                Err(_) => String::from_utf8_lossy(&attr.value).into_owned(),
+            };
+            self.item.extra.insert(key, value);
+        }
+
+        Ok(())
+    }
+
+    /// The DTD default of a missing attribute in [`SchemaMode::Tolerant`].
+    fn attr_or_default<T: Default>(
+        &self,
+        result: Result<T, ItemParseError>,
+    ) -> Result<T, ItemParseError> {
+        match result {
+            Err(ItemParseError::TagAttrMissing(..)) if self.policy.mode == SchemaMode::Tolerant => {
+                Ok(Default::default())
+            }
+            x => x,
+        }
+    }
+
+    fn on_end(&mut self, name: &[u8]) -> Result<Option<ItemRef<'a>>, ItemParseError> {
+        if let Some(extra) = self.extra_element.as_mut() {
+            if extra.depth > 0 {
+                extra.depth -= 1;
+            } else if let Some(extra) = self.extra_element.take().filter(|x| x.is_kept) {
+                let value = String::from_utf8_lossy(&extra.value).into_owned();
+                self.item.extra.insert(extra.name, value);
+            }
+            return Ok(None);
+        }
+
+        match name {
+            b"items" => {}
+            b"item" => {
+                self.on_missing_tags()?;
+
+                self.state = State::Idle;
+                self.processed_item_tags.clear();
+
+                return Ok(Some(mem::take(&mut self.item)));
+            }
+            _ => {
+                let tag = ItemTag::try_from(name)
+                    .map_err(|_| ItemParseError::UnknownTag(name.to_owned()))?;
+                match self.state {
+                    State::Idle | State::WaitTag => {
+                        return Err(ItemParseError::StateMismatch(format!(
+                            "expect not {:?}",
+                            self.state
+                        )));
+                    }
+                    State::WaitTagValue(wait_tag) => {
+                        if wait_tag != tag {
                             return Err(ItemParseError::StateMismatch(format!(
                                 "expect {:?} but current {:?}",
-                                State::Idle,
+                                State::WaitTagValue(tag),
                                 self.state
                             )));
                         }
 
-                        self.item = Default::default();
+                        let value = self.value.take().unwrap_or_default();
+                        self.set_value(tag, value)?;
+                        self.processed_item_tags.insert(tag);
                         self.state = State::WaitTag;
                     }
-                    _ => {
-                        if let Ok(tag) = ItemTag::try_from(e.name().as_ref()) {
-                            match self.state {
-                                State::Idle => {
-                                    return Err(ItemParseError::StateMismatch(format!(
-                                        "expect not {:?}",
-                                        self.state
-                                    )));
-                                }
-                                State::WaitTag => {
-                                    if self.processed_item_tags.contains(&tag) {
-                                        return Err(ItemParseError::DuplicateTag(tag));
-                                    }
-                                    match tag {
-                                        ItemTag::Host => {
-                                            let attrs: Vec<Attribute<'_>> =
-                                                e.attributes().filter_map(|ret| ret.ok()).collect();
-
-                                            let ip = attrs
-                                                .iter()
-                                                .find(|a| a.key.as_ref() == b"ip")
-                                                .map(|x| x.value.clone())
-                                                .ok_or_else(|| {
-                                                    ItemParseError::TagAttrMissing(
-                                                        tag.to_owned(),
-                                                        "ip".to_owned(),
-                                                    )
-                                                })?;
-
-                                            self.item.host.0.ip = ip.into_owned();
-                                        }
-                                        ItemTag::Request => {
-                                            let attrs: Vec<Attribute<'_>> =
-                                                e.attributes().filter_map(|ret| ret.ok()).collect();
-
-                                            let base64 = attrs
-                                                .iter()
-                                                .find(|a| a.key.as_ref() == b"base64")
-                                                .map(|x| x.value.clone())
-                                                .ok_or_else(|| {
-                                                    ItemParseError::TagAttrMissing(
-                                                        tag.to_owned(),
-                                                        "base64".to_owned(),
-                                                    )
-                                                })?;
-
-                                            let base64 =
-                                                str::from_utf8(base64.as_ref()).map_err(|err| {
-                                                    ItemParseError::TagAttrInvalid(
-                                                        tag.to_owned(),
-                                                        "base64".to_owned(),
-                                                        err.to_string(),
-                                                    )
-                                                })?;
-
-                                            let base64: bool =
-                                                base64.parse().map_err(|err: ParseBoolError| {
-                                                    ItemParseError::TagAttrInvalid(
-                                                        tag.to_owned(),
-                                                        "base64".to_owned(),
-                                                        err.to_string(),
-                                                    )
-                                                })?;
-
-                                            self.item.request.0.base64 = base64;
-                                        }
-                                        ItemTag::Response => {
-                                            let attrs: Vec<Attribute<'_>> =
-                                                e.attributes().filter_map(|ret| ret.ok()).collect();
-
-                                            let base64 = attrs
-                                                .iter()
-                                                .find(|a| a.key.as_ref() == b"base64")
-                                                .map(|x| x.value.clone())
-                                                .ok_or_else(|| {
-                                                    ItemParseError::TagAttrMissing(
-                                                        tag.to_owned(),
-                                                        "base64".to_owned(),
-                                                    )
-                                                })?;
-
-                                            let base64 =
-                                                str::from_utf8(base64.as_ref()).map_err(|err| {
-                                                    ItemParseError::TagAttrInvalid(
-                                                        tag.to_owned(),
-                                                        "base64".to_owned(),
-                                                        err.to_string(),
-                                                    )
-                                                })?;
-
-                                            let base64: bool =
-                                                base64.parse().map_err(|err: ParseBoolError| {
-                                                    ItemParseError::TagAttrInvalid(
-                                                        tag.to_owned(),
-                                                        "base64".to_owned(),
-                                                        err.to_string(),
-                                                    )
-                                                })?;
-
-                                            self.item.response.0.base64 = base64;
-                                        }
-                                        _ => {}
-                                    }
-
-                                    self.state = State::WaitTagValue(tag)
-                                }
-                                State::WaitTagValue(_) => {
-                                    return Err(ItemParseError::StateMismatch(format!(
-                                        "expect not {:?}",
-                                        self.state
-                                    )));
-                                }
-                            }
-                        } else {
-                            return Err(ItemParseError::UnknownTag(
-                                e.name().into_inner().to_owned(),
-                            ));
-                        }
-                    }
-                },
-                Ok(Event::End(e)) => match e.name().as_ref() {
-                    b"items" => {}
-                    b"item" => {
-                        let unprocessed_item_tags = ITEM_TAG_SET
-                            .difference(&self.processed_item_tags)
-                            .collect::<HashSet<_>>();
-
-                        if !unprocessed_item_tags.is_empty() {
-                            return Err(ItemParseError::SomeTagsMissing(
-                                unprocessed_item_tags
-                                    .into_iter()
-                                    .map(|x| x.to_owned())
-                                    .collect(),
-                            ));
-                        }
+                }
+            }
+        }
 
-                        self.state = State::Idle;
-                        self.processed_item_tags.clear();
+        Ok(None)
+    }
 
-                        return Ok(self.item.to_owned());
-                    }
-                    _ => {
-                        if let Ok(tag) = ItemTag::try_from(e.name().as_ref()) {
-                            match self.state {
-                                State::Idle => {
-                                    return Err(ItemParseError::StateMismatch(format!(
-                                        "expect not {:?}",
-                                        self.state
-                                    )));
-                                }
-                                State::WaitTag => {
-                                    return Err(ItemParseError::StateMismatch(format!(
-                                        "expect not {:?}",
-                                        self.state
-                                    )));
-                                }
-                                State::WaitTagValue(ref wait_tag) => {
-                                    #[allow(clippy::collapsible_else_if)]
-                                    if wait_tag == &tag {
-                                        if self.processed_item_tags.contains(&tag) {
-                                            self.state = State::WaitTag;
-                                        } else {
-                                            // TODO,
-                                            if tag != ItemTag::Comment {
-                                                return Err(ItemParseError::TagValueMissing(
-                                                    wait_tag.to_owned(),
-                                                ));
-                                            }
-                                        }
-                                    } else {
-                                        return Err(ItemParseError::StateMismatch(format!(
-                                            "expect {:?} but current {:?}",
-                                            State::WaitTagValue(tag),
-                                            self.state
-                                        )));
-                                    }
-                                }
-                            }
-                        } else {
-                            return Err(ItemParseError::UnknownTag(
-                                e.name().into_inner().to_owned(),
-                            ));
-                        }
-                    }
-                },
-                Ok(Event::Text(e)) => match self.state {
-                    State::Idle => {}
-                    State::WaitTag => {}
-                    State::WaitTagValue(ref tag) => {
-                        let bytes = e.as_ref();
-                        match tag {
-                            ItemTag::Time => {
-                                let time = NaiveDateTime::parse_from_str(
-                                    str::from_utf8(bytes).map_err(|err| {
-                                        ItemParseError::TagValueInvalid(
-                                            tag.to_owned(),
-                                            err.to_string(),
-                                        )
-                                    })?,
-                                    "%a %b %d %T %Z %Y",
-                                )
-                                .map_err(|err| {
-                                    ItemParseError::TagValueInvalid(tag.to_owned(), err.to_string())
-                                })?;
-
-                                self.item.time = time;
-
-                                self.processed_item_tags.insert(tag.to_owned());
-                            }
-                            ItemTag::Host => {
-                                self.item.host.1 =
-                                    String::from_utf8(bytes.to_vec()).map_err(|err| {
-                                        ItemParseError::TagValueInvalid(
-                                            tag.to_owned(),
-                                            err.to_string(),
-                                        )
-                                    })?;
-
-                                self.processed_item_tags.insert(tag.to_owned());
-                            }
-                            ItemTag::Port => {
-                                let port: u16 = str::from_utf8(bytes)
-                                    .map_err(|err| {
-                                        ItemParseError::TagValueInvalid(
-                                            tag.to_owned(),
-                                            err.to_string(),
-                                        )
-                                    })?
-                                    .parse()
-                                    .map_err(|err: ParseIntError| {
-                                        ItemParseError::TagValueInvalid(
-                                            tag.to_owned(),
-                                            err.to_string(),
-                                        )
-                                    })?;
-
-                                self.item.port = port;
-
-                                self.processed_item_tags.insert(tag.to_owned());
-                            }
-                            ItemTag::Protocol => {
-                                let protocol: Scheme = str::from_utf8(bytes)
-                                    .map_err(|err| {
-                                        ItemParseError::TagValueInvalid(
-                                            tag.to_owned(),
-                                            err.to_string(),
-                                        )
-                                    })?
-                                    .parse()
-                                    .map_err(|err: InvalidUri| {
-                                        ItemParseError::TagValueInvalid(
-                                            tag.to_owned(),
-                                            err.to_string(),
-                                        )
-                                    })?;
-
-                                self.item.protocol = protocol;
-
-                                self.processed_item_tags.insert(tag.to_owned());
-                            }
-                            ItemTag::Extension => {
-                                self.item.extension = if bytes == b"null" {
-                                    None
-                                } else {
-                                    Some(String::from_utf8(bytes.to_vec()).map_err(|err| {
-                                        ItemParseError::TagValueInvalid(
-                                            tag.to_owned(),
-                                            err.to_string(),
-                                        )
-                                    })?)
-                                };
-
-                                self.processed_item_tags.insert(tag.to_owned());
-                            }
-                            ItemTag::Status => {
-                                let status = StatusCode::from_bytes(bytes).map_err(|err| {
-                                    ItemParseError::TagValueInvalid(tag.to_owned(), err.to_string())
-                                })?;
-
-                                self.item.status = status;
-
-                                self.processed_item_tags.insert(tag.to_owned());
-                            }
-                            ItemTag::ResponseLength => {
-                                let response_length: u32 = str::from_utf8(bytes)
-                                    .map_err(|err| {
-                                        ItemParseError::TagValueInvalid(
-                                            tag.to_owned(),
-                                            err.to_string(),
-                                        )
-                                    })?
-                                    .parse()
-                                    .map_err(|err: ParseIntError| {
-                                        ItemParseError::TagValueInvalid(
-                                            tag.to_owned(),
-                                            err.to_string(),
-                                        )
-                                    })?;
-
-                                self.item.response_length = response_length;
-
-                                self.processed_item_tags.insert(tag.to_owned());
-                            }
-                            ItemTag::Mimetype => {
-                                self.item.mimetype =
-                                    String::from_utf8(bytes.to_vec()).map_err(|err| {
-                                        ItemParseError::TagValueInvalid(
-                                            tag.to_owned(),
-                                            err.to_string(),
-                                        )
-                                    })?;
-
-                                self.processed_item_tags.insert(tag.to_owned());
-                            }
-                            ItemTag::Comment => {
-                                // TODO, why comment is b"\r\n  "
-                                self.item.comment = if bytes.is_empty() || bytes == b"\r\n  " {
-                                    None
-                                } else {
-                                    Some(String::from_utf8(bytes.to_vec()).map_err(|err| {
-                                        ItemParseError::TagValueInvalid(
-                                            tag.to_owned(),
-                                            err.to_string(),
-                                        )
-                                    })?)
-                                };
-
-                                self.processed_item_tags.insert(tag.to_owned());
-                            }
-                            _ => {}
-                        }
-                    }
-                },
-                Ok(Event::CData(e)) => match self.state {
-                    State::Idle => {}
-                    State::WaitTag => {}
-                    State::WaitTagValue(ref tag) => {
-                        let bytes_text = e.escape()?;
-                        let bytes = bytes_text.as_ref();
-                        match tag {
-                            ItemTag::Request => {
-                                self.item.request.1 = bytes.to_vec();
-
-                                self.processed_item_tags.insert(tag.to_owned());
-                            }
-                            ItemTag::Response => {
-                                self.item.response.1 = bytes.to_vec();
-
-                                self.processed_item_tags.insert(tag.to_owned());
-                            }
-                            ItemTag::Url => {
-                                let url = String::from_utf8(bytes.to_vec()).map_err(|err| {
-                                    ItemParseError::TagValueInvalid(tag.to_owned(), err.to_string())
-                                })?;
-
-                                self.item.url = url;
-
-                                self.processed_item_tags.insert(tag.to_owned());
-                            }
-                            ItemTag::Method => {
-                                let method = Method::from_bytes(bytes).map_err(|err| {
-                                    ItemParseError::TagValueInvalid(tag.to_owned(), err.to_string())
-                                })?;
-
-                                self.item.method = method;
-
-                                self.processed_item_tags.insert(tag.to_owned());
-                            }
-                            ItemTag::Path => {
-                                let path = String::from_utf8(bytes.to_vec()).map_err(|err| {
-                                    ItemParseError::TagValueInvalid(tag.to_owned(), err.to_string())
-                                })?;
-
-                                self.item.path = path;
-                                self.processed_item_tags.insert(tag.to_owned());
-                            }
-                            _ => {}
-                        }
-                    }
-                },
-                Err(err) => return Err(ItemParseError::XmlError(err)),
-                Ok(Event::Eof) => return Err(ItemParseError::UnexpectedEof),
-                _ => {}
+    /// Set the missing tags from their defaults, the others are an error unless the
+    /// schema is tolerant.
+    fn on_missing_tags(&mut self) -> Result<(), ItemParseError> {
+        let unprocessed_item_tags = ITEM_TAG_SET
+            .difference(&self.processed_item_tags)
+            .copied()
+            .collect::<Vec<_>>();
+
+        let mut missing_item_tags = HashSet::new();
+        for tag in unprocessed_item_tags {
+            match self.default_value(tag) {
+                Some(x) => self.set_value(tag, Cow::Owned(x.into_bytes()))?,
+                None if self.policy.mode == SchemaMode::Tolerant => {}
+                None => {
+                    missing_item_tags.insert(tag);
+                }
             }
+        }
 
-            self.buf.clear();
+        if !missing_item_tags.is_empty() {
+            return Err(ItemParseError::SomeTagsMissing(missing_item_tags));
         }
+        Ok(())
     }
-}
 
-impl<R> Iterator for Items<R>
-where
-    R: BufRead,
-{
-    type Item = Result<Item, ItemParseError>;
+    fn default_value(&self, tag: ItemTag) -> Option<String> {
+        match self.policy.mode {
+            SchemaMode::Strict => None,
+            _ => self.policy.defaults.get(&tag).cloned(),
+        }
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        match self.item() {
+    fn on_text(&mut self, e: BytesText<'a>) -> Result<(), ItemParseError> {
+        match (self.extra_element.is_some(), self.state) {
+            (true, _) => {
+                let text = e.unescape()?;
+                self.on_value(cow_bytes(text));
+            }
+            (false, State::WaitTagValue(tag)) => {
+                let text = e
+                    .unescape()
+                    .map_err(|err| ItemParseError::TagValueInvalid(tag, err.to_string()))?;
+                self.on_value(cow_bytes(text));
+            }
+            // Whitespace between elements.
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Append text or CDATA to the content of the current element.
+    fn on_value(&mut self, bytes: Cow<'a, [u8]>) {
+        if let Some(extra) = self.extra_element.as_mut() {
+            extra.value.extend_from_slice(&bytes);
+            return;
+        }
+        if let State::WaitTagValue(_) = self.state {
+            self.value = Some(match self.value.take() {
+                Some(x) => {
+                    let mut x = x.into_owned();
+                    x.extend_from_slice(&bytes);
+                    Cow::Owned(x)
+                }
+                None => bytes,
+            });
+        }
+    }
+
+    fn set_value(&mut self, tag: ItemTag, bytes: Cow<'a, [u8]>) -> Result<(), ItemParseError> {
+        let value_invalid =
+            |err: &dyn ToString| ItemParseError::TagValueInvalid(tag, err.to_string());
+
+        // Tags without an empty value, the others are set empty when tolerant.
+        let is_typed = matches!(
+            tag,
+            ItemTag::Time
+                | ItemTag::Port
+                | ItemTag::Protocol
+                | ItemTag::Method
+                | ItemTag::Status
+                | ItemTag::ResponseLength
+        );
+        let bytes = if bytes.is_empty() {
+            match self.default_value(tag).filter(|x| !x.is_empty()) {
+                Some(x) => Cow::Owned(x.into_bytes()),
+                None if self.policy.mode == SchemaMode::Tolerant && is_typed => return Ok(()),
+                None if self.policy.mode == SchemaMode::Tolerant || tag == ItemTag::Comment => {
+                    bytes
+                }
+                None => return Err(ItemParseError::TagValueMissing(tag)),
+            }
+        } else {
+            bytes
+        };
+
+        match tag {
+            ItemTag::Request => {
+                self.item.request.1 = bytes;
+                return Ok(());
+            }
+            ItemTag::Response => {
+                self.item.response.1 = bytes;
+                return Ok(());
+            }
+            ItemTag::Method => {
+                self.item.method = Method::from_bytes(&bytes).map_err(|err| value_invalid(&err))?;
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        let text = cow_str(bytes).map_err(|err| value_invalid(&err))?;
+        match tag {
+            ItemTag::Time => {
+                self.item.time = NaiveDateTime::parse_from_str(&text, "%a %b %d %T %Z %Y")
+                    .map_err(|err| value_invalid(&err))?;
+            }
+            ItemTag::Url => {
+                self.item.url = text;
+            }
+            ItemTag::Host => {
+                self.item.host.1 = text;
+            }
+            ItemTag::Port => {
+                self.item.port = text
+                    .parse()
+                    .map_err(|err: ParseIntError| value_invalid(&err))?;
+            }
+            ItemTag::Protocol => {
+                self.item.protocol = text
+                    .parse::<Scheme>()
+                    .map_err(|err: InvalidUri| value_invalid(&err))?;
+            }
+            ItemTag::Path => {
+                self.item.path = text;
+            }
+            ItemTag::Extension => {
+                self.item.extension = if text.is_empty() || text == "null" {
+                    None
+                } else {
+                    Some(text)
+                };
+            }
+            ItemTag::Status => {
+                self.item.status =
+                    StatusCode::from_bytes(text.as_bytes()).map_err(|err| value_invalid(&err))?;
+            }
+            ItemTag::ResponseLength => {
+                self.item.response_length = text
+                    .parse()
+                    .map_err(|err: ParseIntError| value_invalid(&err))?;
+            }
+            ItemTag::Mimetype => {
+                self.item.mimetype = text;
+            }
+            ItemTag::Comment => {
+                self.item.comment = if text.is_empty() { None } else { Some(text) };
+            }
+            ItemTag::Request | ItemTag::Response | ItemTag::Method => {}
+        }
+
+        Ok(())
+    }
+
+    /// Map the result of reading an item to the next value of an iterator.
+    fn next_result<T>(
+        &mut self,
+        result: Result<T, ItemParseError>,
+    ) -> Option<Result<T, ItemParseError>> {
+        match result {
             Ok(item) => Some(Ok(item)),
             Err(err) => match err {
                 ItemParseError::UnexpectedEof => {
@@ -601,11 +747,65 @@ where
     }
 }
 
+fn tag_attr(e: &BytesStart<'_>, tag: ItemTag, key: &str) -> Result<Vec<u8>, ItemParseError> {
+    e.attributes()
+        .filter_map(|ret| ret.ok())
+        .find(|a| a.key.as_ref() == key.as_bytes())
+        .map(|x| x.value.into_owned())
+        .ok_or_else(|| ItemParseError::TagAttrMissing(tag, key.to_owned()))
+}
+
+fn tag_attr_bool(e: &BytesStart<'_>, tag: ItemTag, key: &str) -> Result<bool, ItemParseError> {
+    let value = tag_attr(e, tag, key)?;
+    let value = str::from_utf8(&value)
+        .map_err(|err| ItemParseError::TagAttrInvalid(tag, key.to_owned(), err.to_string()))?;
+    value.parse().map_err(|err: ParseBoolError| {
+        ItemParseError::TagAttrInvalid(tag, key.to_owned(), err.to_string())
+    })
+}
+
+fn cow_bytes(text: Cow<'_, str>) -> Cow<'_, [u8]> {
+    match text {
+        Cow::Borrowed(x) => Cow::Borrowed(x.as_bytes()),
+        Cow::Owned(x) => Cow::Owned(x.into_bytes()),
+    }
+}
+
+fn cow_str(bytes: Cow<'_, [u8]>) -> Result<Cow<'_, str>, str::Utf8Error> {
+    match bytes {
+        Cow::Borrowed(x) => str::from_utf8(x).map(Cow::Borrowed),
+        Cow::Owned(x) => String::from_utf8(x)
+            .map(Cow::Owned)
+            .map_err(|err| err.utf8_error()),
+    }
+}
+
+impl<R> Iterator for Items<R>
+where
+    R: BufRead,
+{
+    type Item = Result<Item, ItemParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = self.item();
+        self.parser.next_result(result)
+    }
+}
+
+impl<'a> Iterator for ItemRefs<'a> {
+    type Item = Result<ItemRef<'a>, ItemParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = self.item();
+        self.parser.next_result(result)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    use std::{error, fs::File, io::BufReader};
+    use std::{error, fs, fs::File, io::BufReader};
 
     use chrono::NaiveDate;
 
@@ -880,4 +1080,207 @@ mod tests {
 
         Ok(())
     }
+
+    fn read_fixture() -> Result<String, Box<dyn error::Error>> {
+        Ok(fs::read_to_string(
+            "tests/http_history_files/burpsuite_community_v2021.3.2.xml",
+        )?)
+    }
+
+    #[test]
+    fn test_empty_comment() -> Result<(), Box<dyn error::Error>> {
+        let xml = read_fixture()?.replace("\r\n", "\n");
+        for item in ItemRefs::from_slice(xml.as_bytes())? {
+            assert_eq!(item?.comment, None);
+        }
+
+        let xml = xml.replace("<comment></comment>", "<comment>a &amp; b</comment>");
+        for item in ItemRefs::from_slice(xml.as_bytes())? {
+            assert_eq!(item?.comment.as_deref(), Some("a & b"));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_recover() -> Result<(), Box<dyn error::Error>> {
+        let xml = read_fixture()?.replacen("<port>80</port>", "<port>abc</port>", 1);
+
+        let items = ItemRefs::from_slice(xml.as_bytes())?.collect::<Vec<_>>();
+        assert_eq!(items.len(), 2);
+        assert!(matches!(
+            items[0],
+            Err(ItemParseError::TagValueInvalid(ItemTag::Port, _))
+        ));
+        assert_eq!(items[1].as_ref().map(|x| x.port).ok(), Some(443));
+
+        let items = Items::from_reader(xml.as_bytes())?.collect::<Vec<_>>();
+        assert_eq!(items.len(), 2);
+        assert!(items[0].is_err());
+        assert_eq!(items[1].as_ref().map(|x| x.port).ok(), Some(443));
+
+        // Failing on `</item>` does not skip the next item.
+        let xml = read_fixture()?.replacen("<comment></comment>", "", 1);
+        let items = Items::from_reader(xml.as_bytes())?.collect::<Vec<_>>();
+        assert_eq!(items.len(), 2);
+        assert!(matches!(items[0], Err(ItemParseError::SomeTagsMissing(_))));
+        assert!(items[1].is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_schema_strict() -> Result<(), Box<dyn error::Error>> {
+        let xml = read_fixture()?;
+        let strict = SchemaPolicy::new(SchemaMode::Strict);
+        let items = ItemRefs::from_slice(xml.as_bytes())?.with_policy(strict.to_owned());
+        assert_eq!(items.collect::<Result<Vec<_>, _>>()?.len(), 2);
+
+        let url = "<url><![CDATA[http://httpbin.org/get?foo=bar]]></url>";
+        let host = r#"<host ip="34.199.75.4">httpbin.org</host>"#;
+        let reordered = xml.replacen(
+            &format!("{url}\r\n    {host}"),
+            &format!("{host}\r\n    {url}"),
+            1,
+        );
+        assert_ne!(reordered, xml);
+        let mut items = ItemRefs::from_slice(reordered.as_bytes())?.with_policy(strict.to_owned());
+        assert!(matches!(
+            items.next(),
+            Some(Err(ItemParseError::TagOutOfOrder(ItemTag::Host)))
+        ));
+        let mut items = ItemRefs::from_slice(reordered.as_bytes())?;
+        assert!(items.next().ok_or("item missing")?.is_ok());
+
+        let xml = xml.replace(
+            r#"<response base64="true">"#,
+            r#"<response base64="true" responseTime="12">"#,
+        );
+        let mut items = ItemRefs::from_slice(xml.as_bytes())?.with_policy(strict);
+        match items.next() {
+            Some(Err(ItemParseError::AttrUnknown(x))) => assert_eq!(x, "response@responseTime"),
+            x => panic!("{x:?}"),
+        }
+        assert!(ItemRefs::from_slice(xml.as_bytes())?
+            .next()
+            .ok_or("item missing")?
+            .is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_schema_tolerant() -> Result<(), Box<dyn error::Error>> {
+        let xml = read_fixture()?
+            .replace(
+                r#"<response base64="true">"#,
+                r#"<response base64="true" responseTime="12">"#,
+            )
+            .replace(
+                "<comment></comment>",
+                "<highlight>red</highlight><edited/><notes><a>x</a>y</notes>",
+            )
+            .replace("<extension>null</extension>", "");
+
+        let mut items = Items::from_reader(xml.as_bytes())?;
+        assert!(matches!(
+            items.next(),
+            Some(Err(ItemParseError::UnknownTag(_)))
+        ));
+
+        let tolerant = SchemaPolicy::new(SchemaMode::Tolerant);
+        let items = Items::from_reader(xml.as_bytes())?
+            .with_policy(tolerant)
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(items.len(), 2);
+        let item = &items[0];
+        assert_eq!(item.url, "http://httpbin.org/get?foo=bar");
+        assert_eq!(item.extension, None);
+        assert_eq!(item.comment, None);
+        assert_eq!(
+            item.extra
+                .iter()
+                .map(|(k, v)| (k.as_str(), v.as_str()))
+                .collect::<Vec<_>>(),
+            [
+                ("edited", ""),
+                ("highlight", "red"),
+                ("notes", "xy"),
+                ("response@responseTime", "12"),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_schema_defaults() -> Result<(), Box<dyn error::Error>> {
+        let xml = read_fixture()?
+            .replace("<comment></comment>", "")
+            .replace("<status>200</status>", "<status></status>");
+
+        let mut items = ItemRefs::from_slice(xml.as_bytes())?;
+        assert!(matches!(
+            items.next(),
+            Some(Err(ItemParseError::TagValueMissing(ItemTag::Status)))
+        ));
+
+        let policy = SchemaPolicy::default()
+            .with_default(ItemTag::Status, "0")
+            .with_default(ItemTag::Comment, "");
+        let mut items = ItemRefs::from_slice(xml.as_bytes())?.with_policy(policy);
+        match items.next() {
+            Some(Err(ItemParseError::TagValueInvalid(ItemTag::Status, _))) => {}
+            x => panic!("{x:?}"),
+        }
+
+        let policy = SchemaPolicy::default()
+            .with_default(ItemTag::Status, "204")
+            .with_default(ItemTag::Comment, "");
+        let items = ItemRefs::from_slice(xml.as_bytes())?
+            .with_policy(policy)
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(items[0].status, StatusCode::NO_CONTENT);
+        assert_eq!(items[0].comment, None);
+
+        let policy = SchemaPolicy::default()
+            .with_default(ItemTag::Status, "204")
+            .with_default(ItemTag::Comment, "")
+            .with_default(ItemTag::Mimetype, "text");
+        let empty = xml.replace("<mimetype>JSON</mimetype>", "<mimetype></mimetype>");
+        let items = ItemRefs::from_slice(empty.as_bytes())?
+            .with_policy(policy)
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(items[0].mimetype, "text");
+
+        // Only comment may be empty unless tolerant.
+        let empty = xml.replace(
+            "<url><![CDATA[http://httpbin.org/get?foo=bar]]></url>",
+            "<url></url>",
+        );
+        let mut items = ItemRefs::from_slice(empty.as_bytes())?
+            .with_policy(SchemaPolicy::default().with_default(ItemTag::Status, "204"));
+        assert!(matches!(
+            items.next(),
+            Some(Err(ItemParseError::TagValueMissing(ItemTag::Url)))
+        ));
+        let items = ItemRefs::from_slice(empty.as_bytes())?
+            .with_policy(SchemaPolicy::new(SchemaMode::Tolerant))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(items[0].url, "");
+        assert_eq!(items[0].status, StatusCode::default());
+
+        let xml = xml
+            .replace("<time>", "<time x=\"\">")
+            .replace("<status></status>", "");
+        let mut items = ItemRefs::from_slice(xml.as_bytes())?;
+        match items.next() {
+            Some(Err(ItemParseError::SomeTagsMissing(x))) => {
+                assert_eq!(x, HashSet::from([ItemTag::Status, ItemTag::Comment]));
+            }
+            x => panic!("{x:?}"),
+        }
+
+        Ok(())
+    }
 }
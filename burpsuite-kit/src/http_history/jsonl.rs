@@ -0,0 +1,505 @@
+use core::str::FromStr as _;
+use std::{
+    collections::BTreeMap,
+    io::{BufRead, Error as IoError, Write},
+};
+
+use base64::{engine::general_purpose, DecodeError, Engine as _};
+use chrono::{DateTime, NaiveDateTime, SecondsFormat};
+use http::{uri::Scheme, Method, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    item::{Item, ItemHostAttr, ItemRequestAttr, ItemResponseAttr},
+    items::ItemsAttr,
+    message::Message,
+};
+
+/// Version of the record layout, bumped on any change. 2 added `extra`.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// How request and response payloads are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PayloadFormat {
+    /// The exact bytes, base64-encoded. Lossless.
+    #[default]
+    Base64,
+    /// Start line, headers and body as separate fields, for indexing.
+    ///
+    /// Messages with header values that are not UTF-8 are written as in `Base64`.
+    ///
+    /// Line endings are written back as CRLF, so bytes may differ from the original
+    /// for messages Burp stored with bare LF.
+    Parsed,
+}
+
+/// One line of an NDJSON export.
+///
+/// An export starts with an optional `items_attr` record followed by one `item`
+/// record per line.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "record", rename_all = "snake_case")]
+pub enum JsonlRecord {
+    ItemsAttr(JsonlItemsAttr),
+    Item(Box<JsonlItem>),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonlItemsAttr {
+    pub schema_version: u32,
+    pub burp_version: String,
+    /// RFC 3339, UTC.
+    pub export_time: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonlItem {
+    pub schema_version: u32,
+    /// RFC 3339, UTC.
+    pub time: String,
+    pub url: String,
+    pub host: String,
+    /// Resolved address, empty when Burp did not record one.
+    pub ip: String,
+    pub port: u16,
+    /// `http` or `https`.
+    pub protocol: String,
+    pub method: String,
+    pub path: String,
+    pub extension: Option<String>,
+    pub status: u16,
+    pub response_length: u32,
+    pub mimetype: String,
+    pub comment: Option<String>,
+    pub request: JsonlPayload,
+    pub response: JsonlPayload,
+    /// `Item::extra`, missing before schema version 2.
+    #[serde(default)]
+    pub extra: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonlPayload {
+    /// `base64` attribute of the Burp element, kept for a byte-exact XML round trip.
+    pub burp_base64: bool,
+    #[serde(flatten)]
+    pub content: JsonlPayloadContent,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum JsonlPayloadContent {
+    Raw {
+        /// Base64 of the decoded message bytes.
+        raw: String,
+    },
+    Parsed {
+        start_line: String,
+        headers: Vec<(String, String)>,
+        /// UTF-8 text, or base64 when `body_base64` is set.
+        body: String,
+        body_base64: bool,
+    },
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum JsonlError {
+    #[error("IoError {0:?}")]
+    IoError(#[from] IoError),
+    #[error("JsonError {0}")]
+    JsonError(#[from] serde_json::Error),
+    #[error("Base64Invalid {0}")]
+    Base64Invalid(#[from] DecodeError),
+    #[error("SchemaVersionUnsupported {0}")]
+    SchemaVersionUnsupported(u32),
+    #[error("FieldInvalid {0} {1}")]
+    FieldInvalid(&'static str, String),
+    #[error("RecordUnexpected {0}")]
+    RecordUnexpected(usize),
+}
+
+//
+pub fn format_time(time: &NaiveDateTime) -> String {
+    time.and_utc().to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+pub fn parse_time(s: &str) -> Result<NaiveDateTime, JsonlError> {
+    DateTime::parse_from_rfc3339(s)
+        .map(|x| x.naive_utc())
+        .map_err(|err| JsonlError::FieldInvalid("time", err.to_string()))
+}
+
+impl JsonlItemsAttr {
+    pub fn new(attr: &ItemsAttr) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            burp_version: attr.burp_version.to_owned(),
+            export_time: format_time(&attr.export_time),
+        }
+    }
+
+    pub fn to_items_attr(&self) -> Result<ItemsAttr, JsonlError> {
+        check_schema_version(self.schema_version)?;
+        Ok(ItemsAttr {
+            burp_version: self.burp_version.to_owned(),
+            export_time: parse_time(&self.export_time)?,
+        })
+    }
+}
+
+impl JsonlItem {
+    pub fn new(item: &Item, format: PayloadFormat) -> Result<Self, JsonlError> {
+        Ok(Self {
+            schema_version: SCHEMA_VERSION,
+            time: format_time(&item.time),
+            url: item.url.to_owned(),
+            host: item.host.1.to_owned(),
+            ip: String::from_utf8_lossy(&item.host.0.ip).into_owned(),
+            port: item.port,
+            protocol: item.protocol.to_string(),
+            method: item.method.to_string(),
+            path: item.path.to_owned(),
+            extension: item.extension.to_owned(),
+            status: item.status.as_u16(),
+            response_length: item.response_length,
+            mimetype: item.mimetype.to_owned(),
+            comment: item.comment.to_owned(),
+            request: JsonlPayload::new(item.request.0.base64, &item.request_bytes()?, format),
+            response: JsonlPayload::new(item.response.0.base64, &item.response_bytes()?, format),
+            extra: item.extra.to_owned(),
+        })
+    }
+
+    pub fn to_item(&self) -> Result<Item, JsonlError> {
+        check_schema_version(self.schema_version)?;
+        let invalid = |field, err: &dyn ToString| JsonlError::FieldInvalid(field, err.to_string());
+
+        Ok(Item {
+            time: parse_time(&self.time)?,
+            url: self.url.to_owned(),
+            host: (
+                ItemHostAttr {
+                    ip: self.ip.as_bytes().to_vec(),
+                },
+                self.host.to_owned(),
+            ),
+            port: self.port,
+            protocol: Scheme::from_str(&self.protocol).map_err(|err| invalid("protocol", &err))?,
+            method: Method::from_str(&self.method).map_err(|err| invalid("method", &err))?,
+            path: self.path.to_owned(),
+            extension: self.extension.to_owned(),
+            request: (
+                ItemRequestAttr {
+                    base64: self.request.burp_base64,
+                },
+                self.request.to_burp_bytes()?,
+            ),
+            status: StatusCode::from_u16(self.status).map_err(|err| invalid("status", &err))?,
+            response_length: self.response_length,
+            mimetype: self.mimetype.to_owned(),
+            response: (
+                ItemResponseAttr {
+                    base64: self.response.burp_base64,
+                },
+                self.response.to_burp_bytes()?,
+            ),
+            comment: self.comment.to_owned(),
+            extra: self.extra.to_owned(),
+        })
+    }
+}
+
+impl JsonlPayload {
+    pub fn new(burp_base64: bool, bytes: &[u8], format: PayloadFormat) -> Self {
+        let parsed = match format {
+            PayloadFormat::Base64 => None,
+            PayloadFormat::Parsed => Message::parse(bytes).ok().filter(|x| {
+                x.headers
+                    .iter()
+                    .all(|x| core::str::from_utf8(x.value).is_ok())
+            }),
+        };
+        let content = match parsed {
+            Some(message) => {
+                let (body, body_base64) = match core::str::from_utf8(message.body) {
+                    Ok(x) => (x.to_owned(), false),
+                    Err(_) => (general_purpose::STANDARD.encode(message.body), true),
+                };
+                JsonlPayloadContent::Parsed {
+                    start_line: message.start_line.to_owned(),
+                    headers: message
+                        .headers
+                        .iter()
+                        .map(|x| {
+                            (
+                                x.name.to_owned(),
+                                String::from_utf8_lossy(x.value).into_owned(),
+                            )
+                        })
+                        .collect(),
+                    body,
+                    body_base64,
+                }
+            }
+            None => JsonlPayloadContent::Raw {
+                raw: general_purpose::STANDARD.encode(bytes),
+            },
+        };
+        Self {
+            burp_base64,
+            content,
+        }
+    }
+
+    /// Decoded message bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, JsonlError> {
+        match &self.content {
+            JsonlPayloadContent::Raw { raw } => Ok(general_purpose::STANDARD.decode(raw)?),
+            JsonlPayloadContent::Parsed {
+                start_line,
+                headers,
+                body,
+                body_base64,
+            } => {
+                if start_line.is_empty() && headers.is_empty() && body.is_empty() {
+                    return Ok(vec![]);
+                }
+                let mut bytes = format!("{start_line}\r\n").into_bytes();
+                for (name, value) in headers.iter() {
+                    bytes.extend(format!("{name}: {value}\r\n").as_bytes());
+                }
+                bytes.extend(b"\r\n");
+                if *body_base64 {
+                    bytes.extend(general_purpose::STANDARD.decode(body)?);
+                } else {
+                    bytes.extend(body.as_bytes());
+                }
+                Ok(bytes)
+            }
+        }
+    }
+
+    /// Bytes as stored in `Item`, base64-encoded again when `burp_base64` is set.
+    fn to_burp_bytes(&self) -> Result<Vec<u8>, JsonlError> {
+        let bytes = self.to_bytes()?;
+        Ok(if self.burp_base64 {
+            general_purpose::STANDARD.encode(bytes).into_bytes()
+        } else {
+            bytes
+        })
+    }
+}
+
+fn check_schema_version(version: u32) -> Result<(), JsonlError> {
+    if version == 0 || version > SCHEMA_VERSION {
+        return Err(JsonlError::SchemaVersionUnsupported(version));
+    }
+    Ok(())
+}
+
+/// Writes items as NDJSON, one record per line.
+pub struct JsonlWriter<W>
+where
+    W: Write,
+{
+    writer: W,
+    format: PayloadFormat,
+}
+
+impl<W> JsonlWriter<W>
+where
+    W: Write,
+{
+    pub fn new(writer: W, format: PayloadFormat) -> Self {
+        Self { writer, format }
+    }
+
+    /// Write the leading `items_attr` record, needed to rebuild the Burp XML header.
+    pub fn write_attr(&mut self, attr: &ItemsAttr) -> Result<(), JsonlError> {
+        self.write_record(&JsonlRecord::ItemsAttr(JsonlItemsAttr::new(attr)))
+    }
+
+    pub fn write_item(&mut self, item: &Item) -> Result<(), JsonlError> {
+        let record = JsonlRecord::Item(Box::new(JsonlItem::new(item, self.format)?));
+        self.write_record(&record)
+    }
+
+    fn write_record(&mut self, record: &JsonlRecord) -> Result<(), JsonlError> {
+        serde_json::to_writer(&mut self.writer, record)?;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Reads items back from NDJSON written by `JsonlWriter`. Blank lines are skipped.
+pub struct JsonlReader<R>
+where
+    R: BufRead,
+{
+    pub attr: Option<ItemsAttr>,
+
+    reader: R,
+    line: String,
+    line_number: usize,
+    pending: Option<JsonlItem>,
+}
+
+impl<R> JsonlReader<R>
+where
+    R: BufRead,
+{
+    pub fn from_reader(reader: R) -> Result<Self, JsonlError> {
+        let mut this = Self {
+            attr: None,
+            reader,
+            line: String::new(),
+            line_number: 0,
+            pending: None,
+        };
+        match this.next_record()? {
+            Some(JsonlRecord::ItemsAttr(x)) => this.attr = Some(x.to_items_attr()?),
+            Some(JsonlRecord::Item(x)) => this.pending = Some(*x),
+            None => {}
+        }
+        Ok(this)
+    }
+
+    fn next_record(&mut self) -> Result<Option<JsonlRecord>, JsonlError> {
+        loop {
+            self.line.clear();
+            if self.reader.read_line(&mut self.line)? == 0 {
+                return Ok(None);
+            }
+            self.line_number += 1;
+            if !self.line.trim().is_empty() {
+                return Ok(Some(serde_json::from_str(&self.line)?));
+            }
+        }
+    }
+}
+
+impl<R> Iterator for JsonlReader<R>
+where
+    R: BufRead,
+{
+    type Item = Result<Item, JsonlError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(x) = self.pending.take() {
+            return Some(x.to_item());
+        }
+        match self.next_record() {
+            Ok(Some(JsonlRecord::Item(x))) => Some(x.to_item()),
+            Ok(Some(JsonlRecord::ItemsAttr(_))) => {
+                Some(Err(JsonlError::RecordUnexpected(self.line_number)))
+            }
+            Ok(None) => None,
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{fs::File, io::BufReader};
+
+    use crate::http_history::{items::Items, writer::ItemsWriter};
+
+    #[test]
+    fn test_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+        let path = "tests/http_history_files/burpsuite_community_v2021.3.2.xml";
+        let items = Items::from_reader(BufReader::new(File::open(path)?))?;
+        let attr = items.attr.to_owned();
+        let items = items.collect::<Result<Vec<_>, _>>()?;
+
+        let mut xml = ItemsWriter::new(vec![], &attr)?;
+        for item in items.iter() {
+            xml.write_item(item)?;
+        }
+        xml.finish()?;
+        let xml = xml.into_inner();
+
+        for format in [PayloadFormat::Base64, PayloadFormat::Parsed] {
+            let mut writer = JsonlWriter::new(vec![], format);
+            writer.write_attr(&attr)?;
+            for item in items.iter() {
+                writer.write_item(item)?;
+            }
+            let ndjson = writer.into_inner();
+            assert_eq!(ndjson.iter().filter(|x| **x == b'\n').count(), 3);
+
+            let mut reader = JsonlReader::from_reader(&ndjson[..])?;
+            let attr = reader.attr.take().unwrap();
+            let mut writer = ItemsWriter::new(vec![], &attr)?;
+            for item in reader {
+                writer.write_item(&item?)?;
+            }
+            writer.finish()?;
+            assert_eq!(writer.into_inner(), xml);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_record() -> Result<(), Box<dyn std::error::Error>> {
+        let mut item = Item {
+            url: "http://example.com/".to_owned(),
+            ..Default::default()
+        };
+        item.set_request_bytes("GET / HTTP/1.1\r\nHost: example.com\r\n\r\n");
+        item.set_response_bytes(b"HTTP/1.1 200 OK\r\n\r\n\xff");
+        item.extra.insert("highlight".to_owned(), "red".to_owned());
+
+        let mut value = serde_json::to_value(JsonlRecord::Item(Box::new(JsonlItem::new(
+            &item,
+            PayloadFormat::Parsed,
+        )?)))?;
+        assert_eq!(value["record"], "item");
+        assert_eq!(value["schema_version"], SCHEMA_VERSION);
+        assert_eq!(value["time"], "1970-01-01T00:00:00Z");
+        assert_eq!(value["request"]["start_line"], "GET / HTTP/1.1");
+        assert_eq!(value["request"]["headers"][0][1], "example.com");
+        assert_eq!(value["response"]["body"], "/w==");
+        assert_eq!(value["response"]["body_base64"], true);
+        assert_eq!(value["extra"]["highlight"], "red");
+
+        let line = value.to_string();
+        let read = JsonlReader::from_reader(line.as_bytes())?.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(read[0].extra, item.extra);
+
+        // Version 1 records have no extra.
+        let mut old = value.to_owned();
+        old["schema_version"] = 1.into();
+        old.as_object_mut().ok_or("not an object")?.remove("extra");
+        let line = old.to_string();
+        let read = JsonlReader::from_reader(line.as_bytes())?.collect::<Result<Vec<_>, _>>()?;
+        assert!(read[0].extra.is_empty());
+
+        let mut item = item.to_owned();
+        item.set_request_bytes(b"GET / HTTP/1.1\r\nX-A: \xff\r\n\r\n");
+        let record = JsonlItem::new(&item, PayloadFormat::Parsed)?;
+        assert!(matches!(
+            record.request.content,
+            JsonlPayloadContent::Raw { .. }
+        ));
+        assert_eq!(record.to_item()?.request_bytes()?, item.request_bytes()?);
+
+        value["schema_version"] = (SCHEMA_VERSION + 1).into();
+        let line = value.to_string();
+        let mut reader = JsonlReader::from_reader(line.as_bytes())?;
+        assert!(reader.attr.is_none());
+        assert!(matches!(
+            reader.next(),
+            Some(Err(JsonlError::SchemaVersionUnsupported(_)))
+        ));
+
+        Ok(())
+    }
+}
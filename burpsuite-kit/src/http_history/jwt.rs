@@ -0,0 +1,454 @@
+use core::{fmt, ops::Range, str};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, DecodeError, Engine as _};
+use hmac::{Hmac, Mac as _};
+use once_cell::sync::Lazy;
+use regex::bytes::Regex;
+use serde_json::{Map, Value};
+use sha2::{Sha256, Sha384, Sha512};
+
+use super::{
+    item::Item,
+    message::{fix_content_length, Message},
+};
+
+static JWT_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"eyJ[A-Za-z0-9_-]+\.eyJ[A-Za-z0-9_-]*\.[A-Za-z0-9_-]*").expect("invalid regex")
+});
+
+/// Secrets tried against HMAC-signed tokens by `Analyzer::default`.
+pub const DEFAULT_WORDLIST: &[&str] = &[
+    "",
+    "secret",
+    "secretkey",
+    "secret_key",
+    "password",
+    "123456",
+    "changeme",
+    "jwt",
+    "jwt_secret",
+    "key",
+    "test",
+    "admin",
+    "your-256-bit-secret",
+    "your-384-bit-secret",
+    "your-512-bit-secret",
+];
+
+//
+#[derive(thiserror::Error, Debug)]
+pub enum JwtError {
+    #[error("PartsInvalid")]
+    PartsInvalid,
+    #[error("Base64Invalid {0}")]
+    Base64Invalid(#[from] DecodeError),
+    #[error("JsonInvalid {0}")]
+    JsonInvalid(#[from] serde_json::Error),
+    #[error("SpanInvalid")]
+    SpanInvalid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HmacAlg {
+    HS256,
+    HS384,
+    HS512,
+}
+
+impl HmacAlg {
+    pub fn from_alg(alg: &str) -> Option<Self> {
+        match alg {
+            "HS256" => Some(Self::HS256),
+            "HS384" => Some(Self::HS384),
+            "HS512" => Some(Self::HS512),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::HS256 => "HS256",
+            Self::HS384 => "HS384",
+            Self::HS512 => "HS512",
+        }
+    }
+
+    pub fn sign(&self, key: &[u8], input: &[u8]) -> Vec<u8> {
+        macro_rules! sign {
+            ($digest:ty) => {{
+                let mut mac =
+                    Hmac::<$digest>::new_from_slice(key).expect("HMAC accepts any key length");
+                mac.update(input);
+                mac.finalize().into_bytes().to_vec()
+            }};
+        }
+        match self {
+            Self::HS256 => sign!(Sha256),
+            Self::HS384 => sign!(Sha384),
+            Self::HS512 => sign!(Sha512),
+        }
+    }
+}
+
+//
+#[derive(Debug, Clone, PartialEq)]
+pub struct Jwt {
+    pub header: Map<String, Value>,
+    pub claims: Map<String, Value>,
+    pub signature: Vec<u8>,
+    raw_header: String,
+    raw_claims: String,
+}
+
+impl Jwt {
+    pub fn decode(token: &str) -> Result<Self, JwtError> {
+        let mut parts = token.split('.');
+        let (raw_header, raw_claims, raw_signature) =
+            match (parts.next(), parts.next(), parts.next(), parts.next()) {
+                (Some(h), Some(c), Some(s), None) => (h, c, s),
+                _ => return Err(JwtError::PartsInvalid),
+            };
+
+        Ok(Self {
+            header: serde_json::from_slice(&URL_SAFE_NO_PAD.decode(raw_header)?)?,
+            claims: serde_json::from_slice(&URL_SAFE_NO_PAD.decode(raw_claims)?)?,
+            signature: URL_SAFE_NO_PAD.decode(raw_signature)?,
+            raw_header: raw_header.to_owned(),
+            raw_claims: raw_claims.to_owned(),
+        })
+    }
+
+    pub fn alg(&self) -> Option<&str> {
+        self.header.get("alg").and_then(|x| x.as_str())
+    }
+
+    /// A numeric claim, truncated when encoded as a float such as `1700000000.0`.
+    pub fn claim_i64(&self, name: &str) -> Option<i64> {
+        let value = self.claims.get(name)?;
+        value.as_i64().or_else(|| value.as_f64().map(|x| x as i64))
+    }
+
+    /// `header.claims` as it appeared in the original token.
+    pub fn signing_input(&self) -> String {
+        format!("{}.{}", self.raw_header, self.raw_claims)
+    }
+
+    pub fn verify_hmac(&self, key: &[u8]) -> bool {
+        match self.alg().and_then(HmacAlg::from_alg) {
+            Some(alg) => alg.sign(key, self.signing_input().as_bytes()) == self.signature,
+            None => false,
+        }
+    }
+
+    /// Same claims with `alg` set to `none` and an empty signature.
+    pub fn to_alg_none(&self) -> String {
+        let mut header = self.header.to_owned();
+        header.insert("alg".to_owned(), Value::from("none"));
+        format!("{}.", encode_parts(&header, &self.claims))
+    }
+
+    /// Encode the current header and claims, signed with `key`.
+    pub fn sign_hmac(&self, alg: HmacAlg, key: &[u8]) -> String {
+        let mut header = self.header.to_owned();
+        header.insert("alg".to_owned(), Value::from(alg.as_str()));
+        let input = encode_parts(&header, &self.claims);
+        let signature = alg.sign(key, input.as_bytes());
+        format!("{input}.{}", URL_SAFE_NO_PAD.encode(signature))
+    }
+}
+
+fn encode_parts(header: &Map<String, Value>, claims: &Map<String, Value>) -> String {
+    format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(Value::Object(header.to_owned()).to_string()),
+        URL_SAFE_NO_PAD.encode(Value::Object(claims.to_owned()).to_string())
+    )
+}
+
+//
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JwtLocation {
+    RequestLine,
+    RequestHeader(String),
+    RequestCookie(String),
+    RequestBody,
+    ResponseHeader(String),
+    ResponseBody,
+}
+
+impl fmt::Display for JwtLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RequestLine => write!(f, "request line"),
+            Self::RequestHeader(name) => write!(f, "request header {name}"),
+            Self::RequestCookie(name) => write!(f, "request cookie {name}"),
+            Self::RequestBody => write!(f, "request body"),
+            Self::ResponseHeader(name) => write!(f, "response header {name}"),
+            Self::ResponseBody => write!(f, "response body"),
+        }
+    }
+}
+
+impl JwtLocation {
+    pub fn is_request(&self) -> bool {
+        matches!(
+            self,
+            Self::RequestLine | Self::RequestHeader(_) | Self::RequestCookie(_) | Self::RequestBody
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FoundJwt {
+    pub item_index: usize,
+    pub location: JwtLocation,
+    /// Byte span in the decoded request or response.
+    pub span: Range<usize>,
+    pub token: String,
+    pub jwt: Jwt,
+}
+
+/// Find decodable JWTs in the decoded request and response of an item.
+pub fn find_in_item(item_index: usize, item: &Item) -> Vec<FoundJwt> {
+    let mut found = vec![];
+    for is_request in [true, false] {
+        let bytes = if is_request {
+            item.request_bytes()
+        } else {
+            item.response_bytes()
+        };
+        let bytes = match bytes {
+            Ok(x) => x,
+            Err(_) => continue,
+        };
+        let message = Message::parse(&bytes).ok();
+
+        for m in JWT_REGEX.find_iter(&bytes) {
+            let token = match str::from_utf8(m.as_bytes()) {
+                Ok(x) => x,
+                Err(_) => continue,
+            };
+            let jwt = match Jwt::decode(token) {
+                Ok(x) => x,
+                Err(_) => continue,
+            };
+
+            found.push(FoundJwt {
+                item_index,
+                location: locate(message.as_ref(), m.start(), is_request),
+                span: m.range(),
+                token: token.to_owned(),
+                jwt,
+            })
+        }
+    }
+    found
+}
+
+fn locate(message: Option<&Message<'_>>, offset: usize, is_request: bool) -> JwtLocation {
+    let body = if is_request {
+        JwtLocation::RequestBody
+    } else {
+        JwtLocation::ResponseBody
+    };
+    let message = match message {
+        Some(x) => x,
+        None => return body,
+    };
+    if offset >= message.body_offset {
+        return body;
+    }
+
+    let header = message
+        .headers
+        .iter()
+        .find(|x| (x.value_offset..x.value_offset + x.value.len()).contains(&offset));
+    match header {
+        Some(header) if !is_request => JwtLocation::ResponseHeader(header.name.to_owned()),
+        Some(header) if header.name.eq_ignore_ascii_case("cookie") => {
+            let before = &header.value[..offset - header.value_offset];
+            let pair_start = before
+                .iter()
+                .rposition(|b| *b == b';')
+                .map(|x| x + 1)
+                .unwrap_or(0);
+            let name = String::from_utf8_lossy(&before[pair_start..]);
+            JwtLocation::RequestCookie(name.trim().trim_end_matches('=').to_owned())
+        }
+        Some(header) => JwtLocation::RequestHeader(header.name.to_owned()),
+        None if is_request => JwtLocation::RequestLine,
+        None => JwtLocation::ResponseHeader(String::new()),
+    }
+}
+
+/// Copy of `item` whose request has the token at `found.span` replaced by `token`.
+pub fn inject(item: &Item, found: &FoundJwt, token: &str) -> Result<Item, JwtError> {
+    if !found.location.is_request() {
+        return Err(JwtError::SpanInvalid);
+    }
+    let bytes = item.request_bytes()?;
+    if bytes.get(found.span.to_owned()) != Some(found.token.as_bytes()) {
+        return Err(JwtError::SpanInvalid);
+    }
+
+    let mut request = bytes[..found.span.start].to_vec();
+    request.extend(token.as_bytes());
+    request.extend(&bytes[found.span.end..]);
+
+    let mut item = item.to_owned();
+    item.set_request_bytes(fix_content_length(&request));
+    Ok(item)
+}
+
+//
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Weakness {
+    AlgNone,
+    WeakSecret(String),
+    MissingExp,
+    LongLifetime(i64),
+}
+
+impl fmt::Display for Weakness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AlgNone => write!(f, "alg none"),
+            Self::WeakSecret(secret) => write!(f, "weak HMAC secret {secret:?}"),
+            Self::MissingExp => write!(f, "missing exp"),
+            Self::LongLifetime(secs) => write!(f, "lifetime {secs}s"),
+        }
+    }
+}
+
+pub struct Analyzer {
+    pub wordlist: Vec<String>,
+    /// `exp - iat` above this, in seconds, is reported as `LongLifetime`.
+    pub max_lifetime: i64,
+}
+
+impl Default for Analyzer {
+    fn default() -> Self {
+        Self {
+            wordlist: DEFAULT_WORDLIST.iter().map(|x| x.to_string()).collect(),
+            max_lifetime: 24 * 60 * 60,
+        }
+    }
+}
+
+impl Analyzer {
+    pub fn analyze(&self, jwt: &Jwt) -> Vec<Weakness> {
+        let mut weaknesses = vec![];
+
+        match jwt.alg() {
+            Some(alg) if alg.eq_ignore_ascii_case("none") => weaknesses.push(Weakness::AlgNone),
+            Some(alg) if HmacAlg::from_alg(alg).is_some() => {
+                if let Some(secret) = self.wordlist.iter().find(|x| jwt.verify_hmac(x.as_bytes())) {
+                    weaknesses.push(Weakness::WeakSecret(secret.to_owned()))
+                }
+            }
+            _ => {}
+        }
+
+        match (jwt.claim_i64("exp"), jwt.claim_i64("iat")) {
+            (None, _) => weaknesses.push(Weakness::MissingExp),
+            (Some(exp), Some(iat)) if exp.saturating_sub(iat) > self.max_lifetime => {
+                weaknesses.push(Weakness::LongLifetime(exp.saturating_sub(iat)))
+            }
+            _ => {}
+        }
+
+        weaknesses
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // HS256 with secret "secret", claims {"sub":"1","iat":1600000000}
+    fn token() -> String {
+        let jwt =
+            Jwt::decode("eyJhbGciOiJIUzI1NiJ9.eyJzdWIiOiIxIiwiaWF0IjoxNjAwMDAwMDAwfQ.").unwrap();
+        jwt.sign_hmac(HmacAlg::HS256, b"secret")
+    }
+
+    #[test]
+    fn test_decode_and_analyze() {
+        let jwt = Jwt::decode(&token()).unwrap();
+        assert_eq!(jwt.alg(), Some("HS256"));
+        assert_eq!(jwt.claims.get("sub"), Some(&Value::from("1")));
+        assert!(jwt.verify_hmac(b"secret"));
+        assert!(!jwt.verify_hmac(b"other"));
+
+        assert_eq!(
+            Analyzer::default().analyze(&jwt),
+            vec![
+                Weakness::WeakSecret("secret".to_owned()),
+                Weakness::MissingExp
+            ]
+        );
+
+        let none = Jwt::decode(&jwt.to_alg_none()).unwrap();
+        assert_eq!(none.alg(), Some("none"));
+        assert!(none.signature.is_empty());
+        assert!(Analyzer::default()
+            .analyze(&none)
+            .contains(&Weakness::AlgNone));
+
+        assert!(Jwt::decode("a.b").is_err());
+    }
+
+    #[test]
+    fn test_find_and_inject() {
+        let token = token();
+        let mut item = Item::default();
+        item.set_request_bytes(format!(
+            "POST /api HTTP/1.1\r\nAuthorization: Bearer {token}\r\nCookie: a=1; session={token}\r\nContent-Length: {}\r\n\r\n{{\"t\":\"{token}\"}}",
+            token.len() + 8
+        ));
+
+        let found = find_in_item(5, &item);
+        assert_eq!(
+            found
+                .iter()
+                .map(|x| x.location.to_owned())
+                .collect::<Vec<_>>(),
+            vec![
+                JwtLocation::RequestHeader("Authorization".to_owned()),
+                JwtLocation::RequestCookie("session".to_owned()),
+                JwtLocation::RequestBody,
+            ]
+        );
+        assert!(found.iter().all(|x| x.item_index == 5 && x.token == token));
+
+        let tampered = found[2].jwt.to_alg_none();
+        let injected = inject(&item, &found[2], &tampered).unwrap();
+        let bytes = injected.request_bytes().unwrap();
+        let message = Message::parse(&bytes).unwrap();
+        assert_eq!(message.body, format!("{{\"t\":\"{tampered}\"}}").as_bytes());
+        assert_eq!(
+            message.header_str("content-length"),
+            Some(message.body.len().to_string().as_str())
+        );
+        assert_eq!(
+            message.header_str("authorization"),
+            Some(format!("Bearer {token}").as_str())
+        );
+    }
+
+    #[test]
+    fn test_float_claims() {
+        let encode = |x: &str| URL_SAFE_NO_PAD.encode(x);
+        let token = format!(
+            "{}.{}.",
+            encode(r#"{"alg":"none"}"#),
+            encode(r#"{"iat":1600000000.0,"exp":1700000000.5}"#)
+        );
+        let jwt = Jwt::decode(&token).unwrap();
+        assert_eq!(jwt.claim_i64("exp"), Some(1700000000));
+        assert_eq!(
+            Analyzer::default().analyze(&jwt),
+            vec![Weakness::AlgNone, Weakness::LongLifetime(100000000)]
+        );
+    }
+}
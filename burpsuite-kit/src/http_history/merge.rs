@@ -0,0 +1,385 @@
+use std::{
+    borrow::Cow,
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet},
+    hash::{DefaultHasher, Hash as _, Hasher as _},
+    io::Write,
+};
+
+use chrono::{DateTime, Duration, NaiveDateTime};
+
+use super::{
+    item::Item,
+    items::ItemsAttr,
+    message::Message,
+    writer::{ItemsWriteError, ItemsWriter},
+};
+
+const WINDOW_FORMAT: &str = "%Y%m%dT%H%M%S";
+
+//
+/// Items of several sources merged into one stream ordered by time.
+///
+/// Each source is expected in time order, as Burp exports it, items of the same time
+/// keep the order of their sources. An error is yielded as soon as it is read.
+pub struct MergedItems<I>
+where
+    I: Iterator,
+{
+    sources: Vec<I>,
+    heads: Vec<Option<I::Item>>,
+    heap: BinaryHeap<Reverse<(NaiveDateTime, usize)>>,
+}
+
+impl<I, E> MergedItems<I>
+where
+    I: Iterator<Item = Result<Item, E>>,
+{
+    pub fn new(sources: impl IntoIterator<Item = I>) -> Self {
+        let sources: Vec<I> = sources.into_iter().collect();
+        let mut merged = Self {
+            heads: sources.iter().map(|_| None).collect(),
+            heap: BinaryHeap::with_capacity(sources.len()),
+            sources,
+        };
+        for i in 0..merged.sources.len() {
+            merged.advance(i);
+        }
+        merged
+    }
+
+    fn advance(&mut self, i: usize) {
+        if let Some(next) = self.sources[i].next() {
+            let time = match &next {
+                Ok(x) => x.time,
+                Err(_) => NaiveDateTime::MIN,
+            };
+            self.heads[i] = Some(next);
+            self.heap.push(Reverse((time, i)));
+        }
+    }
+}
+
+impl<I, E> Iterator for MergedItems<I>
+where
+    I: Iterator<Item = Result<Item, E>>,
+{
+    type Item = Result<Item, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse((_, i)) = self.heap.pop()?;
+        let head = self.heads[i].take();
+        self.advance(i);
+        head
+    }
+}
+
+/// Header of a merged export, that of the latest export.
+pub fn merge_attrs<'a>(attrs: impl IntoIterator<Item = &'a ItemsAttr>) -> Option<ItemsAttr> {
+    attrs.into_iter().max_by_key(|x| x.export_time).cloned()
+}
+
+//
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dedupe {
+    #[default]
+    None,
+    /// Same request and response.
+    Exact,
+    /// Same method, URL and request body.
+    Near,
+}
+
+/// Drops items whose hash, as chosen by [`Dedupe`], was seen before.
+#[derive(Debug, Default)]
+pub struct Deduper {
+    pub dedupe: Dedupe,
+    pub dropped: usize,
+    seen: HashSet<u64>,
+}
+
+impl Deduper {
+    pub fn new(dedupe: Dedupe) -> Self {
+        Self {
+            dedupe,
+            ..Default::default()
+        }
+    }
+
+    /// Whether an item with the same hash was seen before, remembering this one.
+    pub fn is_duplicate(&mut self, item: &Item) -> bool {
+        let hash = match self.dedupe {
+            Dedupe::None => return false,
+            Dedupe::Exact => exact_hash(item),
+            Dedupe::Near => near_hash(item),
+        };
+        let is_duplicate = !self.seen.insert(hash);
+        if is_duplicate {
+            self.dropped += 1;
+        }
+        is_duplicate
+    }
+}
+
+/// Hash of the request and response, decoded so base64 and raw payloads compare equal.
+pub fn exact_hash(item: &Item) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    request_bytes(item).hash(&mut hasher);
+    item.response_bytes()
+        .unwrap_or(Cow::Borrowed(&item.response.1))
+        .hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Hash of the method, URL and request body.
+pub fn near_hash(item: &Item) -> u64 {
+    let request = request_bytes(item);
+    let body = Message::parse(&request)
+        .map(|x| x.body)
+        .unwrap_or(&request[..]);
+
+    let mut hasher = DefaultHasher::new();
+    item.method.as_str().hash(&mut hasher);
+    item.url.hash(&mut hasher);
+    body.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn request_bytes(item: &Item) -> Cow<'_, [u8]> {
+    item.request_bytes()
+        .unwrap_or(Cow::Borrowed(&item.request.1))
+}
+
+//
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitBy {
+    Host,
+    /// Windows of this length, aligned on the Unix epoch.
+    Window(Duration),
+    /// Parts of this many items.
+    Count(usize),
+}
+
+impl SplitBy {
+    /// Name of the part item number `n` of the stream goes to, usable in file names.
+    pub fn key(&self, item: &Item, n: usize) -> String {
+        match self {
+            Self::Host => item
+                .host
+                .1
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect(),
+            Self::Window(window) => {
+                let secs = window.num_seconds().max(1);
+                let time = item.time.and_utc().timestamp();
+                DateTime::from_timestamp(time - time.rem_euclid(secs), 0)
+                    .map(|x| x.format(WINDOW_FORMAT).to_string())
+                    .unwrap_or_default()
+            }
+            Self::Count(count) => format!("{:04}", n / (*count).max(1) + 1),
+        }
+    }
+
+    /// Whether a part is complete once the next one starts, so only one is
+    /// kept open. Windows assume items ordered by time, as Burp exports and
+    /// [`MergedItems`] are.
+    pub fn is_sequential(&self) -> bool {
+        matches!(self, Self::Count(_) | Self::Window(_))
+    }
+}
+
+//
+/// Writer of one part of a [`SplitWriter`].
+pub trait PartWriter {
+    type Output;
+    type Error: From<ItemsWriteError>;
+
+    fn write_item(&mut self, item: &Item) -> Result<(), Self::Error>;
+
+    /// Write the end of the part and return what it was written to.
+    fn finish_part(self) -> Result<Self::Output, Self::Error>;
+}
+
+impl<W> PartWriter for ItemsWriter<W>
+where
+    W: Write,
+{
+    type Output = W;
+    type Error = ItemsWriteError;
+
+    fn write_item(&mut self, item: &Item) -> Result<(), Self::Error> {
+        ItemsWriter::write_item(self, item)
+    }
+
+    fn finish_part(mut self) -> Result<Self::Output, Self::Error> {
+        self.finish()?;
+        Ok(self.into_inner())
+    }
+}
+
+//
+/// Writes items to one part per key, such as one Burp XML document each.
+///
+/// `create` opens the writer of a part from its key.
+pub struct SplitWriter<P, F>
+where
+    P: PartWriter,
+{
+    by: SplitBy,
+    create: F,
+    n: usize,
+    writers: HashMap<String, P>,
+    finished: Vec<(String, P::Output)>,
+}
+
+impl<P, F> SplitWriter<P, F>
+where
+    P: PartWriter,
+    F: FnMut(&str) -> Result<P, P::Error>,
+{
+    pub fn new(by: SplitBy, create: F) -> Self {
+        Self {
+            by,
+            create,
+            n: 0,
+            writers: HashMap::new(),
+            finished: vec![],
+        }
+    }
+
+    /// Fails with [`ItemsWriteError::PartFinished`] when a sequential part is
+    /// written to again after the next one started.
+    pub fn write_item(&mut self, item: &Item) -> Result<(), P::Error> {
+        let key = self.by.key(item, self.n);
+        self.n += 1;
+
+        let writer = match self.writers.get_mut(&key) {
+            Some(x) => x,
+            None => {
+                if self.by.is_sequential() {
+                    if self.finished.iter().any(|x| x.0 == key) {
+                        return Err(ItemsWriteError::PartFinished(key).into());
+                    }
+                    self.finish_writers()?;
+                }
+                let writer = (self.create)(&key)?;
+                self.writers.entry(key).or_insert(writer)
+            }
+        };
+        writer.write_item(item)
+    }
+
+    /// Finish every part and return their outputs, ordered by key.
+    pub fn finish(mut self) -> Result<Vec<(String, P::Output)>, P::Error> {
+        self.finish_writers()?;
+        self.finished.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(self.finished)
+    }
+
+    fn finish_writers(&mut self) -> Result<(), P::Error> {
+        for (key, writer) in self.writers.drain() {
+            self.finished.push((key, writer.finish_part()?));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{fs, io::Error as IoError};
+
+    use crate::http_history::items::{ItemParseError, Items};
+
+    fn read(name: &str) -> Result<Vec<u8>, IoError> {
+        fs::read(format!("tests/http_history_files/{name}"))
+    }
+
+    #[test]
+    fn test_merge() -> Result<(), Box<dyn std::error::Error>> {
+        let a = read("burpsuite_community_v2021.3.2.xml")?;
+        let b = read("burpsuite_community_v2020.12.1.xml")?;
+        let sources = [
+            Items::from_reader(&a[..])?,
+            Items::from_reader(&b[..])?,
+            Items::from_reader(&a[..])?,
+        ];
+        let attr = merge_attrs(sources.iter().map(|x| &x.attr)).ok_or("attr missing")?;
+        assert_eq!(attr.burp_version, "2021.3.2");
+
+        let items = MergedItems::new(sources).collect::<Result<Vec<_>, ItemParseError>>()?;
+        assert_eq!(items.len(), 6);
+        assert!(items.windows(2).all(|x| x[0].time <= x[1].time));
+
+        let mut deduper = Deduper::new(Dedupe::Exact);
+        let exact: Vec<&Item> = items.iter().filter(|x| !deduper.is_duplicate(x)).collect();
+        assert_eq!(exact.len(), 4);
+        assert_eq!(deduper.dropped, 2);
+
+        // Same request with another response is only a near duplicate.
+        let mut changed = items[0].to_owned();
+        changed.set_response_bytes(b"HTTP/1.1 204 No Content\r\n\r\n");
+        let mut deduper = Deduper::new(Dedupe::Exact);
+        assert!(!deduper.is_duplicate(&items[0]));
+        assert!(!deduper.is_duplicate(&changed));
+        let mut deduper = Deduper::new(Dedupe::Near);
+        assert!(!deduper.is_duplicate(&items[0]));
+        assert!(deduper.is_duplicate(&changed));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_split() -> Result<(), Box<dyn std::error::Error>> {
+        let a = read("burpsuite_community_v2021.3.2.xml")?;
+        let b = read("burpsuite_community_v1.7.36.xml")?;
+        let sources = [Items::from_reader(&a[..])?, Items::from_reader(&b[..])?];
+        let attr = merge_attrs(sources.iter().map(|x| &x.attr)).ok_or("attr missing")?;
+        let items = MergedItems::new(sources).collect::<Result<Vec<_>, ItemParseError>>()?;
+
+        for (by, keys) in [
+            (SplitBy::Count(3), vec!["0001", "0002"]),
+            (SplitBy::Host, vec!["httpbin.org"]),
+            (
+                SplitBy::Window(Duration::days(1)),
+                vec!["20210106T000000", "20210331T000000"],
+            ),
+        ] {
+            let mut writer = SplitWriter::new(by, |_| ItemsWriter::new(vec![], &attr));
+            for item in items.iter() {
+                writer.write_item(item)?;
+            }
+            let parts = writer.finish()?;
+            assert_eq!(parts.iter().map(|x| x.0.as_str()).collect::<Vec<_>>(), keys);
+
+            let mut len = 0;
+            for (_, bytes) in parts {
+                let part = Items::from_reader(&bytes[..])?;
+                assert_eq!(part.attr.burp_version, attr.burp_version);
+                len += part.collect::<Result<Vec<_>, _>>()?.len();
+            }
+            assert_eq!(len, items.len());
+        }
+
+        // Out of order windows would overwrite a finished part.
+        let mut writer = SplitWriter::new(SplitBy::Window(Duration::days(1)), |_| {
+            ItemsWriter::new(vec![], &attr)
+        });
+        writer.write_item(&items[0])?;
+        writer.write_item(&items[items.len() - 1])?;
+        assert!(matches!(
+            writer.write_item(&items[0]),
+            Err(ItemsWriteError::PartFinished(x)) if x == "20210106T000000"
+        ));
+
+        Ok(())
+    }
+}
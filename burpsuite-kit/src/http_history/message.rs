@@ -0,0 +1,234 @@
+use core::str;
+use std::borrow::Cow;
+
+//
+#[derive(Clone, Debug)]
+pub struct Message<'a> {
+    pub start_line: &'a str,
+    pub headers: Vec<Header<'a>>,
+    pub body: &'a [u8],
+    pub body_offset: usize,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Header<'a> {
+    pub name: &'a str,
+    pub value: &'a [u8],
+    /// Offset of `value` in the raw message.
+    pub value_offset: usize,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum MessageParseError {
+    #[error("StartLineMissing")]
+    StartLineMissing,
+    #[error("StartLineInvalid {0}")]
+    StartLineInvalid(String),
+    #[error("HeaderInvalid {0}")]
+    HeaderInvalid(usize),
+}
+
+impl<'a> Message<'a> {
+    /// Parse an HTTP/1.x request or response as stored by Burp.
+    ///
+    /// A message without an empty line after the headers is treated as head only.
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, MessageParseError> {
+        let mut lines = Lines { bytes, pos: 0 };
+
+        let (start_line, _) = lines.next().ok_or(MessageParseError::StartLineMissing)?;
+        let start_line = str::from_utf8(start_line)
+            .map_err(|err| MessageParseError::StartLineInvalid(err.to_string()))?;
+        if start_line.is_empty() {
+            return Err(MessageParseError::StartLineMissing);
+        }
+
+        Self::parse_rest(start_line, lines)
+    }
+
+    /// Parse a header block without a start line, such as a multipart body part.
+    pub fn parse_headers(bytes: &'a [u8]) -> Result<Self, MessageParseError> {
+        Self::parse_rest("", Lines { bytes, pos: 0 })
+    }
+
+    fn parse_rest(start_line: &'a str, mut lines: Lines<'a>) -> Result<Self, MessageParseError> {
+        let bytes = lines.bytes;
+        let mut headers = vec![];
+        let body_offset = loop {
+            match lines.next() {
+                Some((&[], _)) => break lines.pos,
+                Some((line, offset)) => {
+                    let colon = line
+                        .iter()
+                        .position(|b| *b == b':')
+                        .ok_or(MessageParseError::HeaderInvalid(offset))?;
+                    let name = str::from_utf8(&line[..colon])
+                        .map_err(|_| MessageParseError::HeaderInvalid(offset))?
+                        .trim();
+
+                    let mut value_start = colon + 1;
+                    while line.get(value_start).map(|b| *b == b' ' || *b == b'\t') == Some(true) {
+                        value_start += 1;
+                    }
+                    let mut value_end = line.len();
+                    while value_end > value_start
+                        && (line[value_end - 1] == b' ' || line[value_end - 1] == b'\t')
+                    {
+                        value_end -= 1;
+                    }
+
+                    headers.push(Header {
+                        name,
+                        value: &line[value_start..value_end],
+                        value_offset: offset + value_start,
+                    });
+                }
+                None => break bytes.len(),
+            }
+        };
+
+        Ok(Self {
+            start_line,
+            headers,
+            body: &bytes[body_offset..],
+            body_offset,
+        })
+    }
+
+    /// First header value with the given case-insensitive name.
+    pub fn header(&self, name: &str) -> Option<&'a [u8]> {
+        self.headers_all(name).next()
+    }
+
+    pub fn header_str(&self, name: &str) -> Option<&'a str> {
+        self.header(name).and_then(|x| str::from_utf8(x).ok())
+    }
+
+    /// All header values with the given case-insensitive name, in message order.
+    pub fn headers_all<'b>(&'b self, name: &'b str) -> impl Iterator<Item = &'a [u8]> + 'b {
+        self.headers
+            .iter()
+            .filter(move |x| x.name.eq_ignore_ascii_case(name))
+            .map(|x| x.value)
+    }
+
+    /// `(method, target, version)` of a request line.
+    pub fn request_line(&self) -> Option<(&'a str, &'a str, &'a str)> {
+        let mut split = self.start_line.split(' ').filter(|x| !x.is_empty());
+        Some((split.next()?, split.next()?, split.next()?))
+    }
+
+    /// Status code of a status line.
+    pub fn status_code(&self) -> Option<u16> {
+        let mut split = self.start_line.split(' ').filter(|x| !x.is_empty());
+        split.next().filter(|x| x.starts_with("HTTP/"))?;
+        split.next()?.parse().ok()
+    }
+
+    /// Lowercased media type of `Content-Type`, without parameters.
+    pub fn mime_type(&self) -> Option<String> {
+        self.header_str("content-type").map(|x| {
+            x.split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_ascii_lowercase()
+        })
+    }
+}
+
+/// `name=value` pairs of a query string or urlencoded body, without percent-decoding.
+pub fn query_pairs(query: &str) -> impl Iterator<Item = (&str, &str)> {
+    query
+        .split('&')
+        .filter(|x| !x.is_empty())
+        .map(|x| x.split_once('=').unwrap_or((x, "")))
+}
+
+/// Rewrite an existing `Content-Length` header to match the body length.
+///
+/// Messages without the header or that cannot be parsed are returned as is.
+pub fn fix_content_length(bytes: &[u8]) -> Cow<'_, [u8]> {
+    let message = match Message::parse(bytes) {
+        Ok(x) => x,
+        Err(_) => return Cow::Borrowed(bytes),
+    };
+    let header = match message
+        .headers
+        .iter()
+        .find(|x| x.name.eq_ignore_ascii_case("content-length"))
+    {
+        Some(x) => x,
+        None => return Cow::Borrowed(bytes),
+    };
+    let content_length = message.body.len().to_string();
+    if header.value == content_length.as_bytes() {
+        return Cow::Borrowed(bytes);
+    }
+
+    let mut fixed = bytes[..header.value_offset].to_vec();
+    fixed.extend(content_length.as_bytes());
+    fixed.extend(&bytes[header.value_offset + header.value.len()..]);
+    Cow::Owned(fixed)
+}
+
+struct Lines<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = (&'a [u8], usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos >= self.bytes.len() {
+            return None;
+        }
+        let start = self.pos;
+        let rest = &self.bytes[start..];
+        let (line, consumed) = match rest.iter().position(|b| *b == b'\n') {
+            Some(n) => (&rest[..n], n + 1),
+            None => (rest, rest.len()),
+        };
+        self.pos += consumed;
+
+        Some((line.strip_suffix(b"\r").unwrap_or(line), start))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request() {
+        let bytes = b"POST /post?a=1 HTTP/1.1\r\nHost: example.com\r\nContent-Type: application/json; charset=utf-8\r\nX-A:  1 \r\n\r\n{}";
+        let message = Message::parse(bytes).unwrap();
+        assert_eq!(
+            message.request_line(),
+            Some(("POST", "/post?a=1", "HTTP/1.1"))
+        );
+        assert_eq!(message.headers.len(), 3);
+        assert_eq!(message.header("host"), Some(&b"example.com"[..]));
+        assert_eq!(message.header("x-a"), Some(&b"1"[..]));
+        let header = message.headers[2];
+        assert_eq!(
+            &bytes[header.value_offset..header.value_offset + header.value.len()],
+            b"1"
+        );
+        assert_eq!(message.mime_type().as_deref(), Some("application/json"));
+        assert_eq!(message.body, b"{}");
+        assert_eq!(message.body_offset, bytes.len() - 2);
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let message =
+            Message::parse(b"HTTP/1.1 404 Not Found\nSet-Cookie: a=1\nSet-Cookie: b=2\n").unwrap();
+        assert_eq!(message.status_code(), Some(404));
+        assert_eq!(message.headers_all("set-cookie").count(), 2);
+        assert!(message.body.is_empty());
+
+        assert!(Message::parse(b"").is_err());
+        assert!(Message::parse(b"HTTP/1.1 200 OK\r\nbad\r\n\r\n").is_err());
+    }
+}
@@ -0,0 +1,664 @@
+use core::str;
+use std::io::{BufRead, Error as IoError, ErrorKind as IoErrorKind, Write};
+
+use base64::DecodeError;
+use chrono::{DateTime, NaiveDateTime};
+use http::uri::Scheme;
+
+use super::{
+    item::{Item, ItemHostAttr},
+    message::{Message, MessageParseError},
+};
+
+/// Flow format version written, as of mitmproxy 10.
+pub const FLOW_FORMAT_VERSION: i64 = 19;
+
+/// Longest tnetstring length prefix accepted, guards against reading garbage.
+const MAX_LENGTH_DIGITS: usize = 12;
+
+/// Larger flows are treated as a corrupt file.
+const MAX_FLOW_LEN: usize = 256 * 1024 * 1024;
+
+/// Deeper nested lists and dicts are treated as a corrupt file.
+const MAX_DEPTH: usize = 64;
+
+//
+#[derive(Debug, Clone, PartialEq)]
+pub enum TnetValue {
+    Bytes(Vec<u8>),
+    String(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Null,
+    List(Vec<TnetValue>),
+    Dict(Vec<(String, TnetValue)>),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum MitmError {
+    #[error("IoError {0:?}")]
+    IoError(#[from] IoError),
+    #[error("TnetstringInvalid {0}")]
+    TnetstringInvalid(String),
+    #[error("FieldMissing {0}")]
+    FieldMissing(&'static str),
+    #[error("FieldInvalid {0}")]
+    FieldInvalid(&'static str),
+    #[error("MessageParseFailed {0}")]
+    MessageParseFailed(#[from] MessageParseError),
+    #[error("PayloadDecodeFailed {0}")]
+    PayloadDecodeFailed(#[from] DecodeError),
+}
+
+impl TnetValue {
+    /// Parse one tnetstring, returning the value and the remaining bytes.
+    pub fn parse(bytes: &[u8]) -> Result<(Self, &[u8]), MitmError> {
+        Self::parse_nested(bytes, 0)
+    }
+
+    fn parse_nested(bytes: &[u8], depth: usize) -> Result<(Self, &[u8]), MitmError> {
+        let invalid = |s: &str| MitmError::TnetstringInvalid(s.to_owned());
+
+        let colon = bytes
+            .iter()
+            .take(MAX_LENGTH_DIGITS + 1)
+            .position(|b| *b == b':')
+            .ok_or_else(|| invalid("length"))?;
+        let len: usize = str::from_utf8(&bytes[..colon])
+            .ok()
+            .and_then(|x| x.parse().ok())
+            .filter(|x| *x < bytes.len())
+            .ok_or_else(|| invalid("length"))?;
+        let data = bytes
+            .get(colon + 1..colon + 1 + len)
+            .ok_or_else(|| invalid("data"))?;
+        let tag = *bytes.get(colon + 1 + len).ok_or_else(|| invalid("tag"))?;
+        let rest = &bytes[colon + 2 + len..];
+
+        let text = || str::from_utf8(data).map_err(|_| invalid("utf-8"));
+        let value = match tag {
+            b',' => Self::Bytes(data.to_vec()),
+            b';' => Self::String(text()?.to_owned()),
+            b'#' => Self::Int(text()?.parse().map_err(|_| invalid("int"))?),
+            b'^' => Self::Float(text()?.parse().map_err(|_| invalid("float"))?),
+            b'!' => match data {
+                b"true" => Self::Bool(true),
+                b"false" => Self::Bool(false),
+                _ => return Err(invalid("bool")),
+            },
+            b'~' if data.is_empty() => Self::Null,
+            b']' | b'}' if depth >= MAX_DEPTH => return Err(invalid("depth")),
+            b']' => {
+                let mut list = vec![];
+                let mut data = data;
+                while !data.is_empty() {
+                    let (value, rest) = Self::parse_nested(data, depth + 1)?;
+                    list.push(value);
+                    data = rest;
+                }
+                Self::List(list)
+            }
+            b'}' => {
+                let mut dict = vec![];
+                let mut data = data;
+                while !data.is_empty() {
+                    let (key, rest) = Self::parse_nested(data, depth + 1)?;
+                    let key = match key {
+                        Self::String(x) => x,
+                        Self::Bytes(x) => String::from_utf8(x).map_err(|_| invalid("key"))?,
+                        _ => return Err(invalid("key")),
+                    };
+                    let (value, rest) = Self::parse_nested(rest, depth + 1)?;
+                    dict.push((key, value));
+                    data = rest;
+                }
+                Self::Dict(dict)
+            }
+            _ => return Err(invalid("tag")),
+        };
+        Ok((value, rest))
+    }
+
+    pub fn dump(&self, out: &mut Vec<u8>) {
+        let (data, tag) = match self {
+            Self::Bytes(x) => (x.to_owned(), b','),
+            Self::String(x) => (x.as_bytes().to_vec(), b';'),
+            Self::Int(x) => (x.to_string().into_bytes(), b'#'),
+            Self::Float(x) => (format!("{x:?}").into_bytes(), b'^'),
+            Self::Bool(x) => (x.to_string().into_bytes(), b'!'),
+            Self::Null => (vec![], b'~'),
+            Self::List(x) => {
+                let mut data = vec![];
+                for value in x.iter() {
+                    value.dump(&mut data);
+                }
+                (data, b']')
+            }
+            Self::Dict(x) => {
+                let mut data = vec![];
+                for (key, value) in x.iter() {
+                    Self::String(key.to_owned()).dump(&mut data);
+                    value.dump(&mut data);
+                }
+                (data, b'}')
+            }
+        };
+        out.extend(data.len().to_string().as_bytes());
+        out.push(b':');
+        out.extend(data);
+        out.push(tag);
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Self> {
+        match self {
+            Self::Dict(x) => x.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Bytes or string contents.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Self::Bytes(x) => Some(x),
+            Self::String(x) => Some(x.as_bytes()),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::Int(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Float(x) => Some(*x),
+            Self::Int(x) => Some(*x as f64),
+            _ => None,
+        }
+    }
+}
+
+fn dict(entries: Vec<(&str, TnetValue)>) -> TnetValue {
+    TnetValue::Dict(
+        entries
+            .into_iter()
+            .map(|(k, v)| (k.to_owned(), v))
+            .collect(),
+    )
+}
+
+//
+/// Convert an HTTP flow to an item.
+pub fn flow_to_item(flow: &TnetValue) -> Result<Item, MitmError> {
+    let request = flow
+        .get("request")
+        .ok_or(MitmError::FieldMissing("request"))?;
+    let field = |value: &'_ TnetValue, name: &'static str| -> Result<Vec<u8>, MitmError> {
+        value
+            .get(name)
+            .and_then(|x| x.as_bytes())
+            .map(|x| x.to_vec())
+            .ok_or(MitmError::FieldMissing(name))
+    };
+
+    let scheme = field(request, "scheme")?;
+    let protocol = if scheme == b"https" {
+        Scheme::HTTPS
+    } else {
+        Scheme::HTTP
+    };
+    let host =
+        String::from_utf8(field(request, "host")?).map_err(|_| MitmError::FieldInvalid("host"))?;
+    let port = request
+        .get("port")
+        .and_then(|x| x.as_i64())
+        .and_then(|x| u16::try_from(x).ok())
+        .ok_or(MitmError::FieldInvalid("port"))?;
+
+    let mut request_bytes = field(request, "method")?;
+    request_bytes.push(b' ');
+    request_bytes.extend(field(request, "path")?);
+    request_bytes.push(b' ');
+    request_bytes.extend(field(request, "http_version")?);
+    request_bytes.extend(b"\r\n");
+    write_headers(request, &mut request_bytes)?;
+    request_bytes.extend(field(request, "content").unwrap_or_default());
+
+    let response_bytes = match flow.get("response") {
+        Some(response @ TnetValue::Dict(_)) => {
+            let status = response
+                .get("status_code")
+                .and_then(|x| x.as_i64())
+                .ok_or(MitmError::FieldInvalid("status_code"))?;
+            let mut bytes = field(response, "http_version")?;
+            bytes.extend(format!(" {status} ").as_bytes());
+            bytes.extend(field(response, "reason").unwrap_or_default());
+            bytes.extend(b"\r\n");
+            write_headers(response, &mut bytes)?;
+            bytes.extend(field(response, "content").unwrap_or_default());
+            bytes
+        }
+        _ => vec![],
+    };
+
+    let mut item = Item::from_messages(protocol, &host, port, &request_bytes, &response_bytes)?;
+    if let Some(time) = request.get("timestamp_start").and_then(|x| x.as_f64()) {
+        item.time = timestamp_to_time(time);
+    }
+    if let Some(TnetValue::List(peername)) = flow
+        .get("server_conn")
+        .and_then(|x| x.get("peername").or_else(|| x.get("address")))
+    {
+        if let Some(ip) = peername.first().and_then(|x| x.as_bytes()) {
+            item.host.0 = ItemHostAttr { ip: ip.to_vec() };
+        }
+    }
+    item.comment = flow
+        .get("comment")
+        .and_then(|x| x.as_bytes())
+        .filter(|x| !x.is_empty())
+        .map(|x| String::from_utf8_lossy(x).into_owned());
+    Ok(item)
+}
+
+fn write_headers(message: &TnetValue, out: &mut Vec<u8>) -> Result<(), MitmError> {
+    let headers = match message.get("headers") {
+        Some(TnetValue::List(x)) => x,
+        _ => return Err(MitmError::FieldMissing("headers")),
+    };
+    for header in headers.iter() {
+        match header {
+            TnetValue::List(pair) if pair.len() == 2 => {
+                let name = pair[0]
+                    .as_bytes()
+                    .ok_or(MitmError::FieldInvalid("headers"))?;
+                let value = pair[1]
+                    .as_bytes()
+                    .ok_or(MitmError::FieldInvalid("headers"))?;
+                out.extend(name);
+                out.extend(b": ");
+                out.extend(value);
+                out.extend(b"\r\n");
+            }
+            _ => return Err(MitmError::FieldInvalid("headers")),
+        }
+    }
+    out.extend(b"\r\n");
+    Ok(())
+}
+
+fn timestamp_to_time(timestamp: f64) -> NaiveDateTime {
+    let secs = timestamp.floor();
+    let nanos = ((timestamp - secs) * 1e9) as u32;
+    DateTime::from_timestamp(secs as i64, nanos)
+        .unwrap_or_default()
+        .naive_utc()
+}
+
+/// Convert an item to an HTTP flow of the `FLOW_FORMAT_VERSION` layout.
+pub fn item_to_flow(item: &Item) -> Result<TnetValue, MitmError> {
+    let timestamp = item.time.and_utc().timestamp_millis() as f64 / 1000.0;
+    let request_bytes = item.request_bytes()?;
+    let request = Message::parse(&request_bytes)?;
+    let (method, target, version) = request
+        .request_line()
+        .ok_or_else(|| MessageParseError::StartLineInvalid(request.start_line.to_owned()))?;
+
+    let headers = |message: &Message<'_>| {
+        TnetValue::List(
+            message
+                .headers
+                .iter()
+                .map(|x| {
+                    TnetValue::List(vec![
+                        TnetValue::Bytes(x.name.as_bytes().to_vec()),
+                        TnetValue::Bytes(x.value.to_vec()),
+                    ])
+                })
+                .collect(),
+        )
+    };
+
+    let request_state = dict(vec![
+        (
+            "http_version",
+            TnetValue::Bytes(version.as_bytes().to_vec()),
+        ),
+        ("headers", headers(&request)),
+        ("content", TnetValue::Bytes(request.body.to_vec())),
+        ("trailers", TnetValue::Null),
+        ("timestamp_start", TnetValue::Float(timestamp)),
+        ("timestamp_end", TnetValue::Float(timestamp)),
+        ("host", TnetValue::String(item.host.1.to_owned())),
+        ("port", TnetValue::Int(item.port.into())),
+        ("method", TnetValue::Bytes(method.as_bytes().to_vec())),
+        (
+            "scheme",
+            TnetValue::Bytes(item.protocol.as_str().as_bytes().to_vec()),
+        ),
+        ("authority", TnetValue::Bytes(vec![])),
+        ("path", TnetValue::Bytes(target.as_bytes().to_vec())),
+    ]);
+
+    let response_bytes = item.response_bytes()?;
+    let response_state = match Message::parse(&response_bytes) {
+        Ok(response) => {
+            let mut split = response.start_line.splitn(3, ' ');
+            let version = split.next().unwrap_or_default();
+            split.next();
+            let reason = split.next().unwrap_or_default();
+            dict(vec![
+                (
+                    "http_version",
+                    TnetValue::Bytes(version.as_bytes().to_vec()),
+                ),
+                ("headers", headers(&response)),
+                ("content", TnetValue::Bytes(response.body.to_vec())),
+                ("trailers", TnetValue::Null),
+                ("timestamp_start", TnetValue::Float(timestamp)),
+                ("timestamp_end", TnetValue::Float(timestamp)),
+                ("status_code", TnetValue::Int(item.status.as_u16().into())),
+                ("reason", TnetValue::Bytes(reason.as_bytes().to_vec())),
+            ])
+        }
+        Err(_) => TnetValue::Null,
+    };
+
+    let id = flow_id(item);
+    let ip = String::from_utf8_lossy(&item.host.0.ip).into_owned();
+    let tls = item.protocol == Scheme::HTTPS;
+    let conn = |id: String, peername: TnetValue, address: TnetValue, sni: TnetValue| {
+        dict(vec![
+            ("id", TnetValue::String(id)),
+            ("peername", peername),
+            ("sockname", TnetValue::Null),
+            ("address", address),
+            ("state", TnetValue::Int(0)),
+            ("error", TnetValue::Null),
+            ("tls", TnetValue::Bool(tls)),
+            ("certificate_list", TnetValue::List(vec![])),
+            ("alpn", TnetValue::Null),
+            ("cipher", TnetValue::Null),
+            ("sni", sni),
+            ("timestamp_start", TnetValue::Float(timestamp)),
+            ("timestamp_end", TnetValue::Float(timestamp)),
+            ("timestamp_tcp_setup", TnetValue::Null),
+            ("timestamp_tls_setup", TnetValue::Null),
+            ("tls_version", TnetValue::Null),
+            ("alpn_offers", TnetValue::List(vec![])),
+            ("cipher_list", TnetValue::List(vec![])),
+            ("via", TnetValue::Null),
+            ("mitmcert", TnetValue::Null),
+            ("proxy_mode", TnetValue::String("regular".to_owned())),
+            ("transport_protocol", TnetValue::String("tcp".to_owned())),
+        ])
+    };
+    let server_peername = if ip.is_empty() {
+        TnetValue::Null
+    } else {
+        TnetValue::List(vec![
+            TnetValue::String(ip),
+            TnetValue::Int(item.port.into()),
+        ])
+    };
+    let server_conn = conn(
+        format!("{id}-server"),
+        server_peername,
+        TnetValue::List(vec![
+            TnetValue::String(item.host.1.to_owned()),
+            TnetValue::Int(item.port.into()),
+        ]),
+        if tls {
+            TnetValue::String(item.host.1.to_owned())
+        } else {
+            TnetValue::Null
+        },
+    );
+
+    Ok(dict(vec![
+        ("version", TnetValue::Int(FLOW_FORMAT_VERSION)),
+        ("type", TnetValue::String("http".to_owned())),
+        ("id", TnetValue::String(id.to_owned())),
+        ("error", TnetValue::Null),
+        (
+            "client_conn",
+            conn(
+                format!("{id}-client"),
+                TnetValue::List(vec![
+                    TnetValue::String("127.0.0.1".to_owned()),
+                    TnetValue::Int(0),
+                ]),
+                TnetValue::Null,
+                TnetValue::Null,
+            ),
+        ),
+        ("server_conn", server_conn),
+        ("intercepted", TnetValue::Bool(false)),
+        ("is_replay", TnetValue::Null),
+        ("marked", TnetValue::String(String::new())),
+        ("metadata", TnetValue::Dict(vec![])),
+        (
+            "comment",
+            TnetValue::String(item.comment.to_owned().unwrap_or_default()),
+        ),
+        ("timestamp_created", TnetValue::Float(timestamp)),
+        ("request", request_state),
+        ("response", response_state),
+        ("websocket", TnetValue::Null),
+        ("backup", TnetValue::Null),
+    ]))
+}
+
+/// Deterministic UUID-shaped id, so exporting the same item twice gives the same flow.
+fn flow_id(item: &Item) -> String {
+    // FNV-1a over the identifying fields.
+    let mut hashes = [0xcbf29ce484222325_u64, 0x84222325cbf29ce4];
+    for (i, hash) in hashes.iter_mut().enumerate() {
+        for b in item
+            .url
+            .as_bytes()
+            .iter()
+            .chain(item.request.1.iter())
+            .chain(item.time.and_utc().timestamp_millis().to_be_bytes().iter())
+            .chain([i as u8].iter())
+        {
+            *hash ^= *b as u64;
+            *hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    let hex = format!("{:016x}{:016x}", hashes[0], hashes[1]);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+//
+/// Reads items from a mitmproxy flow dump. Flows other than HTTP are skipped.
+pub struct MitmFlowReader<R>
+where
+    R: BufRead,
+{
+    reader: R,
+    buf: Vec<u8>,
+}
+
+impl<R> MitmFlowReader<R>
+where
+    R: BufRead,
+{
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buf: vec![],
+        }
+    }
+
+    /// Next raw flow, `None` at end of input.
+    pub fn next_flow(&mut self) -> Result<Option<TnetValue>, MitmError> {
+        self.buf.clear();
+        let n = self.reader.read_until(b':', &mut self.buf)?;
+        if n == 0 {
+            return Ok(None);
+        }
+        if self.buf.last() != Some(&b':') || self.buf.len() > MAX_LENGTH_DIGITS + 1 {
+            return Err(MitmError::TnetstringInvalid("length".to_owned()));
+        }
+        let len: usize = str::from_utf8(&self.buf[..self.buf.len() - 1])
+            .ok()
+            .and_then(|x| x.parse().ok())
+            .filter(|x| *x <= MAX_FLOW_LEN)
+            .ok_or_else(|| MitmError::TnetstringInvalid("length".to_owned()))?;
+
+        let start = self.buf.len();
+        self.buf.resize(start + len + 1, 0);
+        self.reader
+            .read_exact(&mut self.buf[start..])
+            .map_err(|err| match err.kind() {
+                IoErrorKind::UnexpectedEof => MitmError::TnetstringInvalid("data".to_owned()),
+                _ => err.into(),
+            })?;
+
+        let (flow, _) = TnetValue::parse(&self.buf)?;
+        Ok(Some(flow))
+    }
+}
+
+impl<R> Iterator for MitmFlowReader<R>
+where
+    R: BufRead,
+{
+    type Item = Result<Item, MitmError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.next_flow() {
+                Ok(Some(flow)) => {
+                    if flow.get("type").and_then(|x| x.as_bytes()) != Some(b"http") {
+                        continue;
+                    }
+                    return Some(flow_to_item(&flow));
+                }
+                Ok(None) => return None,
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+/// Writes items as a mitmproxy flow dump.
+pub struct MitmFlowWriter<W>
+where
+    W: Write,
+{
+    writer: W,
+}
+
+impl<W> MitmFlowWriter<W>
+where
+    W: Write,
+{
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub fn write_item(&mut self, item: &Item) -> Result<(), MitmError> {
+        let mut bytes = vec![];
+        item_to_flow(item)?.dump(&mut bytes);
+        self.writer.write_all(&bytes)?;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{fs::File, io::BufReader};
+
+    use crate::http_history::items::Items;
+
+    #[test]
+    fn test_tnetstring() -> Result<(), Box<dyn std::error::Error>> {
+        let value = dict(vec![
+            ("a", TnetValue::Bytes(b"x:y".to_vec())),
+            (
+                "b",
+                TnetValue::List(vec![
+                    TnetValue::Int(-1),
+                    TnetValue::Float(1.5),
+                    TnetValue::Bool(true),
+                    TnetValue::Null,
+                ]),
+            ),
+        ]);
+        let mut bytes = vec![];
+        value.dump(&mut bytes);
+        assert_eq!(bytes, b"39:1:a;3:x:y,1:b;21:2:-1#3:1.5^4:true!0:~]}");
+        assert_eq!(TnetValue::parse(&bytes)?, (value, &b""[..]));
+
+        assert!(TnetValue::parse(b"5:abc,").is_err());
+        assert!(TnetValue::parse(b"999999999999:abc,").is_err());
+
+        let mut nested = TnetValue::Null;
+        for _ in 0..=MAX_DEPTH {
+            nested = TnetValue::List(vec![nested]);
+        }
+        let mut bytes = vec![];
+        nested.dump(&mut bytes);
+        match TnetValue::parse(&bytes) {
+            Err(MitmError::TnetstringInvalid(x)) => assert_eq!(x, "depth"),
+            x => panic!("{x:?}"),
+        }
+
+        match MitmFlowReader::new(&b"999999999999:abc"[..]).next() {
+            Some(Err(MitmError::TnetstringInvalid(x))) => assert_eq!(x, "length"),
+            x => panic!("{x:?}"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+        let file = File::open("tests/http_history_files/burpsuite_community_v2021.3.2.xml")?;
+        let items = Items::from_reader(BufReader::new(file))?.collect::<Result<Vec<_>, _>>()?;
+
+        let mut writer = MitmFlowWriter::new(vec![]);
+        for item in items.iter() {
+            writer.write_item(item)?;
+        }
+        let mut dump = writer.into_inner();
+        // A non-HTTP flow, skipped by the reader.
+        dict(vec![("type", TnetValue::String("tcp".to_owned()))]).dump(&mut dump);
+
+        let read = MitmFlowReader::new(&dump[..]).collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(read.len(), items.len());
+        for (read, item) in read.iter().zip(items.iter()) {
+            assert_eq!(read.time, item.time);
+            assert_eq!(read.url, item.url);
+            assert_eq!(read.host.0.ip, item.host.0.ip);
+            assert_eq!(read.port, item.port);
+            assert_eq!(read.protocol, item.protocol);
+            assert_eq!(read.method, item.method);
+            assert_eq!(read.status, item.status);
+            assert_eq!(read.response_length, item.response_length);
+            assert_eq!(read.mimetype, item.mimetype);
+            assert_eq!(read.request_bytes()?, item.request_bytes()?);
+            assert_eq!(read.response_bytes()?, item.response_bytes()?);
+        }
+
+        Ok(())
+    }
+}
@@ -1,2 +1,37 @@
+pub mod compression;
+#[cfg(feature = "csv")]
+pub mod csv;
+pub mod index;
 pub mod item;
+pub mod item_ref;
+#[cfg(feature = "serde")]
+pub mod item_serde;
 pub mod items;
+#[cfg(feature = "jsonl")]
+pub mod jsonl;
+#[cfg(feature = "jwt")]
+pub mod jwt;
+pub mod merge;
+pub mod message;
+pub mod mitmproxy;
+#[cfg(feature = "openapi")]
+pub mod openapi;
+#[cfg(feature = "parallel")]
+pub mod parallel;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+pub mod passive;
+pub mod pcap;
+#[cfg(feature = "postman")]
+pub mod postman;
+pub mod raw_request;
+pub mod redact;
+pub mod secrets;
+pub mod snippet;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+#[cfg(feature = "warc")]
+pub mod warc;
+pub mod writer;
+#[cfg(feature = "zap")]
+pub mod zap;
@@ -0,0 +1,623 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use http::{uri::Scheme, StatusCode};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::{json, Map, Value};
+
+use super::{
+    item::Item,
+    message::{query_pairs, Message},
+};
+
+static UUID_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}$")
+        .expect("invalid regex")
+});
+static HASH_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(?:[0-9a-fA-F]{16,}|[0-9a-fA-F]{24})$").expect("invalid regex"));
+
+/// Request headers that are not worth documenting as parameters.
+const IGNORED_HEADERS: &[&str] = &[
+    "accept",
+    "accept-encoding",
+    "accept-language",
+    "authorization",
+    "cache-control",
+    "connection",
+    "content-length",
+    "content-type",
+    "cookie",
+    "dnt",
+    "host",
+    "if-modified-since",
+    "if-none-match",
+    "keep-alive",
+    "origin",
+    "pragma",
+    "priority",
+    "referer",
+    "te",
+    "upgrade-insecure-requests",
+    "user-agent",
+];
+
+//
+/// JSON schema inferred from samples and merged across them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Schema {
+    /// No sample yet, e.g. items of an empty array.
+    Unknown,
+    /// Conflicting samples.
+    Any,
+    Null,
+    Boolean,
+    Integer,
+    Number,
+    String(Option<&'static str>),
+    Array(Box<Schema>),
+    Object {
+        properties: BTreeMap<String, Schema>,
+        required: BTreeSet<String>,
+    },
+    Nullable(Box<Schema>),
+}
+
+impl Schema {
+    pub fn from_json(value: &Value) -> Self {
+        match value {
+            Value::Null => Self::Null,
+            Value::Bool(_) => Self::Boolean,
+            Value::Number(x) if x.is_i64() || x.is_u64() => Self::Integer,
+            Value::Number(_) => Self::Number,
+            Value::String(x) => Self::String(string_format(x)),
+            Value::Array(x) => Self::Array(Box::new(
+                x.iter()
+                    .map(Self::from_json)
+                    .fold(Self::Unknown, Self::merge),
+            )),
+            Value::Object(x) => Self::Object {
+                properties: x
+                    .iter()
+                    .map(|(k, v)| (k.to_owned(), Self::from_json(v)))
+                    .collect(),
+                required: x.keys().cloned().collect(),
+            },
+        }
+    }
+
+    /// Schema of a query, path, header or cookie value.
+    pub fn from_text(value: &str) -> Self {
+        if value.parse::<i64>().is_ok() {
+            Self::Integer
+        } else if value.parse::<f64>().is_ok() && value.contains('.') {
+            Self::Number
+        } else if value == "true" || value == "false" {
+            Self::Boolean
+        } else {
+            Self::String(string_format(value))
+        }
+    }
+
+    pub fn merge(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::Unknown, x) | (x, Self::Unknown) => x,
+            (Self::Any, _) | (_, Self::Any) => Self::Any,
+            (Self::Null, Self::Null) => Self::Null,
+            (Self::Null, x) | (x, Self::Null) => Self::Nullable(Box::new(x.strip_nullable())),
+            (Self::Nullable(a), b) | (b, Self::Nullable(a)) => {
+                Self::Nullable(Box::new(a.merge(b.strip_nullable())))
+            }
+            (Self::Integer, Self::Number) | (Self::Number, Self::Integer) => Self::Number,
+            (Self::String(a), Self::String(b)) => Self::String(if a == b { a } else { None }),
+            (Self::Array(a), Self::Array(b)) => Self::Array(Box::new(a.merge(*b))),
+            (
+                Self::Object {
+                    mut properties,
+                    required,
+                },
+                Self::Object {
+                    properties: other_properties,
+                    required: other_required,
+                },
+            ) => {
+                for (k, v) in other_properties {
+                    let merged = match properties.remove(&k) {
+                        Some(x) => x.merge(v),
+                        None => v,
+                    };
+                    properties.insert(k, merged);
+                }
+                Self::Object {
+                    properties,
+                    required: required.intersection(&other_required).cloned().collect(),
+                }
+            }
+            (a, b) if a == b => a,
+            _ => Self::Any,
+        }
+    }
+
+    fn strip_nullable(self) -> Self {
+        match self {
+            Self::Nullable(x) => *x,
+            x => x,
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        match self {
+            Self::Unknown | Self::Any => json!({}),
+            Self::Null => json!({ "nullable": true }),
+            Self::Boolean => json!({ "type": "boolean" }),
+            Self::Integer => json!({ "type": "integer" }),
+            Self::Number => json!({ "type": "number" }),
+            Self::String(None) => json!({ "type": "string" }),
+            Self::String(Some(format)) => json!({ "type": "string", "format": format }),
+            Self::Array(x) => json!({ "type": "array", "items": x.to_json() }),
+            Self::Object {
+                properties,
+                required,
+            } => {
+                let mut schema = json!({
+                    "type": "object",
+                    "properties": properties
+                        .iter()
+                        .map(|(k, v)| (k.to_owned(), v.to_json()))
+                        .collect::<Map<_, _>>(),
+                });
+                if !required.is_empty() {
+                    schema["required"] = required.iter().cloned().collect();
+                }
+                schema
+            }
+            Self::Nullable(x) => {
+                let mut schema = x.to_json();
+                schema["nullable"] = Value::Bool(true);
+                schema
+            }
+        }
+    }
+}
+
+fn string_format(value: &str) -> Option<&'static str> {
+    if UUID_REGEX.is_match(value) {
+        Some("uuid")
+    } else if chrono::DateTime::parse_from_rfc3339(value).is_ok() {
+        Some("date-time")
+    } else {
+        None
+    }
+}
+
+/// Whether a path segment looks like an identifier rather than a fixed name.
+pub fn is_id_segment(segment: &str) -> bool {
+    !segment.is_empty()
+        && (segment.bytes().all(|x| x.is_ascii_digit())
+            || UUID_REGEX.is_match(segment)
+            || (HASH_REGEX.is_match(segment) && segment.bytes().any(|x| x.is_ascii_digit())))
+}
+
+/// Template a path such as `/users/42/posts/` into `/users/{id}/posts/` plus the templated values.
+pub fn path_template(path: &str) -> (String, Vec<(String, String)>) {
+    let mut params = vec![];
+    let template = path
+        .split('/')
+        .map(|segment| {
+            if is_id_segment(segment) {
+                let name = match params.len() {
+                    0 => "id".to_owned(),
+                    n => format!("id{}", n + 1),
+                };
+                let template = format!("{{{name}}}");
+                params.push((name, segment.to_owned()));
+                template
+            } else {
+                segment.to_owned()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/");
+    (template, params)
+}
+
+//
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum ParamLocation {
+    Path,
+    Query,
+    Header,
+    Cookie,
+}
+
+impl ParamLocation {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Path => "path",
+            Self::Query => "query",
+            Self::Header => "header",
+            Self::Cookie => "cookie",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Operation {
+    samples: usize,
+    params: BTreeMap<(ParamLocation, String), (Schema, usize)>,
+    request_bodies: BTreeMap<String, Option<Schema>>,
+    responses: BTreeMap<u16, BTreeMap<String, Option<Schema>>>,
+    security: BTreeSet<&'static str>,
+}
+
+/// Infers an OpenAPI 3 document from items. Feed items with `add_item` and render with `to_json`.
+#[derive(Debug)]
+pub struct OpenApiInference {
+    pub title: String,
+    pub version: String,
+    servers: BTreeSet<String>,
+    paths: BTreeMap<String, BTreeMap<String, Operation>>,
+}
+
+impl Default for OpenApiInference {
+    fn default() -> Self {
+        Self {
+            title: "Inferred from Burp Suite HTTP history".to_owned(),
+            version: "1.0.0".to_owned(),
+            servers: BTreeSet::new(),
+            paths: BTreeMap::new(),
+        }
+    }
+}
+
+impl OpenApiInference {
+    pub fn add_item(&mut self, item: &Item) {
+        let default_port = (item.protocol == Scheme::HTTP && item.port == 80)
+            || (item.protocol == Scheme::HTTPS && item.port == 443);
+        self.servers.insert(if default_port {
+            format!("{}://{}", item.protocol, item.host.1)
+        } else {
+            format!("{}://{}:{}", item.protocol, item.host.1, item.port)
+        });
+
+        let (path, query) = item.path.split_once('?').unwrap_or((&item.path, ""));
+        let (template, path_params) = path_template(path);
+        let operation = self
+            .paths
+            .entry(template)
+            .or_default()
+            .entry(item.method.as_str().to_ascii_lowercase())
+            .or_default();
+        operation.samples += 1;
+
+        let mut params: BTreeMap<(ParamLocation, String), Schema> = BTreeMap::new();
+        let mut add_param = |location, name: &str, value: &str| {
+            let schema = Schema::from_text(value);
+            let key = (location, name.to_owned());
+            let merged = match params.remove(&key) {
+                Some(x) => x.merge(schema),
+                None => schema,
+            };
+            params.insert(key, merged);
+        };
+        for (name, value) in path_params.iter() {
+            add_param(ParamLocation::Path, name, value);
+        }
+        for (name, value) in query_pairs(query) {
+            add_param(ParamLocation::Query, name, value);
+        }
+
+        if let Ok(bytes) = item.request_bytes() {
+            if let Ok(request) = Message::parse(&bytes) {
+                for header in request.headers.iter() {
+                    let name = header.name.to_ascii_lowercase();
+                    let value = String::from_utf8_lossy(header.value);
+                    if name == "cookie" {
+                        for pair in value.split(';') {
+                            if let Some((k, v)) = pair.trim().split_once('=') {
+                                add_param(ParamLocation::Cookie, k, v);
+                            }
+                        }
+                    } else if name == "authorization" {
+                        let scheme = value.split(' ').next().unwrap_or_default();
+                        if scheme.eq_ignore_ascii_case("bearer") {
+                            operation.security.insert("bearerAuth");
+                        } else if scheme.eq_ignore_ascii_case("basic") {
+                            operation.security.insert("basicAuth");
+                        }
+                    } else if !IGNORED_HEADERS.contains(&name.as_str()) && !name.starts_with("sec-")
+                    {
+                        add_param(ParamLocation::Header, header.name, &value);
+                    }
+                }
+
+                if !request.body.is_empty() {
+                    let mime_type = request
+                        .mime_type()
+                        .unwrap_or_else(|| "application/octet-stream".to_owned());
+                    let schema = body_schema(&mime_type, request.body);
+                    merge_content(&mut operation.request_bodies, mime_type, schema);
+                }
+            }
+        }
+
+        for (key, schema) in params {
+            let entry = operation.params.entry(key).or_insert((Schema::Unknown, 0));
+            entry.0 = core::mem::replace(&mut entry.0, Schema::Unknown).merge(schema);
+            entry.1 += 1;
+        }
+
+        let contents = operation.responses.entry(item.status.as_u16()).or_default();
+        if let Ok(bytes) = item.response_bytes() {
+            if let Ok(response) = Message::parse(&bytes) {
+                if !response.body.is_empty() {
+                    let mime_type = response
+                        .mime_type()
+                        .unwrap_or_else(|| "application/octet-stream".to_owned());
+                    let schema = body_schema(&mime_type, response.body);
+                    merge_content(contents, mime_type, schema);
+                }
+            }
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut security_schemes = Map::new();
+        let paths: Map<String, Value> = self
+            .paths
+            .iter()
+            .map(|(path, operations)| {
+                let operations: Map<String, Value> = operations
+                    .iter()
+                    .map(|(method, operation)| {
+                        for name in operation.security.iter() {
+                            security_schemes.insert(
+                                name.to_string(),
+                                match *name {
+                                    "bearerAuth" => json!({ "type": "http", "scheme": "bearer" }),
+                                    _ => json!({ "type": "http", "scheme": "basic" }),
+                                },
+                            );
+                        }
+                        (method.to_owned(), operation_to_json(operation))
+                    })
+                    .collect();
+                (path.to_owned(), Value::Object(operations))
+            })
+            .collect();
+
+        let mut document = json!({
+            "openapi": "3.0.3",
+            "info": { "title": self.title, "version": self.version },
+            "servers": self
+                .servers
+                .iter()
+                .map(|x| json!({ "url": x }))
+                .collect::<Vec<_>>(),
+            "paths": paths,
+        });
+        if !security_schemes.is_empty() {
+            document["components"] = json!({ "securitySchemes": security_schemes });
+        }
+        document
+    }
+
+    pub fn to_json_string(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(&self.to_json())
+    }
+
+    pub fn to_yaml_string(&self) -> Result<String, serde_yaml::Error> {
+        serde_yaml::to_string(&self.to_json())
+    }
+}
+
+impl Extend<Item> for OpenApiInference {
+    fn extend<T: IntoIterator<Item = Item>>(&mut self, iter: T) {
+        for item in iter {
+            self.add_item(&item)
+        }
+    }
+}
+
+fn body_schema(mime_type: &str, body: &[u8]) -> Option<Schema> {
+    if mime_type == "application/json" || mime_type.ends_with("+json") {
+        serde_json::from_slice::<Value>(body)
+            .ok()
+            .map(|x| Schema::from_json(&x))
+    } else if mime_type == "application/x-www-form-urlencoded" {
+        let body = String::from_utf8_lossy(body);
+        let properties: BTreeMap<String, Schema> = query_pairs(&body)
+            .map(|(k, _)| (k.to_owned(), Schema::String(None)))
+            .collect();
+        Some(Schema::Object {
+            required: properties.keys().cloned().collect(),
+            properties,
+        })
+    } else {
+        None
+    }
+}
+
+fn merge_content(
+    contents: &mut BTreeMap<String, Option<Schema>>,
+    mime_type: String,
+    schema: Option<Schema>,
+) {
+    let merged = match (contents.remove(&mime_type), schema) {
+        (Some(Some(a)), Some(b)) => Some(a.merge(b)),
+        (Some(a), b) => a.or(b),
+        (None, b) => b,
+    };
+    contents.insert(mime_type, merged);
+}
+
+fn content_to_json(contents: &BTreeMap<String, Option<Schema>>) -> Value {
+    contents
+        .iter()
+        .map(|(mime_type, schema)| {
+            (
+                mime_type.to_owned(),
+                match schema {
+                    Some(x) => json!({ "schema": x.to_json() }),
+                    None => json!({}),
+                },
+            )
+        })
+        .collect::<Map<_, _>>()
+        .into()
+}
+
+fn operation_to_json(operation: &Operation) -> Value {
+    let parameters: Vec<Value> = operation
+        .params
+        .iter()
+        .map(|((location, name), (schema, count))| {
+            json!({
+                "name": name,
+                "in": location.as_str(),
+                "required": *location == ParamLocation::Path || *count == operation.samples,
+                "schema": schema.to_json(),
+            })
+        })
+        .collect();
+
+    let responses: Map<String, Value> = operation
+        .responses
+        .iter()
+        .map(|(status, contents)| {
+            let mut response = json!({
+                "description": StatusCode::from_u16(*status)
+                    .ok()
+                    .and_then(|x| x.canonical_reason())
+                    .unwrap_or("Response"),
+            });
+            if !contents.is_empty() {
+                response["content"] = content_to_json(contents);
+            }
+            (status.to_string(), response)
+        })
+        .collect();
+
+    let mut value = json!({ "responses": responses });
+    if !parameters.is_empty() {
+        value["parameters"] = parameters.into();
+    }
+    if !operation.request_bodies.is_empty() {
+        value["requestBody"] = json!({ "content": content_to_json(&operation.request_bodies) });
+    }
+    if !operation.security.is_empty() {
+        value["security"] = operation
+            .security
+            .iter()
+            .map(|x| json!({ *x: [] }))
+            .collect();
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{fs::File, io::BufReader};
+
+    use http::Method;
+
+    use crate::http_history::items::Items;
+
+    #[test]
+    fn test_path_template() {
+        assert_eq!(
+            path_template("/users/42/posts/0b9a7c0e-1f43-4a8e-9c1d-2f6a4b3c5d7e"),
+            (
+                "/users/{id}/posts/{id2}".to_owned(),
+                vec![
+                    ("id".to_owned(), "42".to_owned()),
+                    (
+                        "id2".to_owned(),
+                        "0b9a7c0e-1f43-4a8e-9c1d-2f6a4b3c5d7e".to_owned()
+                    )
+                ]
+            )
+        );
+        assert_eq!(path_template("/api/v1/deadbeefdeadbeef1").0, "/api/v1/{id}");
+        assert_eq!(path_template("/api/v1/status").0, "/api/v1/status");
+    }
+
+    #[test]
+    fn test_schema_merge() {
+        let a = Schema::from_json(&json!({ "id": 1, "name": "a", "tags": [] }));
+        let b = Schema::from_json(&json!({ "id": 1.5, "name": null, "tags": ["x"] }));
+        assert_eq!(
+            a.merge(b).to_json(),
+            json!({
+                "type": "object",
+                "properties": {
+                    "id": { "type": "number" },
+                    "name": { "type": "string", "nullable": true },
+                    "tags": { "type": "array", "items": { "type": "string" } },
+                },
+                "required": ["id", "name", "tags"],
+            })
+        );
+    }
+
+    #[test]
+    fn test_inference() -> Result<(), Box<dyn std::error::Error>> {
+        let mut inference = OpenApiInference::default();
+        for (id, body) in [(1, r#"{"a":1}"#), (2, r#"{"a":2,"b":"x"}"#)] {
+            let mut item = Item {
+                method: Method::PUT,
+                path: format!("/users/{id}?verbose=true"),
+                host: (Default::default(), "example.com".to_owned()),
+                port: 443,
+                protocol: Scheme::HTTPS,
+                status: StatusCode::NO_CONTENT,
+                ..Default::default()
+            };
+            item.set_request_bytes(format!("PUT /users/{id}?verbose=true HTTP/1.1\r\nHost: example.com\r\nAuthorization: Bearer t\r\nX-Request-Id: {id}\r\nContent-Type: application/json\r\n\r\n{body}"));
+            item.set_response_bytes("HTTP/1.1 204 No Content\r\n\r\n");
+            inference.add_item(&item);
+        }
+
+        let file = File::open("tests/http_history_files/burpsuite_community_v2021.3.2.xml")?;
+        inference.extend(Items::from_reader(BufReader::new(file))?.filter_map(|x| x.ok()));
+
+        let document = inference.to_json();
+        assert_eq!(
+            document["servers"],
+            json!([
+                { "url": "http://httpbin.org" },
+                { "url": "https://example.com" },
+                { "url": "https://httpbin.org" },
+            ])
+        );
+
+        let operation = &document["paths"]["/users/{id}"]["put"];
+        assert_eq!(
+            operation["parameters"][0],
+            json!({ "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } })
+        );
+        assert_eq!(operation["parameters"][1]["name"], "verbose");
+        assert_eq!(operation["parameters"][1]["schema"]["type"], "boolean");
+        assert_eq!(operation["parameters"][2]["name"], "X-Request-Id");
+        assert_eq!(
+            operation["requestBody"]["content"]["application/json"]["schema"]["required"],
+            json!(["a"])
+        );
+        assert_eq!(operation["responses"]["204"]["description"], "No Content");
+        assert_eq!(operation["security"], json!([{ "bearerAuth": [] }]));
+
+        let operation = &document["paths"]["/get"]["get"];
+        assert_eq!(operation["parameters"][0]["name"], "foo");
+        assert_eq!(
+            operation["responses"]["200"]["content"]["application/json"]["schema"]["type"],
+            "object"
+        );
+
+        assert!(inference.to_yaml_string()?.starts_with("openapi: 3.0.3\n"));
+        assert!(inference.to_json_string()?.contains("\"/post\""));
+
+        Ok(())
+    }
+}
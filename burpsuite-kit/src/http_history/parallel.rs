@@ -0,0 +1,267 @@
+use std::{collections::VecDeque, io::BufRead, mem, sync::Arc};
+
+use rayon::{prelude::*, ThreadPool};
+
+use super::{
+    index::{parse_item, parse_items, read_header, IndexError, Scanner},
+    item::Item,
+    items::{ItemParseError, Items, ItemsAttr},
+};
+
+pub const CHUNK_LEN_DEFAULT: usize = 4 * 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct ParallelOptions {
+    /// Bytes of `<item>` elements parsed by one task, an item is never split.
+    pub chunk_len: usize,
+    /// Chunks read ahead and parsed together, defaults to twice the pool threads.
+    pub chunks_per_batch: Option<usize>,
+    /// Pool the chunks are parsed on, the global rayon pool when unset.
+    pub thread_pool: Option<Arc<ThreadPool>>,
+}
+
+impl Default for ParallelOptions {
+    fn default() -> Self {
+        Self {
+            chunk_len: CHUNK_LEN_DEFAULT,
+            chunks_per_batch: None,
+            thread_pool: None,
+        }
+    }
+}
+
+//
+/// Items of a Burp XML export parsed on a rayon pool, yielded in document order.
+///
+/// The document is split at `<item>` boundaries found by the same markup scanner as
+/// [`ItemsIndex`](super::index::ItemsIndex), so `<item>` inside CDATA sections or
+/// comments never splits a chunk.
+pub struct ParallelItems<R> {
+    pub attr: ItemsAttr,
+    header: Vec<u8>,
+    reader: R,
+    chunk_len: usize,
+    chunks_per_batch: usize,
+    thread_pool: Option<Arc<ThreadPool>>,
+    scanner: Scanner,
+    /// Unscanned bytes and the item being scanned, `buf[0]` is at offset `buf_base`.
+    buf: Vec<u8>,
+    buf_base: u64,
+    scan_pos: usize,
+    is_eof: bool,
+    parsed: VecDeque<Result<Item, IndexError>>,
+}
+
+impl<R> ParallelItems<R>
+where
+    R: BufRead,
+{
+    pub fn from_reader(reader: R) -> Result<Self, IndexError> {
+        Self::with_options(reader, ParallelOptions::default())
+    }
+
+    pub fn with_options(mut reader: R, options: ParallelOptions) -> Result<Self, IndexError> {
+        let header = read_header(&mut reader)?;
+        let attr = Items::from_reader(&header[..])?.attr;
+        let threads = match &options.thread_pool {
+            Some(x) => x.current_num_threads(),
+            None => rayon::current_num_threads(),
+        };
+
+        Ok(Self {
+            attr,
+            buf_base: header.len() as u64,
+            header,
+            reader,
+            chunk_len: options.chunk_len.max(1),
+            chunks_per_batch: options.chunks_per_batch.unwrap_or(threads * 2).max(1),
+            thread_pool: options.thread_pool,
+            scanner: Scanner::default(),
+            buf: vec![],
+            scan_pos: 0,
+            is_eof: false,
+            parsed: VecDeque::new(),
+        })
+    }
+
+    /// Read the next chunks, up to `chunks_per_batch` of them.
+    fn read_batch(&mut self) -> Result<Vec<Chunk>, IndexError> {
+        let mut chunks = vec![];
+        let mut chunk = Chunk::default();
+        loop {
+            let mut n = 0;
+            for range in self.scanner.ranges.iter() {
+                if chunks.len() >= self.chunks_per_batch {
+                    break;
+                }
+                let start = (range.start - self.buf_base) as usize;
+                let end = (range.end - self.buf_base) as usize;
+                chunk.bytes.extend_from_slice(&self.buf[start..end]);
+                chunk.ends.push(chunk.bytes.len());
+                if chunk.bytes.len() >= self.chunk_len {
+                    chunks.push(mem::take(&mut chunk));
+                }
+                n += 1;
+            }
+            self.scanner.ranges.drain(..n);
+
+            // Keep the items not chunked yet and the start of an item still being scanned.
+            let done = match self.scanner.ranges.first().map(|x| x.start) {
+                Some(x) => Some(x),
+                None => self.scanner.item_start,
+            }
+            .map(|x| ((x - self.buf_base) as usize).min(self.scan_pos))
+            .unwrap_or(self.scan_pos);
+            self.buf.drain(..done);
+            self.buf_base += done as u64;
+            self.scan_pos -= done;
+
+            if chunks.len() >= self.chunks_per_batch || self.is_eof {
+                break;
+            }
+
+            let data = self.reader.fill_buf()?;
+            self.is_eof = data.is_empty();
+            self.buf.extend_from_slice(data);
+            let n = data.len();
+            self.reader.consume(n);
+
+            let base = self.buf_base + self.scan_pos as u64;
+            self.scan_pos += self
+                .scanner
+                .scan(&self.buf[self.scan_pos..], base, self.is_eof);
+        }
+        if !chunk.ends.is_empty() {
+            chunks.push(chunk);
+        }
+        Ok(chunks)
+    }
+
+    /// Whether every item has been read from the reader.
+    fn is_done(&self) -> bool {
+        self.is_eof && self.scanner.ranges.is_empty()
+    }
+
+    fn parse_batch(&self, chunks: Vec<Chunk>) -> Vec<Vec<Result<Item, IndexError>>> {
+        let header = &self.header[..];
+        let parse = || chunks.par_iter().map(|x| x.parse(header)).collect();
+        match &self.thread_pool {
+            Some(x) => x.install(parse),
+            None => parse(),
+        }
+    }
+}
+
+impl<R> Iterator for ParallelItems<R>
+where
+    R: BufRead,
+{
+    type Item = Result<Item, IndexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.parsed.is_empty() && !self.is_done() {
+            let chunks = match self.read_batch() {
+                Ok(x) => x,
+                Err(err) => {
+                    self.is_eof = true;
+                    return Some(Err(err));
+                }
+            };
+            let parsed = self.parse_batch(chunks);
+            self.parsed.extend(parsed.into_iter().flatten());
+
+            if self.is_done() && self.scanner.item_start.take().is_some() {
+                self.parsed.push_back(Err(IndexError::ItemParseError(
+                    ItemParseError::UnexpectedEof,
+                )));
+            }
+        }
+        self.parsed.pop_front()
+    }
+}
+
+/// Consecutive `<item>` elements and the end offset of each.
+#[derive(Default)]
+struct Chunk {
+    bytes: Vec<u8>,
+    ends: Vec<usize>,
+}
+
+impl Chunk {
+    fn parse(&self, header: &[u8]) -> Vec<Result<Item, IndexError>> {
+        let items = parse_items(header, &self.bytes)
+            .and_then(|x| x.collect::<Result<Vec<_>, _>>().map_err(Into::into));
+        match items {
+            Ok(items) if items.len() == self.ends.len() => items.into_iter().map(Ok).collect(),
+            // Parsed again item by item, so an invalid item does not hide the others.
+            _ => {
+                let mut start = 0;
+                self.ends
+                    .iter()
+                    .map(|end| {
+                        let item = parse_item(header, &self.bytes[start..*end]);
+                        start = *end;
+                        item
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{fs, io::BufReader};
+
+    #[test]
+    fn test_parallel_items() -> Result<(), Box<dyn std::error::Error>> {
+        let bytes = fs::read("tests/http_history_files/burpsuite_community_v2021.3.2.xml")?;
+        let items = Items::from_reader(&bytes[..])?.collect::<Result<Vec<_>, _>>()?;
+
+        // One item per chunk and a small buffer, across several batches.
+        let options = ParallelOptions {
+            chunk_len: 1,
+            chunks_per_batch: Some(1),
+            thread_pool: Some(Arc::new(
+                rayon::ThreadPoolBuilder::new().num_threads(2).build()?,
+            )),
+        };
+        let parallel_items =
+            ParallelItems::with_options(BufReader::with_capacity(5, &bytes[..]), options)?;
+        assert_eq!(parallel_items.attr.burp_version, "2021.3.2");
+        let parallel_items = parallel_items.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(parallel_items.len(), items.len());
+        for (x, y) in parallel_items.iter().zip(items.iter()) {
+            assert_eq!(x.url, y.url);
+            assert_eq!(x.response.1, y.response.1);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parallel_items_invalid() -> Result<(), Box<dyn std::error::Error>> {
+        let xml = fs::read_to_string("tests/http_history_files/burpsuite_community_v2021.3.2.xml")?;
+        let start = xml.find("<item>").ok_or("item missing")?;
+        let end = xml.rfind("</items>").ok_or("items missing")?;
+        // An invalid item first, a truncated one last.
+        let xml = format!(
+            "{}<item><time>x</time></item>\n{}<item><url>",
+            &xml[..start],
+            &xml[start..end]
+        );
+
+        let results = ParallelItems::from_reader(xml.as_bytes())?.collect::<Vec<_>>();
+        assert_eq!(results.len(), 4);
+        assert!(results[0].is_err());
+        assert!(results[1].is_ok() && results[2].is_ok());
+        assert!(matches!(
+            results[3],
+            Err(IndexError::ItemParseError(ItemParseError::UnexpectedEof))
+        ));
+
+        Ok(())
+    }
+}
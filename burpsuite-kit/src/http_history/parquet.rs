@@ -0,0 +1,315 @@
+use std::{io::Write, sync::Arc};
+
+use arrow_array::{
+    builder::{
+        BinaryBuilder, MapBuilder, StringBuilder, TimestampMillisecondBuilder, UInt16Builder,
+        UInt32Builder,
+    },
+    ArrayRef, RecordBatch,
+};
+use arrow_schema::{ArrowError, DataType, Field, Schema, SchemaRef, TimeUnit};
+use parquet::{
+    arrow::ArrowWriter, basic::Compression, errors::ParquetError,
+    file::properties::WriterProperties,
+};
+
+use super::{item::Item, message::Message};
+
+pub const DEFAULT_ROW_GROUP_SIZE: usize = 8192;
+
+/// Rows buffered in builders before being handed to the Parquet writer.
+const BATCH_SIZE: usize = 1024;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ParquetExportError {
+    #[error("ArrowError {0}")]
+    ArrowError(#[from] ArrowError),
+    #[error("ParquetError {0}")]
+    ParquetError(#[from] ParquetError),
+}
+
+/// Arrow schema of exported items.
+///
+/// `request` and `response` hold the decoded bytes, null when the payload is not
+/// valid base64. The header maps keep names as sent and repeated headers as
+/// repeated entries.
+pub fn schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new(
+            "time",
+            DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())),
+            false,
+        ),
+        Field::new("url", DataType::Utf8, false),
+        Field::new("host", DataType::Utf8, false),
+        Field::new("ip", DataType::Utf8, false),
+        Field::new("port", DataType::UInt16, false),
+        Field::new("protocol", DataType::Utf8, false),
+        Field::new("method", DataType::Utf8, false),
+        Field::new("path", DataType::Utf8, false),
+        Field::new("extension", DataType::Utf8, true),
+        Field::new("status", DataType::UInt16, false),
+        Field::new("response_length", DataType::UInt32, false),
+        Field::new("mimetype", DataType::Utf8, false),
+        Field::new("comment", DataType::Utf8, true),
+        Field::new("request", DataType::Binary, true),
+        Field::new("response", DataType::Binary, true),
+        headers_field("request_headers"),
+        headers_field("response_headers"),
+    ]))
+}
+
+fn headers_field(name: &str) -> Field {
+    Field::new_map(
+        name,
+        "entries",
+        Field::new("keys", DataType::Utf8, false),
+        Field::new("values", DataType::Utf8, true),
+        false,
+        false,
+    )
+}
+
+#[derive(Debug, Clone)]
+pub struct ParquetExportOptions {
+    pub row_group_size: usize,
+    pub compression: Compression,
+}
+
+impl Default for ParquetExportOptions {
+    fn default() -> Self {
+        Self {
+            row_group_size: DEFAULT_ROW_GROUP_SIZE,
+            compression: Compression::SNAPPY,
+        }
+    }
+}
+
+/// Streams items into a Parquet file, one row group per `row_group_size` items.
+pub struct ParquetItemsWriter<W>
+where
+    W: Write + Send,
+{
+    writer: ArrowWriter<W>,
+    columns: Columns,
+}
+
+impl<W> ParquetItemsWriter<W>
+where
+    W: Write + Send,
+{
+    pub fn new(writer: W, options: &ParquetExportOptions) -> Result<Self, ParquetExportError> {
+        let props = WriterProperties::builder()
+            .set_max_row_group_size(options.row_group_size)
+            .set_compression(options.compression)
+            .build();
+        Ok(Self {
+            writer: ArrowWriter::try_new(writer, schema(), Some(props))?,
+            columns: Columns::default(),
+        })
+    }
+
+    pub fn write_item(&mut self, item: &Item) -> Result<(), ParquetExportError> {
+        self.columns.append(item)?;
+        if self.columns.len >= BATCH_SIZE {
+            self.flush_batch()?;
+        }
+        Ok(())
+    }
+
+    fn flush_batch(&mut self) -> Result<(), ParquetExportError> {
+        if self.columns.len > 0 {
+            let batch = self.columns.finish()?;
+            self.writer.write(&batch)?;
+        }
+        Ok(())
+    }
+
+    /// Write the remaining rows and the footer, returning the inner writer.
+    pub fn finish(mut self) -> Result<W, ParquetExportError> {
+        self.flush_batch()?;
+        Ok(self.writer.into_inner()?)
+    }
+}
+
+#[derive(Default)]
+struct Columns {
+    len: usize,
+    time: TimestampMillisecondBuilder,
+    url: StringBuilder,
+    host: StringBuilder,
+    ip: StringBuilder,
+    port: UInt16Builder,
+    protocol: StringBuilder,
+    method: StringBuilder,
+    path: StringBuilder,
+    extension: StringBuilder,
+    status: UInt16Builder,
+    response_length: UInt32Builder,
+    mimetype: StringBuilder,
+    comment: StringBuilder,
+    request: BinaryBuilder,
+    response: BinaryBuilder,
+    request_headers: HeadersBuilder,
+    response_headers: HeadersBuilder,
+}
+
+struct HeadersBuilder(MapBuilder<StringBuilder, StringBuilder>);
+
+impl Default for HeadersBuilder {
+    fn default() -> Self {
+        Self(MapBuilder::new(
+            None,
+            StringBuilder::new(),
+            StringBuilder::new(),
+        ))
+    }
+}
+
+impl HeadersBuilder {
+    fn append(&mut self, bytes: Option<&[u8]>) -> Result<(), ArrowError> {
+        if let Some(message) = bytes.and_then(|x| Message::parse(x).ok()) {
+            for header in message.headers.iter() {
+                self.0.keys().append_value(header.name);
+                self.0
+                    .values()
+                    .append_value(String::from_utf8_lossy(header.value));
+            }
+        }
+        self.0.append(true)
+    }
+}
+
+impl Columns {
+    fn append(&mut self, item: &Item) -> Result<(), ParquetExportError> {
+        let request = item.request_bytes().ok();
+        let response = item.response_bytes().ok();
+
+        self.time
+            .append_value(item.time.and_utc().timestamp_millis());
+        self.url.append_value(&item.url);
+        self.host.append_value(&item.host.1);
+        self.ip
+            .append_value(String::from_utf8_lossy(&item.host.0.ip));
+        self.port.append_value(item.port);
+        self.protocol.append_value(item.protocol.as_str());
+        self.method.append_value(item.method.as_str());
+        self.path.append_value(&item.path);
+        self.extension.append_option(item.extension.as_deref());
+        self.status.append_value(item.status.as_u16());
+        self.response_length.append_value(item.response_length);
+        self.mimetype.append_value(&item.mimetype);
+        self.comment.append_option(item.comment.as_deref());
+        self.request.append_option(request.as_deref());
+        self.response.append_option(response.as_deref());
+        self.request_headers.append(request.as_deref())?;
+        self.response_headers.append(response.as_deref())?;
+
+        self.len += 1;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<RecordBatch, ArrowError> {
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(self.time.finish().with_timezone("UTC")),
+            Arc::new(self.url.finish()),
+            Arc::new(self.host.finish()),
+            Arc::new(self.ip.finish()),
+            Arc::new(self.port.finish()),
+            Arc::new(self.protocol.finish()),
+            Arc::new(self.method.finish()),
+            Arc::new(self.path.finish()),
+            Arc::new(self.extension.finish()),
+            Arc::new(self.status.finish()),
+            Arc::new(self.response_length.finish()),
+            Arc::new(self.mimetype.finish()),
+            Arc::new(self.comment.finish()),
+            Arc::new(self.request.finish()),
+            Arc::new(self.response.finish()),
+            Arc::new(self.request_headers.0.finish()),
+            Arc::new(self.response_headers.0.finish()),
+        ];
+        self.len = 0;
+        RecordBatch::try_new(schema(), columns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{env, fs::File, io::BufReader};
+
+    use arrow_array::{cast::AsArray as _, types::UInt16Type};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    use crate::http_history::items::Items;
+
+    #[test]
+    fn test_export() -> Result<(), Box<dyn std::error::Error>> {
+        let path = env::temp_dir().join(format!(
+            "burpsuite_kit_parquet_{}.parquet",
+            std::process::id()
+        ));
+
+        let options = ParquetExportOptions {
+            row_group_size: 3,
+            ..Default::default()
+        };
+        let mut writer = ParquetItemsWriter::new(File::create(&path)?, &options)?;
+        for name in [
+            "burpsuite_community_v2021.3.2.xml",
+            "burpsuite_community_v2020.12.1.xml",
+        ] {
+            let file = File::open(format!("tests/http_history_files/{name}"))?;
+            for item in Items::from_reader(BufReader::new(file))? {
+                writer.write_item(&item?)?;
+            }
+        }
+        writer.finish()?;
+
+        let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(&path)?)?;
+        assert_eq!(builder.metadata().num_row_groups(), 2);
+        assert_eq!(builder.schema(), &schema());
+
+        let batches = builder.build()?.collect::<Result<Vec<_>, _>>()?;
+        let rows: usize = batches.iter().map(|x| x.num_rows()).sum();
+        assert_eq!(rows, 4);
+
+        let batch = &batches[0];
+        assert_eq!(batch.column(2).as_string::<i32>().value(0), "httpbin.org");
+        assert_eq!(batch.column(9).as_primitive::<UInt16Type>().value(0), 200);
+        assert!(batch
+            .column(13)
+            .as_binary::<i32>()
+            .value(0)
+            .starts_with(b"GET /get?foo=bar HTTP/1.1\r\n"));
+
+        let headers = batch.column(16).as_map();
+        let keys = headers.keys().as_string::<i32>();
+        let values = headers.values().as_string::<i32>();
+        let server = (headers.value_offsets()[0]..headers.value_offsets()[1])
+            .map(|x| x as usize)
+            .find(|x| keys.value(*x) == "Server")
+            .map(|x| values.value(x));
+        assert_eq!(server, Some("gunicorn/19.9.0"));
+
+        // An undecodable payload is null rather than aborting the export.
+        let mut item = Item::default();
+        item.request.0.base64 = true;
+        item.request.1 = b"GET / HTTP/1.1\r\n\r\n".to_vec();
+        item.set_response_bytes(b"HTTP/1.1 204 No Content\r\n\r\n");
+        let mut writer = ParquetItemsWriter::new(File::create(&path)?, &options)?;
+        writer.write_item(&item)?;
+        writer.finish()?;
+        let batches = ParquetRecordBatchReaderBuilder::try_new(File::open(&path)?)?
+            .build()?
+            .collect::<Result<Vec<_>, _>>()?;
+        assert!(batches[0].column(13).is_null(0));
+        assert!(batches[0].column(14).is_valid(0));
+        assert_eq!(batches[0].column(15).as_map().value_length(0), 0);
+
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,625 @@
+use std::borrow::Cow;
+
+use http::uri::Scheme;
+use strum::Display;
+
+use super::{item::Item, message::Message};
+
+//
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Display)]
+#[strum(serialize_all = "snake_case")]
+pub enum Severity {
+    Info,
+    Low,
+    Medium,
+    High,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// Index of the originating item in the scanned stream.
+    pub item_index: usize,
+    pub url: String,
+    pub check: &'static str,
+    pub severity: Severity,
+    pub title: String,
+    pub detail: String,
+}
+
+/// What a check sees of one item.
+///
+/// `request` and `response` are `None` when the payload cannot be decoded or parsed.
+pub struct CheckContext<'a> {
+    pub item_index: usize,
+    pub item: &'a Item,
+    pub request: Option<&'a Message<'a>>,
+    pub response: Option<&'a Message<'a>>,
+}
+
+impl<'a> CheckContext<'a> {
+    pub fn finding(
+        &self,
+        check: &'static str,
+        severity: Severity,
+        title: impl Into<String>,
+        detail: impl Into<String>,
+    ) -> Finding {
+        Finding {
+            item_index: self.item_index,
+            url: self.item.url.to_owned(),
+            check,
+            severity,
+            title: title.into(),
+            detail: detail.into(),
+        }
+    }
+
+    pub fn is_https(&self) -> bool {
+        self.item.protocol == Scheme::HTTPS
+    }
+
+    pub fn response_mime_type(&self) -> Option<String> {
+        self.response.and_then(|x| x.mime_type())
+    }
+
+    pub fn response_body_text(&self) -> Option<Cow<'a, str>> {
+        self.response.map(|x| String::from_utf8_lossy(x.body))
+    }
+}
+
+pub trait PassiveCheck {
+    fn name(&self) -> &'static str;
+
+    fn check(&self, ctx: &CheckContext<'_>, findings: &mut Vec<Finding>);
+}
+
+//
+pub struct Scanner {
+    checks: Vec<Box<dyn PassiveCheck>>,
+}
+
+impl Default for Scanner {
+    fn default() -> Self {
+        let mut scanner = Self::empty();
+        scanner
+            .add_check(SecurityHeadersCheck)
+            .add_check(CookieFlagsCheck)
+            .add_check(CorsCheck)
+            .add_check(MixedContentCheck)
+            .add_check(CacheableSensitiveCheck)
+            .add_check(ServerBannerCheck)
+            .add_check(DirectoryListingCheck)
+            .add_check(StackTraceCheck);
+        scanner
+    }
+}
+
+impl Scanner {
+    pub fn empty() -> Self {
+        Self { checks: vec![] }
+    }
+
+    pub fn add_check(&mut self, check: impl PassiveCheck + 'static) -> &mut Self {
+        self.checks.push(Box::new(check));
+        self
+    }
+
+    pub fn check_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.checks.iter().map(|x| x.name())
+    }
+
+    pub fn scan_item(&self, item_index: usize, item: &Item) -> Vec<Finding> {
+        let request_bytes = item.request_bytes().ok();
+        let request = request_bytes
+            .as_deref()
+            .and_then(|x| Message::parse(x).ok());
+        let response_bytes = item.response_bytes().ok();
+        let response = response_bytes
+            .as_deref()
+            .and_then(|x| Message::parse(x).ok());
+
+        let ctx = CheckContext {
+            item_index,
+            item,
+            request: request.as_ref(),
+            response: response.as_ref(),
+        };
+
+        let mut findings = vec![];
+        for check in self.checks.iter() {
+            check.check(&ctx, &mut findings);
+        }
+        findings
+    }
+
+    pub fn scan<'a>(&self, items: impl IntoIterator<Item = &'a Item>) -> Vec<Finding> {
+        items
+            .into_iter()
+            .enumerate()
+            .flat_map(|(i, item)| self.scan_item(i, item))
+            .collect()
+    }
+}
+
+//
+pub struct SecurityHeadersCheck;
+
+impl PassiveCheck for SecurityHeadersCheck {
+    fn name(&self) -> &'static str {
+        "security_headers"
+    }
+
+    fn check(&self, ctx: &CheckContext<'_>, findings: &mut Vec<Finding>) {
+        let response = match ctx.response {
+            Some(x) => x,
+            None => return,
+        };
+
+        if ctx.is_https() {
+            match response.header_str("strict-transport-security") {
+                None => findings.push(ctx.finding(
+                    self.name(),
+                    Severity::Low,
+                    "Strict-Transport-Security missing",
+                    "HTTPS response without an HSTS header",
+                )),
+                Some(value) => {
+                    let max_age = value
+                        .split(';')
+                        .filter_map(|x| x.trim().split_once('='))
+                        .find(|(k, _)| k.trim().eq_ignore_ascii_case("max-age"))
+                        .and_then(|(_, v)| v.trim().trim_matches('"').parse::<u64>().ok());
+                    if max_age.unwrap_or(0) < 15_552_000 {
+                        findings.push(ctx.finding(
+                            self.name(),
+                            Severity::Low,
+                            "Strict-Transport-Security weak",
+                            format!("max-age below 180 days: {value}"),
+                        ))
+                    }
+                }
+            }
+        }
+
+        if ctx.response_mime_type().as_deref() != Some("text/html") {
+            return;
+        }
+
+        match response.header_str("x-content-type-options") {
+            Some(value) if value.eq_ignore_ascii_case("nosniff") => {}
+            _ => findings.push(ctx.finding(
+                self.name(),
+                Severity::Low,
+                "X-Content-Type-Options missing",
+                "HTML response without X-Content-Type-Options: nosniff",
+            )),
+        }
+
+        let csp = response.header_str("content-security-policy");
+        match csp {
+            None => findings.push(ctx.finding(
+                self.name(),
+                Severity::Low,
+                "Content-Security-Policy missing",
+                "HTML response without a Content-Security-Policy header",
+            )),
+            Some(value) => {
+                let lowercase = value.to_ascii_lowercase();
+                if lowercase.contains("'unsafe-inline'") || lowercase.contains("'unsafe-eval'") {
+                    findings.push(ctx.finding(
+                        self.name(),
+                        Severity::Low,
+                        "Content-Security-Policy weak",
+                        format!("policy allows unsafe sources: {value}"),
+                    ))
+                }
+            }
+        }
+
+        let has_frame_ancestors = csp
+            .map(|x| x.to_ascii_lowercase().contains("frame-ancestors"))
+            .unwrap_or(false);
+        if !has_frame_ancestors && response.header("x-frame-options").is_none() {
+            findings.push(ctx.finding(
+                self.name(),
+                Severity::Low,
+                "Clickjacking protection missing",
+                "HTML response without CSP frame-ancestors or X-Frame-Options",
+            ))
+        }
+    }
+}
+
+//
+pub struct CookieFlagsCheck;
+
+impl PassiveCheck for CookieFlagsCheck {
+    fn name(&self) -> &'static str {
+        "cookie_flags"
+    }
+
+    fn check(&self, ctx: &CheckContext<'_>, findings: &mut Vec<Finding>) {
+        let response = match ctx.response {
+            Some(x) => x,
+            None => return,
+        };
+
+        for value in response.headers_all("set-cookie") {
+            let value = String::from_utf8_lossy(value);
+            let mut parts = value.split(';').map(|x| x.trim());
+            let name = parts
+                .next()
+                .and_then(|x| x.split_once('=').map(|(k, _)| k))
+                .unwrap_or_default();
+            let attrs: Vec<String> = parts
+                .map(|x| {
+                    x.split('=')
+                        .next()
+                        .unwrap_or_default()
+                        .trim()
+                        .to_ascii_lowercase()
+                })
+                .collect();
+
+            let missing: Vec<&str> = [
+                ("secure", "Secure"),
+                ("httponly", "HttpOnly"),
+                ("samesite", "SameSite"),
+            ]
+            .iter()
+            .filter(|(attr, _)| !attrs.iter().any(|x| x == attr))
+            .map(|(_, display)| *display)
+            .collect();
+
+            if !missing.is_empty() {
+                findings.push(ctx.finding(
+                    self.name(),
+                    if missing.contains(&"Secure") && ctx.is_https() {
+                        Severity::Medium
+                    } else {
+                        Severity::Low
+                    },
+                    format!("Cookie {name} without {}", missing.join(", ")),
+                    format!("Set-Cookie for {name} lacks {}", missing.join(", ")),
+                ))
+            }
+        }
+    }
+}
+
+//
+pub struct CorsCheck;
+
+impl PassiveCheck for CorsCheck {
+    fn name(&self) -> &'static str {
+        "cors"
+    }
+
+    fn check(&self, ctx: &CheckContext<'_>, findings: &mut Vec<Finding>) {
+        let response = match ctx.response {
+            Some(x) => x,
+            None => return,
+        };
+        let allow_origin = match response.header_str("access-control-allow-origin") {
+            Some(x) => x,
+            None => return,
+        };
+        let allow_credentials = response
+            .header_str("access-control-allow-credentials")
+            .map(|x| x.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+        let request_origin = ctx.request.and_then(|x| x.header_str("origin"));
+
+        let (severity, title) = if allow_origin == "null" {
+            (Severity::Medium, "CORS allows null origin")
+        } else if request_origin.is_some() && request_origin == Some(allow_origin) {
+            if allow_credentials {
+                (Severity::High, "CORS reflects origin with credentials")
+            } else {
+                (Severity::Low, "CORS reflects origin")
+            }
+        } else if allow_origin == "*" {
+            if allow_credentials {
+                (Severity::Medium, "CORS wildcard origin with credentials")
+            } else {
+                (Severity::Info, "CORS wildcard origin")
+            }
+        } else {
+            return;
+        };
+
+        findings.push(ctx.finding(
+            self.name(),
+            severity,
+            title,
+            format!(
+                "Access-Control-Allow-Origin: {allow_origin}, Access-Control-Allow-Credentials: {allow_credentials}"
+            ),
+        ))
+    }
+}
+
+//
+pub struct MixedContentCheck;
+
+impl PassiveCheck for MixedContentCheck {
+    fn name(&self) -> &'static str {
+        "mixed_content"
+    }
+
+    fn check(&self, ctx: &CheckContext<'_>, findings: &mut Vec<Finding>) {
+        if !ctx.is_https() || ctx.response_mime_type().as_deref() != Some("text/html") {
+            return;
+        }
+        let body = match ctx.response_body_text() {
+            Some(x) => x.to_ascii_lowercase(),
+            None => return,
+        };
+
+        let urls: Vec<&str> = [
+            "src=\"http://",
+            "src='http://",
+            "href=\"http://",
+            "action=\"http://",
+        ]
+        .iter()
+        .flat_map(|pattern| body.match_indices(pattern))
+        .filter_map(|(i, pattern)| {
+            let start = i + pattern.len() - "http://".len();
+            let rest = &body[start..];
+            rest.find(['"', '\'']).map(|end| &rest[..end])
+        })
+        .collect();
+
+        if !urls.is_empty() {
+            findings.push(ctx.finding(
+                self.name(),
+                Severity::Low,
+                "Mixed content",
+                format!("HTTPS page loads HTTP resources: {}", urls.join(", ")),
+            ))
+        }
+    }
+}
+
+//
+pub struct CacheableSensitiveCheck;
+
+impl PassiveCheck for CacheableSensitiveCheck {
+    fn name(&self) -> &'static str {
+        "cacheable_sensitive"
+    }
+
+    fn check(&self, ctx: &CheckContext<'_>, findings: &mut Vec<Finding>) {
+        let (request, response) = match (ctx.request, ctx.response) {
+            (Some(req), Some(res)) => (req, res),
+            _ => return,
+        };
+        if !ctx.item.status.is_success() {
+            return;
+        }
+
+        let is_sensitive = request.header("authorization").is_some()
+            || request.header("cookie").is_some()
+            || response.header("set-cookie").is_some();
+        if !is_sensitive {
+            return;
+        }
+
+        let cache_control = response
+            .header_str("cache-control")
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        if cache_control.contains("no-store") || cache_control.contains("private") {
+            return;
+        }
+
+        findings.push(ctx.finding(
+            self.name(),
+            Severity::Low,
+            "Cacheable sensitive response",
+            format!(
+                "authenticated response without Cache-Control no-store/private (Cache-Control: {})",
+                if cache_control.is_empty() {
+                    "missing"
+                } else {
+                    cache_control.as_str()
+                }
+            ),
+        ))
+    }
+}
+
+//
+pub struct ServerBannerCheck;
+
+impl PassiveCheck for ServerBannerCheck {
+    fn name(&self) -> &'static str {
+        "server_banner"
+    }
+
+    fn check(&self, ctx: &CheckContext<'_>, findings: &mut Vec<Finding>) {
+        let response = match ctx.response {
+            Some(x) => x,
+            None => return,
+        };
+
+        for name in [
+            "server",
+            "x-powered-by",
+            "x-aspnet-version",
+            "x-aspnetmvc-version",
+        ] {
+            if let Some(value) = response.header_str(name) {
+                if value.chars().any(|x| x.is_ascii_digit()) {
+                    findings.push(ctx.finding(
+                        self.name(),
+                        Severity::Info,
+                        "Verbose server banner",
+                        format!("{name}: {value}"),
+                    ))
+                }
+            }
+        }
+    }
+}
+
+//
+pub struct DirectoryListingCheck;
+
+impl PassiveCheck for DirectoryListingCheck {
+    fn name(&self) -> &'static str {
+        "directory_listing"
+    }
+
+    fn check(&self, ctx: &CheckContext<'_>, findings: &mut Vec<Finding>) {
+        if !ctx.item.status.is_success() {
+            return;
+        }
+        let body = match ctx.response_body_text() {
+            Some(x) => x,
+            None => return,
+        };
+
+        if [
+            "<title>Index of /",
+            "<h1>Index of /",
+            "<title>Directory listing for /",
+            "[To Parent Directory]",
+        ]
+        .iter()
+        .any(|x| body.contains(x))
+        {
+            findings.push(ctx.finding(
+                self.name(),
+                Severity::Low,
+                "Directory listing",
+                "response looks like a web server directory index",
+            ))
+        }
+    }
+}
+
+//
+pub struct StackTraceCheck;
+
+impl PassiveCheck for StackTraceCheck {
+    fn name(&self) -> &'static str {
+        "stack_trace"
+    }
+
+    fn check(&self, ctx: &CheckContext<'_>, findings: &mut Vec<Finding>) {
+        let body = match ctx.response_body_text() {
+            Some(x) => x,
+            None => return,
+        };
+
+        let marker = [
+            "Traceback (most recent call last)",
+            "Exception in thread \"",
+            "\tat java.",
+            "\n    at java.",
+            "at System.",
+            "Stack trace:",
+            "PHP Fatal error",
+            "PHP Warning:",
+            "<b>Fatal error</b>:",
+            "panicked at",
+            "goroutine 1 [running]",
+            "Microsoft OLE DB Provider",
+        ]
+        .into_iter()
+        .find(|x| body.contains(x));
+
+        if let Some(marker) = marker {
+            findings.push(ctx.finding(
+                self.name(),
+                Severity::Low,
+                "Stack trace disclosure",
+                format!("response body contains {marker:?}"),
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{fs::File, io::BufReader};
+
+    use base64::{engine::general_purpose, Engine as _};
+
+    use crate::http_history::items::Items;
+
+    fn item_with(response: &[u8]) -> Item {
+        let mut item = Item {
+            url: "https://example.com/".to_owned(),
+            protocol: Scheme::HTTPS,
+            ..Default::default()
+        };
+        item.request.1 = b"GET / HTTP/1.1\r\nHost: example.com\r\nCookie: a=1\r\n\r\n".to_vec();
+        item.response.0.base64 = true;
+        item.response.1 = general_purpose::STANDARD.encode(response).into_bytes();
+        item
+    }
+
+    #[test]
+    fn test_default_checks() {
+        let item = item_with(b"HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nSet-Cookie: sid=1; Path=/; HttpOnly\r\nServer: Apache/2.4.1\r\n\r\n<html><title>Index of /files</title><img src=\"http://cdn/x.png\"></html>");
+
+        let findings = Scanner::default().scan_item(7, &item);
+        let checks: Vec<_> = findings.iter().map(|x| x.check).collect();
+        assert!(findings.iter().all(|x| x.item_index == 7));
+        for check in [
+            "security_headers",
+            "cookie_flags",
+            "mixed_content",
+            "cacheable_sensitive",
+            "server_banner",
+            "directory_listing",
+        ] {
+            assert!(checks.contains(&check), "{check} {findings:?}");
+        }
+        assert!(!checks.contains(&"cors"));
+        assert!(!checks.contains(&"stack_trace"));
+    }
+
+    #[test]
+    fn test_custom_check() {
+        struct AlwaysCheck;
+        impl PassiveCheck for AlwaysCheck {
+            fn name(&self) -> &'static str {
+                "always"
+            }
+            fn check(&self, ctx: &CheckContext<'_>, findings: &mut Vec<Finding>) {
+                findings.push(ctx.finding(self.name(), Severity::Info, "always", ""))
+            }
+        }
+
+        let mut scanner = Scanner::empty();
+        scanner.add_check(AlwaysCheck);
+        let item = item_with(b"HTTP/1.1 200 OK\r\n\r\n");
+        let findings = scanner.scan([&item, &item]);
+        assert_eq!(
+            findings.iter().map(|x| x.item_index).collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+    }
+
+    #[test]
+    fn test_http_history_file() -> Result<(), Box<dyn std::error::Error>> {
+        let file = File::open("tests/http_history_files/burpsuite_community_v2021.3.2.xml")?;
+        let items = Items::from_reader(BufReader::new(file))?.collect::<Result<Vec<_>, _>>()?;
+
+        let findings = Scanner::default().scan(items.iter());
+        assert!(findings
+            .iter()
+            .any(|x| x.check == "cors" && x.severity == Severity::Medium));
+        assert!(findings
+            .iter()
+            .any(|x| x.check == "server_banner" && x.detail == "server: gunicorn/19.9.0"));
+
+        Ok(())
+    }
+}
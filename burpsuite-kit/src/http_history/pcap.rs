@@ -0,0 +1,929 @@
+use core::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    str,
+};
+use std::{
+    collections::{btree_map::Entry, BTreeMap, HashMap},
+    io::{Error as IoError, ErrorKind as IoErrorKind, Read},
+};
+
+use chrono::{DateTime, NaiveDateTime};
+use http::uri::Scheme;
+
+use super::{
+    item::{Item, ItemHostAttr},
+    message::Message,
+};
+
+const PCAPNG_SECTION_HEADER: u32 = 0x0A0D_0D0A;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+const PCAPNG_INTERFACE_DESCRIPTION: u32 = 0x0000_0001;
+const PCAPNG_SIMPLE_PACKET: u32 = 0x0000_0003;
+const PCAPNG_ENHANCED_PACKET: u32 = 0x0000_0006;
+
+/// Larger blocks or packets are treated as a corrupt file.
+const MAX_BLOCK_LEN: usize = 256 * 1024 * 1024;
+
+pub const LINKTYPE_NULL: u32 = 0;
+pub const LINKTYPE_ETHERNET: u32 = 1;
+pub const LINKTYPE_RAW: u32 = 101;
+pub const LINKTYPE_LINUX_SLL: u32 = 113;
+pub const LINKTYPE_IPV4: u32 = 228;
+pub const LINKTYPE_IPV6: u32 = 229;
+pub const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+#[derive(thiserror::Error, Debug)]
+pub enum PcapError {
+    #[error("IoError {0:?}")]
+    IoError(#[from] IoError),
+    #[error("FormatUnknown {0:#010x}")]
+    FormatUnknown(u32),
+    #[error("BlockInvalid {0}")]
+    BlockInvalid(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Packet {
+    pub time: NaiveDateTime,
+    pub link_type: u32,
+    pub data: Vec<u8>,
+}
+
+enum Format {
+    Pcap {
+        is_big_endian: bool,
+        is_nanos: bool,
+        link_type: u32,
+    },
+    PcapNg {
+        is_big_endian: bool,
+        /// `(link_type, timestamp units per second)` by interface id.
+        interfaces: Vec<(u32, u64)>,
+    },
+}
+
+//
+/// Reads packets from a pcap or pcapng file, detected from its magic number.
+pub struct PacketReader<R>
+where
+    R: Read,
+{
+    reader: R,
+    format: Format,
+}
+
+impl<R> PacketReader<R>
+where
+    R: Read,
+{
+    pub fn new(mut reader: R) -> Result<Self, PcapError> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+
+        let format = match u32::from_be_bytes(magic) {
+            0xA1B2_C3D4 | 0xA1B2_3C4D | 0xD4C3_B2A1 | 0x4D3C_B2A1 => {
+                let magic_be = u32::from_be_bytes(magic);
+                let is_big_endian = magic_be == 0xA1B2_C3D4 || magic_be == 0xA1B2_3C4D;
+                let is_nanos = magic_be == 0xA1B2_3C4D || magic_be == 0x4D3C_B2A1;
+                let mut header = [0; 20];
+                reader.read_exact(&mut header)?;
+                Format::Pcap {
+                    is_big_endian,
+                    is_nanos,
+                    link_type: u32_at(&header, 16, is_big_endian),
+                }
+            }
+            PCAPNG_SECTION_HEADER => {
+                let mut format = Format::PcapNg {
+                    is_big_endian: false,
+                    interfaces: vec![],
+                };
+                read_section_header(&mut reader, &mut format)?;
+                format
+            }
+            x => return Err(PcapError::FormatUnknown(x)),
+        };
+
+        Ok(Self { reader, format })
+    }
+
+    /// Next packet, `None` at end of input.
+    pub fn next_packet(&mut self) -> Result<Option<Packet>, PcapError> {
+        match &mut self.format {
+            Format::Pcap {
+                is_big_endian,
+                is_nanos,
+                link_type,
+            } => {
+                let mut header = [0; 16];
+                if !read_exact_or_eof(&mut self.reader, &mut header)? {
+                    return Ok(None);
+                }
+                let secs = u32_at(&header, 0, *is_big_endian);
+                let frac = u32_at(&header, 4, *is_big_endian);
+                let len = u32_at(&header, 8, *is_big_endian) as usize;
+                if len > MAX_BLOCK_LEN {
+                    return Err(PcapError::BlockInvalid(format!("packet length {len}")));
+                }
+                let mut data = vec![0; len];
+                self.reader.read_exact(&mut data)?;
+                let nanos = if *is_nanos {
+                    Some(frac)
+                } else {
+                    frac.checked_mul(1000)
+                }
+                .filter(|x| *x < 1_000_000_000)
+                .ok_or_else(|| PcapError::BlockInvalid(format!("packet time fraction {frac}")))?;
+                Ok(Some(Packet {
+                    time: time(secs.into(), nanos),
+                    link_type: *link_type,
+                    data,
+                }))
+            }
+            Format::PcapNg { .. } => self.next_pcapng_packet(),
+        }
+    }
+
+    fn next_pcapng_packet(&mut self) -> Result<Option<Packet>, PcapError> {
+        loop {
+            let mut header = [0; 8];
+            if !read_exact_or_eof(&mut self.reader, &mut header)? {
+                return Ok(None);
+            }
+            if u32::from_be_bytes([header[0], header[1], header[2], header[3]])
+                == PCAPNG_SECTION_HEADER
+            {
+                read_section_header_rest(&mut self.reader, &mut self.format, header)?;
+                continue;
+            }
+
+            let Format::PcapNg {
+                is_big_endian,
+                interfaces,
+            } = &mut self.format
+            else {
+                unreachable!()
+            };
+            let block_type = u32_at(&header, 0, *is_big_endian);
+            let body = read_block_body(&mut self.reader, &header, *is_big_endian)?;
+
+            match block_type {
+                PCAPNG_INTERFACE_DESCRIPTION => {
+                    if body.len() < 8 {
+                        return Err(PcapError::BlockInvalid("interface".to_owned()));
+                    }
+                    let link_type = u16_at(&body, 0, *is_big_endian) as u32;
+                    let mut units = 1_000_000;
+                    for (code, value) in options(&body[8..], *is_big_endian) {
+                        // if_tsresol
+                        if code == 9 && !value.is_empty() {
+                            let v = value[0];
+                            units = if v & 0x80 == 0 {
+                                10_u64.checked_pow(v.into()).unwrap_or(u64::MAX)
+                            } else {
+                                1_u64.checked_shl((v & 0x7f).into()).unwrap_or(u64::MAX)
+                            };
+                        }
+                    }
+                    interfaces.push((link_type, units));
+                }
+                PCAPNG_ENHANCED_PACKET => {
+                    if body.len() < 20 {
+                        return Err(PcapError::BlockInvalid("enhanced packet".to_owned()));
+                    }
+                    let interface = u32_at(&body, 0, *is_big_endian) as usize;
+                    let (link_type, units) = *interfaces
+                        .get(interface)
+                        .ok_or_else(|| PcapError::BlockInvalid(format!("interface {interface}")))?;
+                    let ts = ((u32_at(&body, 4, *is_big_endian) as u64) << 32)
+                        | u32_at(&body, 8, *is_big_endian) as u64;
+                    let len = (u32_at(&body, 12, *is_big_endian) as usize).min(body.len() - 20);
+                    let secs = ts / units;
+                    let nanos = ((ts % units) as u128 * 1_000_000_000 / units as u128) as u32;
+                    return Ok(Some(Packet {
+                        time: time(secs as i64, nanos),
+                        link_type,
+                        data: body[20..20 + len].to_vec(),
+                    }));
+                }
+                PCAPNG_SIMPLE_PACKET => {
+                    if body.len() < 4 {
+                        return Err(PcapError::BlockInvalid("simple packet".to_owned()));
+                    }
+                    let (link_type, _) = *interfaces
+                        .first()
+                        .ok_or_else(|| PcapError::BlockInvalid("interface 0".to_owned()))?;
+                    let len = (u32_at(&body, 0, *is_big_endian) as usize).min(body.len() - 4);
+                    return Ok(Some(Packet {
+                        time: DateTime::UNIX_EPOCH.naive_utc(),
+                        link_type,
+                        data: body[4..4 + len].to_vec(),
+                    }));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<R> Iterator for PacketReader<R>
+where
+    R: Read,
+{
+    type Item = Result<Packet, PcapError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_packet().transpose()
+    }
+}
+
+fn read_section_header<R: Read>(reader: &mut R, format: &mut Format) -> Result<(), PcapError> {
+    let mut header = [0; 8];
+    header[..4].copy_from_slice(&PCAPNG_SECTION_HEADER.to_be_bytes());
+    reader.read_exact(&mut header[4..])?;
+    read_section_header_rest(reader, format, header)
+}
+
+/// A new section resets the byte order and the interfaces.
+fn read_section_header_rest<R: Read>(
+    reader: &mut R,
+    format: &mut Format,
+    header: [u8; 8],
+) -> Result<(), PcapError> {
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    let is_big_endian = match u32::from_be_bytes(magic) {
+        PCAPNG_BYTE_ORDER_MAGIC => true,
+        x if x.swap_bytes() == PCAPNG_BYTE_ORDER_MAGIC => false,
+        x => return Err(PcapError::FormatUnknown(x)),
+    };
+    let total_len = u32_at(&header, 4, is_big_endian) as usize;
+    if !(16..=MAX_BLOCK_LEN).contains(&total_len) {
+        return Err(PcapError::BlockInvalid("section header".to_owned()));
+    }
+    let mut rest = vec![0; total_len - 12];
+    reader.read_exact(&mut rest)?;
+
+    *format = Format::PcapNg {
+        is_big_endian,
+        interfaces: vec![],
+    };
+    Ok(())
+}
+
+fn read_block_body<R: Read>(
+    reader: &mut R,
+    header: &[u8; 8],
+    is_big_endian: bool,
+) -> Result<Vec<u8>, PcapError> {
+    let total_len = u32_at(header, 4, is_big_endian) as usize;
+    if !(12..=MAX_BLOCK_LEN).contains(&total_len) || !total_len.is_multiple_of(4) {
+        return Err(PcapError::BlockInvalid(format!("block length {total_len}")));
+    }
+    let mut body = vec![0; total_len - 8];
+    reader.read_exact(&mut body)?;
+    body.truncate(total_len - 12);
+    Ok(body)
+}
+
+/// `(code, value)` of a pcapng options list.
+fn options(mut bytes: &[u8], is_big_endian: bool) -> Vec<(u16, &[u8])> {
+    let mut options = vec![];
+    while bytes.len() >= 4 {
+        let code = u16_at(bytes, 0, is_big_endian);
+        let len = u16_at(bytes, 2, is_big_endian) as usize;
+        if code == 0 || bytes.len() < 4 + len {
+            break;
+        }
+        options.push((code, &bytes[4..4 + len]));
+        bytes = &bytes[(4 + len).div_ceil(4) * 4..];
+    }
+    options
+}
+
+fn read_exact_or_eof<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<bool, PcapError> {
+    match reader.read_exact(buf) {
+        Ok(()) => Ok(true),
+        Err(err) if err.kind() == IoErrorKind::UnexpectedEof => Ok(false),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn u16_at(bytes: &[u8], offset: usize, is_big_endian: bool) -> u16 {
+    let b = [bytes[offset], bytes[offset + 1]];
+    if is_big_endian {
+        u16::from_be_bytes(b)
+    } else {
+        u16::from_le_bytes(b)
+    }
+}
+
+fn u32_at(bytes: &[u8], offset: usize, is_big_endian: bool) -> u32 {
+    let b = [
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ];
+    if is_big_endian {
+        u32::from_be_bytes(b)
+    } else {
+        u32::from_le_bytes(b)
+    }
+}
+
+fn time(secs: i64, nanos: u32) -> NaiveDateTime {
+    DateTime::from_timestamp(secs, nanos)
+        .unwrap_or_default()
+        .naive_utc()
+}
+
+//
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct Endpoint {
+    ip: IpAddr,
+    port: u16,
+}
+
+struct Segment<'a> {
+    src: Endpoint,
+    dst: Endpoint,
+    seq: u32,
+    is_syn: bool,
+    is_ack: bool,
+    payload: &'a [u8],
+}
+
+/// TCP segment of a link-layer frame, `None` for anything else.
+fn parse_segment(link_type: u32, frame: &[u8]) -> Option<Segment<'_>> {
+    let ip = match link_type {
+        LINKTYPE_ETHERNET => {
+            let mut offset = 12;
+            let mut ether_type = u16::from_be_bytes([*frame.get(offset)?, *frame.get(offset + 1)?]);
+            // 802.1Q and 802.1ad tags.
+            while ether_type == 0x8100 || ether_type == 0x88a8 {
+                offset += 4;
+                ether_type = u16::from_be_bytes([*frame.get(offset)?, *frame.get(offset + 1)?]);
+            }
+            frame.get(offset + 2..)?
+        }
+        LINKTYPE_NULL => frame.get(4..)?,
+        LINKTYPE_LINUX_SLL => frame.get(16..)?,
+        LINKTYPE_LINUX_SLL2 => frame.get(20..)?,
+        LINKTYPE_RAW | LINKTYPE_IPV4 | LINKTYPE_IPV6 => frame,
+        _ => return None,
+    };
+
+    let (src_ip, dst_ip, tcp) = match ip.first()? >> 4 {
+        4 => {
+            let header_len = ((ip[0] & 0x0f) as usize) * 4;
+            let total_len = u16::from_be_bytes([*ip.get(2)?, *ip.get(3)?]) as usize;
+            let flags_fragment = u16::from_be_bytes([*ip.get(6)?, *ip.get(7)?]);
+            // Fragments are not reassembled.
+            if *ip.get(9)? != 6 || flags_fragment & 0x3fff != 0 {
+                return None;
+            }
+            let src: [u8; 4] = ip.get(12..16)?.try_into().ok()?;
+            let dst: [u8; 4] = ip.get(16..20)?.try_into().ok()?;
+            let end = if total_len >= header_len {
+                total_len.min(ip.len())
+            } else {
+                ip.len()
+            };
+            (
+                IpAddr::V4(Ipv4Addr::from(src)),
+                IpAddr::V4(Ipv4Addr::from(dst)),
+                ip.get(header_len..end)?,
+            )
+        }
+        6 => {
+            let payload_len = u16::from_be_bytes([*ip.get(4)?, *ip.get(5)?]) as usize;
+            let src: [u8; 16] = ip.get(8..24)?.try_into().ok()?;
+            let dst: [u8; 16] = ip.get(24..40)?.try_into().ok()?;
+            let end = (40 + payload_len).min(ip.len());
+            let mut next_header = *ip.get(6)?;
+            let mut offset = 40;
+            // Hop-by-hop, routing and destination options.
+            while matches!(next_header, 0 | 43 | 60) {
+                next_header = *ip.get(offset)?;
+                offset += (*ip.get(offset + 1)? as usize + 1) * 8;
+            }
+            if next_header != 6 {
+                return None;
+            }
+            (
+                IpAddr::V6(Ipv6Addr::from(src)),
+                IpAddr::V6(Ipv6Addr::from(dst)),
+                ip.get(offset..end)?,
+            )
+        }
+        _ => return None,
+    };
+
+    let header_len = ((*tcp.get(12)? >> 4) as usize) * 4;
+    let flags = *tcp.get(13)?;
+    Some(Segment {
+        src: Endpoint {
+            ip: src_ip,
+            port: u16::from_be_bytes([tcp[0], tcp[1]]),
+        },
+        dst: Endpoint {
+            ip: dst_ip,
+            port: u16::from_be_bytes([tcp[2], tcp[3]]),
+        },
+        seq: u32::from_be_bytes(tcp.get(4..8)?.try_into().ok()?),
+        is_syn: flags & 0x02 != 0,
+        is_ack: flags & 0x10 != 0,
+        payload: tcp.get(header_len..)?,
+    })
+}
+
+/// One direction of a TCP connection, in sequence order.
+#[derive(Default)]
+struct Stream {
+    base_seq: Option<u32>,
+    data: Vec<u8>,
+    /// `(offset, time)` of each contiguous append.
+    times: Vec<(usize, NaiveDateTime)>,
+    pending: BTreeMap<usize, (Vec<u8>, NaiveDateTime)>,
+}
+
+impl Stream {
+    fn add(&mut self, seq: u32, is_syn: bool, payload: &[u8], time: NaiveDateTime) {
+        let base_seq = *self
+            .base_seq
+            .get_or_insert(if is_syn { seq.wrapping_add(1) } else { seq });
+        let seq = if is_syn { seq.wrapping_add(1) } else { seq };
+        if payload.is_empty() {
+            return;
+        }
+        let offset = seq.wrapping_sub(base_seq) as usize;
+        // Before the start of the stream, as seen from a capture started mid-connection.
+        if offset > self.data.len() + MAX_BLOCK_LEN {
+            return;
+        }
+        // A longer retransmission at the same offset fills more of the stream.
+        match self.pending.entry(offset) {
+            Entry::Vacant(x) => {
+                x.insert((payload.to_vec(), time));
+            }
+            Entry::Occupied(mut x) => {
+                if payload.len() > x.get().0.len() {
+                    x.insert((payload.to_vec(), time));
+                }
+            }
+        }
+
+        while let Some(entry) = self.pending.first_entry() {
+            if *entry.key() > self.data.len() {
+                break;
+            }
+            let (offset, (payload, time)) = entry.remove_entry();
+            let end = offset + payload.len();
+            if end > self.data.len() {
+                self.times.push((self.data.len(), time));
+                let start = self.data.len() - offset;
+                self.data.extend(&payload[start..]);
+            }
+        }
+    }
+
+    fn time_at(&self, offset: usize) -> NaiveDateTime {
+        let i = self.times.partition_point(|(x, _)| *x <= offset);
+        self.times
+            .get(i.saturating_sub(1))
+            .map(|(_, x)| *x)
+            .unwrap_or_default()
+    }
+}
+
+struct Connection {
+    /// Lower endpoint first.
+    endpoints: (Endpoint, Endpoint),
+    client: Option<Endpoint>,
+    streams: HashMap<Endpoint, Stream>,
+}
+
+impl Connection {
+    fn new(endpoints: (Endpoint, Endpoint)) -> Self {
+        Self {
+            endpoints,
+            client: None,
+            streams: HashMap::new(),
+        }
+    }
+}
+
+//
+/// Reassemble the TCP streams of a capture and pair their HTTP/1.x requests and
+/// responses into items, ordered by request time.
+///
+/// Captures are expected to be plaintext, every item has the `http` protocol.
+pub fn items_from_pcap<R>(reader: R) -> Result<Vec<Item>, PcapError>
+where
+    R: Read,
+{
+    let mut connections: HashMap<(Endpoint, Endpoint), Connection> = HashMap::new();
+    let mut finished = vec![];
+
+    for packet in PacketReader::new(reader)? {
+        let packet = packet?;
+        let segment = match parse_segment(packet.link_type, &packet.data) {
+            Some(x) => x,
+            None => continue,
+        };
+        let key = if segment.src < segment.dst {
+            (segment.src, segment.dst)
+        } else {
+            (segment.dst, segment.src)
+        };
+
+        // A new handshake on a known 4-tuple starts a new connection.
+        if segment.is_syn && !segment.is_ack {
+            if let Some(connection) = connections.remove(&key) {
+                finished.push(connection);
+            }
+            connections
+                .entry(key)
+                .or_insert_with(|| Connection::new(key))
+                .client = Some(segment.src);
+        }
+
+        let connection = connections
+            .entry(key)
+            .or_insert_with(|| Connection::new(key));
+        connection.streams.entry(segment.src).or_default().add(
+            segment.seq,
+            segment.is_syn,
+            segment.payload,
+            packet.time,
+        );
+    }
+    finished.extend(connections.into_values());
+
+    let mut items = vec![];
+    for connection in finished.iter() {
+        items.extend(connection_items(connection));
+    }
+    items.sort_by_key(|x| x.time);
+    Ok(items)
+}
+
+fn connection_items(connection: &Connection) -> Vec<Item> {
+    let client = connection.client.or_else(|| {
+        connection
+            .streams
+            .iter()
+            .find(|(_, x)| starts_with_method(&x.data))
+            .map(|(endpoint, _)| *endpoint)
+    });
+    let client = match client {
+        Some(x) => x,
+        None => return vec![],
+    };
+    let server = if client == connection.endpoints.0 {
+        connection.endpoints.1
+    } else {
+        connection.endpoints.0
+    };
+    let empty = Stream::default();
+    let requests = connection.streams.get(&client).unwrap_or(&empty);
+    let responses = connection.streams.get(&server).unwrap_or(&empty);
+
+    let mut items = vec![];
+    let mut request_offset = 0;
+    let mut response_offset = 0;
+    while request_offset < requests.data.len() {
+        let request = &requests.data[request_offset..];
+        let request_len = match message_len(request, None) {
+            Some(x) => x,
+            None => break,
+        };
+        let request = &request[..request_len];
+        let is_head = request.starts_with(b"HEAD ");
+
+        // Interim 1xx responses are not items.
+        let mut response: &[u8] = &[];
+        while response_offset < responses.data.len() {
+            let rest = &responses.data[response_offset..];
+            let len = message_len(rest, Some(is_head)).unwrap_or(rest.len());
+            response_offset += len;
+            let is_interim = Message::parse(&rest[..len])
+                .ok()
+                .and_then(|x| x.status_code())
+                .map(|x| (100..200).contains(&x) && x != 101)
+                == Some(true);
+            if !is_interim {
+                response = &rest[..len];
+                break;
+            }
+        }
+
+        let message = Message::parse(request).ok();
+        let host = message
+            .as_ref()
+            .and_then(|x| x.header_str("host"))
+            .map(|x| strip_port(x).to_owned())
+            .unwrap_or_else(|| server.ip.to_string());
+        if let Ok(mut item) =
+            Item::from_messages(Scheme::HTTP, &host, server.port, request, response)
+        {
+            item.time = requests.time_at(request_offset);
+            item.host.0 = ItemHostAttr {
+                ip: server.ip.to_string().into_bytes(),
+            };
+            items.push(item);
+        }
+
+        request_offset += request_len;
+        // The connection is no longer HTTP after an upgrade.
+        if response.starts_with(b"HTTP/1.1 101") {
+            break;
+        }
+    }
+    items
+}
+
+fn starts_with_method(data: &[u8]) -> bool {
+    let n = data
+        .iter()
+        .take(16)
+        .take_while(|b| b.is_ascii_uppercase())
+        .count();
+    n > 0 && data.get(n) == Some(&b' ')
+}
+
+fn strip_port(host: &str) -> &str {
+    if host.starts_with('[') {
+        return host
+            .split_once(']')
+            .map(|(x, _)| x.trim_start_matches('['))
+            .unwrap_or(host);
+    }
+    host.rsplit_once(':').map(|(x, _)| x).unwrap_or(host)
+}
+
+/// Length of the first message in `bytes`, `None` when its head is incomplete.
+///
+/// `is_head_response` is `Some` for responses, whose body also depends on the request.
+/// A message without a length runs to the end, truncated ones to what was captured.
+fn message_len(bytes: &[u8], is_head_response: Option<bool>) -> Option<usize> {
+    let head_len = bytes
+        .windows(4)
+        .position(|x| x == b"\r\n\r\n")
+        .map(|x| x + 4)
+        .or_else(|| bytes.windows(2).position(|x| x == b"\n\n").map(|x| x + 2))?;
+    let message = Message::parse(&bytes[..head_len]).ok()?;
+    let body = &bytes[head_len..];
+
+    let has_no_body = match is_head_response {
+        Some(is_head) => {
+            let status = message.status_code().unwrap_or_default();
+            is_head || (100..200).contains(&status) || status == 204 || status == 304
+        }
+        None => false,
+    };
+    if has_no_body {
+        return Some(head_len);
+    }
+
+    let is_chunked = message
+        .header_str("transfer-encoding")
+        .map(|x| x.to_ascii_lowercase().contains("chunked"))
+        == Some(true);
+    let body_len = if is_chunked {
+        chunked_len(body).unwrap_or(body.len())
+    } else if let Some(n) = message
+        .header_str("content-length")
+        .and_then(|x| x.trim().parse::<usize>().ok())
+    {
+        n.min(body.len())
+    } else if is_head_response.is_some() {
+        body.len()
+    } else {
+        0
+    };
+    Some(head_len + body_len)
+}
+
+/// Length of a chunked body including the last chunk and trailers.
+fn chunked_len(body: &[u8]) -> Option<usize> {
+    let mut offset = 0;
+    loop {
+        let line_end = offset + body[offset..].windows(2).position(|x| x == b"\r\n")?;
+        let size = str::from_utf8(&body[offset..line_end]).ok()?;
+        let size = usize::from_str_radix(size.split(';').next()?.trim(), 16).ok()?;
+        offset = line_end + 2;
+        if size == 0 {
+            // Trailers up to an empty line.
+            loop {
+                let line_end = offset + body[offset..].windows(2).position(|x| x == b"\r\n")?;
+                let is_empty = line_end == offset;
+                offset = line_end + 2;
+                if is_empty {
+                    return Some(offset);
+                }
+            }
+        }
+        offset = offset.checked_add(size)?.checked_add(2)?;
+        if offset > body.len() {
+            return None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use http::{Method, StatusCode};
+
+    const CLIENT: [u8; 4] = [192, 168, 1, 10];
+    const SERVER: [u8; 4] = [93, 184, 216, 34];
+
+    struct Frame {
+        secs: u32,
+        from_client: bool,
+        seq: u32,
+        flags: u8,
+        payload: &'static [u8],
+    }
+
+    fn ethernet(frame: &Frame) -> Vec<u8> {
+        let (src, dst, sport, dport) = if frame.from_client {
+            (CLIENT, SERVER, 50000_u16, 80_u16)
+        } else {
+            (SERVER, CLIENT, 80, 50000)
+        };
+        let mut tcp = vec![];
+        tcp.extend(sport.to_be_bytes());
+        tcp.extend(dport.to_be_bytes());
+        tcp.extend(frame.seq.to_be_bytes());
+        tcp.extend(0_u32.to_be_bytes());
+        tcp.extend([0x50, frame.flags, 0xff, 0xff, 0, 0, 0, 0]);
+        tcp.extend(frame.payload);
+
+        let mut ip = vec![0x45, 0];
+        ip.extend(((20 + tcp.len()) as u16).to_be_bytes());
+        ip.extend([0, 0, 0x40, 0, 64, 6, 0, 0]);
+        ip.extend(src);
+        ip.extend(dst);
+        ip.extend(tcp);
+
+        let mut eth = vec![0; 12];
+        eth.extend([0x08, 0x00]);
+        eth.extend(ip);
+        eth
+    }
+
+    fn frames() -> Vec<Frame> {
+        const SYN: u8 = 0x02;
+        const ACK: u8 = 0x10;
+        const PSH_ACK: u8 = 0x18;
+        let frame = |secs, from_client, seq, flags, payload| Frame {
+            secs,
+            from_client,
+            seq,
+            flags,
+            payload,
+        };
+        let css: &[u8] =
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/css\r\nContent-Length: 2\r\n\r\n{}";
+        vec![
+            frame(100, true, 1000, SYN, b""),
+            frame(100, false, 5000, SYN | ACK, b""),
+            // Two pipelined requests, the second split in two segments.
+            frame(
+                101,
+                true,
+                1001,
+                PSH_ACK,
+                b"GET /a.css HTTP/1.1\r\nHost: example.com\r\n\r\nPOST /b HTTP/1.1\r\nHost: example.com\r\n",
+            ),
+            frame(102, true, 1001 + 79, PSH_ACK, b"Content-Length: 3\r\n\r\nx=1"),
+            // Responses out of order, with a retransmission.
+            frame(
+                103,
+                false,
+                5001 + 64,
+                PSH_ACK,
+                b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nok\r\n0\r\n\r\n",
+            ),
+            frame(103, false, 5001, PSH_ACK, css),
+            frame(104, false, 5001, PSH_ACK, css),
+        ]
+    }
+
+    fn pcap() -> Vec<u8> {
+        let mut bytes = vec![];
+        bytes.extend(0xA1B2C3D4_u32.to_le_bytes());
+        bytes.extend([2, 0, 4, 0]);
+        bytes.extend([0; 8]);
+        bytes.extend(65535_u32.to_le_bytes());
+        bytes.extend(LINKTYPE_ETHERNET.to_le_bytes());
+        for frame in frames() {
+            let data = ethernet(&frame);
+            bytes.extend(frame.secs.to_le_bytes());
+            bytes.extend(500_000_u32.to_le_bytes());
+            bytes.extend((data.len() as u32).to_le_bytes());
+            bytes.extend((data.len() as u32).to_le_bytes());
+            bytes.extend(data);
+        }
+        bytes
+    }
+
+    fn pcapng() -> Vec<u8> {
+        fn block(block_type: u32, body: &[u8]) -> Vec<u8> {
+            let padded = body.len().div_ceil(4) * 4;
+            let total = (12 + padded) as u32;
+            let mut bytes = vec![];
+            bytes.extend(block_type.to_be_bytes());
+            bytes.extend(total.to_be_bytes());
+            bytes.extend(body);
+            bytes.resize(8 + padded, 0);
+            bytes.extend(total.to_be_bytes());
+            bytes
+        }
+
+        let mut shb = vec![];
+        shb.extend(PCAPNG_BYTE_ORDER_MAGIC.to_be_bytes());
+        shb.extend([0, 1, 0, 0]);
+        shb.extend(u64::MAX.to_be_bytes());
+        let mut bytes = block(PCAPNG_SECTION_HEADER, &shb);
+
+        let mut idb = vec![];
+        idb.extend((LINKTYPE_ETHERNET as u16).to_be_bytes());
+        idb.extend([0, 0]);
+        idb.extend(65535_u32.to_be_bytes());
+        // if_tsresol of milliseconds.
+        idb.extend([0, 9, 0, 1, 3, 0, 0, 0]);
+        idb.extend([0, 0, 0, 0]);
+        bytes.extend(block(PCAPNG_INTERFACE_DESCRIPTION, &idb));
+
+        for frame in frames() {
+            let data = ethernet(&frame);
+            let ts = frame.secs as u64 * 1000 + 500;
+            let mut epb = vec![];
+            epb.extend(0_u32.to_be_bytes());
+            epb.extend(((ts >> 32) as u32).to_be_bytes());
+            epb.extend((ts as u32).to_be_bytes());
+            epb.extend((data.len() as u32).to_be_bytes());
+            epb.extend((data.len() as u32).to_be_bytes());
+            epb.extend(data);
+            bytes.extend(block(PCAPNG_ENHANCED_PACKET, &epb));
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_items() -> Result<(), Box<dyn std::error::Error>> {
+        for capture in [pcap(), pcapng()] {
+            let items = items_from_pcap(&capture[..])?;
+            assert_eq!(items.len(), 2);
+
+            let item = &items[0];
+            assert_eq!(item.url, "http://example.com/a.css");
+            assert_eq!(item.host.0.ip, b"93.184.216.34");
+            assert_eq!(item.port, 80);
+            assert_eq!(item.time.to_string(), "1970-01-01 00:01:41.500");
+            assert_eq!(item.extension.as_deref(), Some("css"));
+            assert_eq!(item.mimetype, "CSS");
+            assert!(item.response_bytes()?.ends_with(b"\r\n\r\n{}"));
+
+            let item = &items[1];
+            assert_eq!(item.method, Method::POST);
+            assert_eq!(item.status, StatusCode::OK);
+            assert!(item
+                .request_bytes()?
+                .ends_with(b"Content-Length: 3\r\n\r\nx=1"));
+            assert!(item.response_bytes()?.ends_with(b"2\r\nok\r\n0\r\n\r\n"));
+            assert_eq!(item.response_length, 59);
+        }
+
+        assert!(matches!(
+            items_from_pcap(&b"\0\0\0\0"[..]),
+            Err(PcapError::FormatUnknown(0))
+        ));
+
+        let mut capture = pcap();
+        capture[28..32].copy_from_slice(&1_000_000_u32.to_le_bytes());
+        assert!(matches!(
+            items_from_pcap(&capture[..]),
+            Err(PcapError::BlockInvalid(_))
+        ));
+
+        let time = NaiveDateTime::default();
+        let mut stream = Stream::default();
+        stream.add(100, true, b"", time);
+        stream.add(106, false, b"f", time);
+        stream.add(106, false, b"fgh", time);
+        stream.add(106, false, b"f", time);
+        stream.add(101, false, b"abcde", time);
+        assert_eq!(stream.data, b"abcdefgh");
+
+        assert_eq!(chunked_len(b"2\r\nok\r\n0\r\n\r\n"), Some(12));
+        assert_eq!(chunked_len(b"ffffffffffffffff\r\nok\r\n"), None);
+
+        Ok(())
+    }
+}
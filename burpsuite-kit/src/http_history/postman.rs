@@ -0,0 +1,337 @@
+use std::collections::BTreeMap;
+
+use http::uri::Scheme;
+use serde_json::{json, Value};
+
+use super::{
+    item::Item,
+    message::{query_pairs, Message},
+};
+
+pub const SCHEMA_V2_1: &str =
+    "https://schema.getpostman.com/json/collection/v2.1.0/collection.json";
+
+/// Request headers Postman computes itself.
+const SKIPPED_HEADERS: &[&str] = &["content-length"];
+
+/// Postman Collection v2.1 built from items, one folder per host and path prefix.
+#[derive(Debug)]
+pub struct PostmanCollection {
+    pub name: String,
+    folders: BTreeMap<String, BTreeMap<String, Vec<Value>>>,
+}
+
+impl PostmanCollection {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            folders: BTreeMap::new(),
+        }
+    }
+
+    pub fn add_item(&mut self, item: &Item) {
+        let path = item.path.split('?').next().unwrap_or_default();
+        let prefix = match path.trim_start_matches('/').split('/').next() {
+            Some(x) if !x.is_empty() && path.trim_start_matches('/').contains('/') => {
+                format!("/{x}")
+            }
+            _ => "/".to_owned(),
+        };
+
+        let request = request_to_json(item);
+        let mut value = json!({
+            "name": format!("{} {}", item.method, item.path),
+            "request": request,
+        });
+        if let Some(response) = response_to_json(item, &request) {
+            value["response"] = json!([response]);
+        }
+
+        self.folders
+            .entry(item.host.1.to_owned())
+            .or_default()
+            .entry(prefix)
+            .or_default()
+            .push(value);
+    }
+
+    pub fn to_json(&self) -> Value {
+        let folders: Vec<Value> = self
+            .folders
+            .iter()
+            .map(|(host, prefixes)| {
+                json!({
+                    "name": host,
+                    "item": prefixes
+                        .iter()
+                        .map(|(prefix, items)| json!({ "name": prefix, "item": items }))
+                        .collect::<Vec<_>>(),
+                })
+            })
+            .collect();
+
+        json!({
+            "info": { "name": self.name, "schema": SCHEMA_V2_1 },
+            "item": folders,
+        })
+    }
+
+    pub fn to_json_string(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(&self.to_json())
+    }
+}
+
+impl Extend<Item> for PostmanCollection {
+    fn extend<T: IntoIterator<Item = Item>>(&mut self, iter: T) {
+        for item in iter {
+            self.add_item(&item)
+        }
+    }
+}
+
+fn url_to_json(item: &Item) -> Value {
+    let (path, query) = item.path.split_once('?').unwrap_or((&item.path, ""));
+    let mut url = json!({
+        "raw": item.url,
+        "protocol": item.protocol.as_str(),
+        "host": item.host.1.split('.').collect::<Vec<_>>(),
+        "path": path.trim_start_matches('/').split('/').collect::<Vec<_>>(),
+    });
+    let default_port = (item.protocol == Scheme::HTTP && item.port == 80)
+        || (item.protocol == Scheme::HTTPS && item.port == 443);
+    if !default_port {
+        url["port"] = item.port.to_string().into();
+    }
+    if !query.is_empty() {
+        url["query"] = query_pairs(query)
+            .map(|(k, v)| json!({ "key": k, "value": v }))
+            .collect();
+    }
+    url
+}
+
+fn headers_to_json(message: &Message<'_>, skipped: &[&str]) -> Value {
+    message
+        .headers
+        .iter()
+        .filter(|x| !skipped.iter().any(|y| x.name.eq_ignore_ascii_case(y)))
+        .map(|x| json!({ "key": x.name, "value": String::from_utf8_lossy(x.value) }))
+        .collect()
+}
+
+fn request_to_json(item: &Item) -> Value {
+    let mut request = json!({
+        "method": item.method.as_str(),
+        "header": [],
+        "url": url_to_json(item),
+    });
+
+    let bytes = match item.request_bytes() {
+        Ok(x) => x,
+        Err(_) => return request,
+    };
+    let message = match Message::parse(&bytes) {
+        Ok(x) => x,
+        Err(_) => return request,
+    };
+    request["header"] = headers_to_json(&message, SKIPPED_HEADERS);
+    if let Some(body) = body_to_json(&message) {
+        request["body"] = body;
+    }
+    request
+}
+
+/// Body in the Postman mode matching the request `Content-Type`.
+pub fn body_to_json(message: &Message<'_>) -> Option<Value> {
+    if message.body.is_empty() {
+        return None;
+    }
+    let content_type = message.header_str("content-type").unwrap_or_default();
+    let mime_type = message.mime_type().unwrap_or_default();
+    let text = String::from_utf8_lossy(message.body);
+
+    let body = match mime_type.as_str() {
+        "application/x-www-form-urlencoded" => json!({
+            "mode": "urlencoded",
+            "urlencoded": query_pairs(&text)
+                .map(|(k, v)| json!({ "key": k, "value": v }))
+                .collect::<Vec<_>>(),
+        }),
+        "multipart/form-data" => match boundary(content_type) {
+            Some(boundary) => json!({
+                "mode": "formdata",
+                "formdata": multipart_parts(message.body, boundary)
+                    .into_iter()
+                    .map(|part| match part.filename {
+                        Some(filename) => json!({ "key": part.name, "type": "file", "src": filename }),
+                        None => json!({
+                            "key": part.name,
+                            "type": "text",
+                            "value": String::from_utf8_lossy(part.data),
+                        }),
+                    })
+                    .collect::<Vec<_>>(),
+            }),
+            None => json!({ "mode": "raw", "raw": text }),
+        },
+        x if x == "application/json" || x.ends_with("+json") => json!({
+            "mode": "raw",
+            "raw": text,
+            "options": { "raw": { "language": "json" } },
+        }),
+        x if x.ends_with("/xml") || x.ends_with("+xml") => json!({
+            "mode": "raw",
+            "raw": text,
+            "options": { "raw": { "language": "xml" } },
+        }),
+        _ => json!({ "mode": "raw", "raw": text }),
+    };
+    Some(body)
+}
+
+fn response_to_json(item: &Item, original_request: &Value) -> Option<Value> {
+    let bytes = item.response_bytes().ok()?;
+    let message = Message::parse(&bytes).ok()?;
+    let mime_type = message.mime_type().unwrap_or_default();
+
+    let language = if mime_type.contains("json") {
+        "json"
+    } else if mime_type.contains("html") {
+        "html"
+    } else if mime_type.contains("xml") {
+        "xml"
+    } else {
+        "text"
+    };
+
+    Some(json!({
+        "name": format!("{} {}", item.status.as_u16(), item.path),
+        "originalRequest": original_request,
+        "status": item.status.canonical_reason().unwrap_or_default(),
+        "code": item.status.as_u16(),
+        "_postman_previewlanguage": language,
+        "header": headers_to_json(&message, &[]),
+        "body": String::from_utf8_lossy(message.body),
+    }))
+}
+
+fn boundary(content_type: &str) -> Option<&str> {
+    content_type
+        .split(';')
+        .filter_map(|x| x.trim().split_once('='))
+        .find(|(k, _)| k.eq_ignore_ascii_case("boundary"))
+        .map(|(_, v)| v.trim_matches('"'))
+}
+
+struct Part<'a> {
+    name: String,
+    filename: Option<String>,
+    data: &'a [u8],
+}
+
+fn multipart_parts<'a>(body: &'a [u8], boundary: &str) -> Vec<Part<'a>> {
+    let delimiter = format!("--{boundary}");
+    let delimiter = delimiter.as_bytes();
+
+    let mut starts = vec![];
+    let mut i = 0;
+    while i + delimiter.len() <= body.len() {
+        if &body[i..i + delimiter.len()] == delimiter {
+            starts.push(i);
+            i += delimiter.len();
+        } else {
+            i += 1;
+        }
+    }
+
+    starts
+        .windows(2)
+        .filter_map(|x| {
+            let part = &body[x[0] + delimiter.len()..x[1]];
+            let part = part.strip_prefix(b"\r\n").unwrap_or(part);
+            let part = part.strip_suffix(b"\r\n").unwrap_or(part);
+            let message = Message::parse_headers(part).ok()?;
+            let disposition = message.header_str("content-disposition")?;
+            let param = |name: &str| {
+                disposition
+                    .split(';')
+                    .filter_map(|x| x.trim().split_once('='))
+                    .find(|(k, _)| k.eq_ignore_ascii_case(name))
+                    .map(|(_, v)| v.trim_matches('"').to_owned())
+            };
+            Some(Part {
+                name: param("name")?,
+                filename: param("filename"),
+                data: message.body,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{fs::File, io::BufReader};
+
+    use http::Method;
+
+    use crate::http_history::items::Items;
+
+    #[test]
+    fn test_collection() -> Result<(), Box<dyn std::error::Error>> {
+        let mut collection = PostmanCollection::new("engagement");
+
+        let file = File::open("tests/http_history_files/burpsuite_community_v2021.3.2.xml")?;
+        collection.extend(Items::from_reader(BufReader::new(file))?.filter_map(|x| x.ok()));
+
+        let mut item = Item {
+            url: "http://example.com:8080/api/upload".to_owned(),
+            host: (Default::default(), "example.com".to_owned()),
+            port: 8080,
+            method: Method::POST,
+            path: "/api/upload".to_owned(),
+            ..Default::default()
+        };
+        item.set_request_bytes("POST /api/upload HTTP/1.1\r\nHost: example.com:8080\r\nContent-Type: multipart/form-data; boundary=XyZ\r\nContent-Length: 1\r\n\r\n--XyZ\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\n1\r\n--XyZ\r\nContent-Disposition: form-data; name=\"f\"; filename=\"x.bin\"\r\nContent-Type: application/octet-stream\r\n\r\n\x00\x01\r\n--XyZ--\r\n");
+        collection.add_item(&item);
+
+        let value = collection.to_json();
+        assert_eq!(value["info"]["schema"], SCHEMA_V2_1);
+        assert_eq!(value["item"][0]["name"], "example.com");
+        assert_eq!(value["item"][1]["name"], "httpbin.org");
+
+        let upload = &value["item"][0]["item"][0];
+        assert_eq!(upload["name"], "/api");
+        let request = &upload["item"][0]["request"];
+        assert_eq!(request["url"]["port"], "8080");
+        assert_eq!(request["url"]["path"], json!(["api", "upload"]));
+        assert_eq!(request["header"].as_array().map(|x| x.len()), Some(2));
+        assert_eq!(
+            request["body"],
+            json!({
+                "mode": "formdata",
+                "formdata": [
+                    { "key": "a", "type": "text", "value": "1" },
+                    { "key": "f", "type": "file", "src": "x.bin" },
+                ],
+            })
+        );
+        assert!(upload["item"][0].get("response").is_none());
+
+        let httpbin = &value["item"][1]["item"][0]["item"];
+        assert_eq!(httpbin[0]["request"]["url"]["query"][0]["key"], "foo");
+        assert_eq!(httpbin[0]["response"][0]["code"], 200);
+        assert_eq!(
+            httpbin[0]["response"][0]["_postman_previewlanguage"],
+            "json"
+        );
+        assert_eq!(httpbin[1]["request"]["body"]["raw"], "{}");
+        assert_eq!(
+            httpbin[1]["request"]["body"]["options"]["raw"]["language"],
+            "json"
+        );
+
+        Ok(())
+    }
+}
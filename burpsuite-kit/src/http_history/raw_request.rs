@@ -0,0 +1,284 @@
+use core::ops::Range;
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Error as IoError, Write as _},
+    path::{Path, PathBuf},
+};
+
+use base64::DecodeError;
+use regex::bytes::Regex;
+
+use super::{
+    item::Item,
+    message::{fix_content_length, Message},
+};
+
+pub const MANIFEST_FILE_NAME: &str = "manifest.tsv";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Marker {
+    /// sqlmap custom injection mark, `*` appended to the value.
+    Sqlmap,
+    /// ffuf keyword, `FUZZ` replacing the value.
+    Ffuf,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum RawRequestExportError {
+    #[error("IoError {0:?}")]
+    IoError(#[from] IoError),
+    #[error("RequestDecodeFailed {0}")]
+    RequestDecodeFailed(#[from] DecodeError),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    pub item_index: usize,
+    pub file_name: String,
+    pub protocol: String,
+    pub method: String,
+    pub url: String,
+    /// Whether the marker parameter was found and marked.
+    pub is_marked: bool,
+}
+
+/// `00012_POST_example.com_api_login.txt` style name for the request file of an item.
+pub fn file_name(item_index: usize, item: &Item) -> String {
+    let path = item.path.split('?').next().unwrap_or_default();
+    let slug: String = format!("{}_{}", item.host.1, path.trim_matches('/'))
+        .chars()
+        .map(|x| {
+            if x.is_ascii_alphanumeric() || x == '.' || x == '-' {
+                x
+            } else {
+                '_'
+            }
+        })
+        .take(80)
+        .collect();
+    format!(
+        "{item_index:05}_{}_{}.txt",
+        item.method,
+        slug.trim_end_matches('_')
+    )
+}
+
+/// Insert `marker` at the value of `param`, searched in the request line query,
+/// urlencoded body, JSON body and cookies in that order.
+///
+/// Returns `None` when the parameter is not present.
+pub fn insert_marker(request: &[u8], param: &str, marker: Marker) -> Option<Vec<u8>> {
+    let message = Message::parse(request).ok()?;
+
+    let range = query_value_range(message.start_line, param)
+        .or_else(|| {
+            let mime_type = message.mime_type().unwrap_or_default();
+            if mime_type == "application/x-www-form-urlencoded" {
+                let body = std::str::from_utf8(message.body).ok()?;
+                pair_value_range(body, message.body_offset, param, '&')
+            } else if mime_type == "application/json" || mime_type.ends_with("+json") {
+                json_value_range(message.body, message.body_offset, param)
+            } else {
+                None
+            }
+        })
+        .or_else(|| {
+            message
+                .headers
+                .iter()
+                .filter(|x| x.name.eq_ignore_ascii_case("cookie"))
+                .find_map(|x| {
+                    let value = std::str::from_utf8(x.value).ok()?;
+                    pair_value_range(value, x.value_offset, param, ';')
+                })
+        })?;
+
+    let mut marked = request[..range.start].to_vec();
+    match marker {
+        Marker::Sqlmap => {
+            marked.extend(&request[range.to_owned()]);
+            marked.push(b'*');
+        }
+        Marker::Ffuf => marked.extend(b"FUZZ"),
+    }
+    marked.extend(&request[range.end..]);
+    Some(fix_content_length(&marked).into_owned())
+}
+
+fn query_value_range(start_line: &str, param: &str) -> Option<Range<usize>> {
+    let query_start = start_line.find('?')? + 1;
+    let query_end = start_line[query_start..]
+        .find(' ')
+        .map(|x| query_start + x)
+        .unwrap_or(start_line.len());
+    pair_value_range(&start_line[query_start..query_end], query_start, param, '&')
+}
+
+fn pair_value_range(
+    pairs: &str,
+    offset: usize,
+    param: &str,
+    separator: char,
+) -> Option<Range<usize>> {
+    let mut pos = 0;
+    for pair in pairs.split(separator) {
+        let leading = pair.len() - pair.trim_start().len();
+        if let Some((name, value)) = pair.trim_start().split_once('=') {
+            if name == param {
+                let start = offset + pos + leading + name.len() + 1;
+                return Some(start..start + value.trim_end().len());
+            }
+        }
+        pos += pair.len() + separator.len_utf8();
+    }
+    None
+}
+
+fn json_value_range(body: &[u8], offset: usize, param: &str) -> Option<Range<usize>> {
+    let regex = Regex::new(&format!(
+        r#""{}"\s*:\s*(?:"((?:[^"\\]|\\.)*)"|(-?[0-9][0-9.eE+-]*|true|false|null))"#,
+        regex::escape(param)
+    ))
+    .ok()?;
+    let captures = regex.captures(body)?;
+    let m = captures.get(1).or_else(|| captures.get(2))?;
+    Some(offset + m.start()..offset + m.end())
+}
+
+/// Writes the decoded request of each exported item to its own file in `dir`.
+pub struct RawRequestExporter {
+    dir: PathBuf,
+    marker: Option<(String, Marker)>,
+    entries: Vec<ManifestEntry>,
+}
+
+impl RawRequestExporter {
+    pub fn new(dir: impl AsRef<Path>) -> Result<Self, RawRequestExportError> {
+        fs::create_dir_all(dir.as_ref())?;
+        Ok(Self {
+            dir: dir.as_ref().to_owned(),
+            marker: None,
+            entries: vec![],
+        })
+    }
+
+    pub fn marker(mut self, param: impl Into<String>, marker: Marker) -> Self {
+        self.marker = Some((param.into(), marker));
+        self
+    }
+
+    pub fn export(
+        &mut self,
+        item_index: usize,
+        item: &Item,
+    ) -> Result<PathBuf, RawRequestExportError> {
+        let request = item.request_bytes()?;
+        let marked = self
+            .marker
+            .as_ref()
+            .and_then(|(param, marker)| insert_marker(&request, param, *marker));
+
+        let file_name = file_name(item_index, item);
+        let path = self.dir.join(&file_name);
+        fs::write(&path, marked.as_deref().unwrap_or(&request))?;
+
+        self.entries.push(ManifestEntry {
+            item_index,
+            file_name,
+            protocol: item.protocol.to_string(),
+            method: item.method.to_string(),
+            url: item.url.to_owned(),
+            is_marked: marked.is_some(),
+        });
+        Ok(path)
+    }
+
+    pub fn entries(&self) -> &[ManifestEntry] {
+        &self.entries
+    }
+
+    /// Write `manifest.tsv` mapping the files back to items.
+    pub fn write_manifest(&self) -> Result<PathBuf, RawRequestExportError> {
+        let path = self.dir.join(MANIFEST_FILE_NAME);
+        let mut w = BufWriter::new(File::create(&path)?);
+        writeln!(w, "item_index\tfile_name\tprotocol\tmethod\turl\tis_marked")?;
+        for entry in self.entries.iter() {
+            writeln!(
+                w,
+                "{}\t{}\t{}\t{}\t{}\t{}",
+                entry.item_index,
+                entry.file_name,
+                entry.protocol,
+                entry.method,
+                entry.url.replace(['\t', '\n'], " "),
+                entry.is_marked
+            )?;
+        }
+        w.flush()?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{env, fs::File, io::BufReader};
+
+    use crate::http_history::items::Items;
+
+    #[test]
+    fn test_insert_marker() {
+        let request = b"POST /search?q=abc&page=2 HTTP/1.1\r\nHost: example.com\r\nCookie: sid=1; lang=en\r\nContent-Type: application/json\r\nContent-Length: 17\r\n\r\n{\"id\": 5, \"n\":\"x\"}";
+
+        assert_eq!(
+            insert_marker(request, "q", Marker::Sqlmap).unwrap()[..35],
+            b"POST /search?q=abc*&page=2 HTTP/1.1"[..]
+        );
+        assert!(insert_marker(request, "page", Marker::Ffuf)
+            .unwrap()
+            .starts_with(b"POST /search?q=abc&page=FUZZ HTTP/1.1"));
+        assert!(
+            String::from_utf8(insert_marker(request, "lang", Marker::Ffuf).unwrap())
+                .unwrap()
+                .contains("Cookie: sid=1; lang=FUZZ\r\n")
+        );
+
+        let marked = insert_marker(request, "n", Marker::Ffuf).unwrap();
+        let message = Message::parse(&marked).unwrap();
+        assert_eq!(message.body, br#"{"id": 5, "n":"FUZZ"}"#);
+        assert_eq!(message.header_str("content-length"), Some("21"));
+        assert!(insert_marker(request, "id", Marker::Sqlmap)
+            .unwrap()
+            .ends_with(br#"{"id": 5*, "n":"x"}"#));
+
+        assert!(insert_marker(request, "missing", Marker::Ffuf).is_none());
+    }
+
+    #[test]
+    fn test_export() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join(format!("burpsuite_kit_raw_request_{}", std::process::id()));
+        let mut exporter = RawRequestExporter::new(&dir)?.marker("foo", Marker::Sqlmap);
+
+        let file = File::open("tests/http_history_files/burpsuite_community_v2021.3.2.xml")?;
+        for (i, item) in Items::from_reader(BufReader::new(file))?.enumerate() {
+            exporter.export(i, &item?)?;
+        }
+        let manifest = fs::read_to_string(exporter.write_manifest()?)?;
+
+        let entries = exporter.entries();
+        assert_eq!(entries[0].file_name, "00000_GET_httpbin.org_get.txt");
+        assert!(entries[0].is_marked);
+        assert_eq!(entries[1].file_name, "00001_POST_httpbin.org_post.txt");
+        assert!(!entries[1].is_marked);
+        assert!(fs::read(dir.join(&entries[0].file_name))?
+            .starts_with(b"GET /get?foo=bar* HTTP/1.1\r\n"));
+        assert_eq!(manifest.lines().count(), 3);
+        assert!(manifest.contains(
+            "0\t00000_GET_httpbin.org_get.txt\thttp\tGET\thttp://httpbin.org/get?foo=bar\ttrue\n"
+        ));
+
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,408 @@
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    io::{BufRead, Write},
+};
+
+use regex::bytes::{Captures, NoExpand, Regex};
+
+use super::{
+    item::Item,
+    items::{ItemParseError, Items},
+    message::Message,
+    writer::{ItemsWriteError, ItemsWriter},
+};
+
+//
+#[derive(Clone, Debug)]
+pub struct RedactConfig {
+    pub mask: String,
+    /// Header names (case-insensitive) whose values are masked.
+    /// `Cookie` and `Set-Cookie` keep cookie names and attributes, auth headers keep the scheme.
+    pub headers: Vec<String>,
+    /// Parameter names masked in URL queries, urlencoded bodies and JSON bodies.
+    pub params: Vec<String>,
+    pub emails: bool,
+    /// Regexes whose matches are masked anywhere in the URL, start line, header values and bodies.
+    pub patterns: Vec<String>,
+    /// Replace `host.0.ip` with a stable `10.x.y.z` per distinct address.
+    pub pseudonymise_ip: bool,
+}
+
+impl Default for RedactConfig {
+    fn default() -> Self {
+        Self {
+            mask: "REDACTED".to_owned(),
+            headers: [
+                "authorization",
+                "proxy-authorization",
+                "cookie",
+                "set-cookie",
+                "x-api-key",
+                "x-auth-token",
+            ]
+            .iter()
+            .map(|x| x.to_string())
+            .collect(),
+            params: vec![],
+            emails: true,
+            patterns: vec![],
+            pseudonymise_ip: false,
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum RedactError {
+    #[error("ItemParseError {0}")]
+    ItemParseError(#[from] ItemParseError),
+    #[error("ItemsWriteError {0}")]
+    ItemsWriteError(#[from] ItemsWriteError),
+}
+
+pub struct Redactor {
+    mask: String,
+    headers: HashSet<String>,
+    params: HashSet<String>,
+    json_params: Vec<Regex>,
+    email: Option<Regex>,
+    patterns: Vec<Regex>,
+    pseudonymise_ip: bool,
+    ips: HashMap<Vec<u8>, Vec<u8>>,
+}
+
+impl Redactor {
+    pub fn new(config: RedactConfig) -> Result<Self, regex::Error> {
+        let json_params = config
+            .params
+            .iter()
+            .map(|x| {
+                Regex::new(&format!(
+                    r#"("{}"\s*:\s*)("(?:[^"\\]|\\.)*"|-?[0-9][0-9.eE+-]*|true|false|null)"#,
+                    regex::escape(x)
+                ))
+            })
+            .collect::<Result<_, _>>()?;
+        let email = if config.emails {
+            Some(Regex::new(
+                r"(?i)[a-z0-9._%+-]+@[a-z0-9-]+(?:\.[a-z0-9-]+)*\.[a-z]{2,}",
+            )?)
+        } else {
+            None
+        };
+        let patterns = config
+            .patterns
+            .iter()
+            .map(|x| Regex::new(x))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            mask: config.mask,
+            headers: config
+                .headers
+                .iter()
+                .map(|x| x.to_ascii_lowercase())
+                .collect(),
+            params: config.params.into_iter().collect(),
+            json_params,
+            email,
+            patterns,
+            pseudonymise_ip: config.pseudonymise_ip,
+            ips: HashMap::new(),
+        })
+    }
+
+    pub fn redact_item(&mut self, item: &mut Item) {
+        item.url = self.redact_url(&item.url);
+        item.path = self.redact_url(&item.path);
+
+        // Payloads that are not valid base64 are redacted as stored, like the
+        // secrets scanner reads them, rather than written out untouched.
+        let bytes = match item.request_bytes() {
+            Ok(x) => self.redact_message(&x, true),
+            Err(_) => self.redact_message(&item.request.1, true),
+        };
+        item.set_request_bytes(bytes);
+        let bytes = match item.response_bytes() {
+            Ok(x) => self.redact_message(&x, false),
+            Err(_) => self.redact_message(&item.response.1, false),
+        };
+        item.response_length = bytes.len() as u32;
+        item.set_response_bytes(bytes);
+
+        if let Some(comment) = item.comment.as_mut() {
+            *comment = String::from_utf8_lossy(&self.redact_text(comment.as_bytes())).into_owned();
+        }
+
+        if self.pseudonymise_ip && !item.host.0.ip.is_empty() {
+            let n = self.ips.len() + 1;
+            item.host.0.ip = self
+                .ips
+                .entry(item.host.0.ip.to_owned())
+                .or_insert_with(|| {
+                    format!("10.{}.{}.{}", (n >> 16) & 0xff, (n >> 8) & 0xff, n & 0xff).into_bytes()
+                })
+                .to_owned();
+        }
+    }
+
+    fn redact_url(&self, url: &str) -> String {
+        let bytes = match url.split_once('?') {
+            Some((path, query)) => {
+                let mut bytes = path.as_bytes().to_vec();
+                bytes.push(b'?');
+                bytes.extend(self.redact_query(query.as_bytes()));
+                bytes
+            }
+            None => url.as_bytes().to_vec(),
+        };
+        String::from_utf8_lossy(&self.redact_text(&bytes)).into_owned()
+    }
+
+    fn redact_message(&self, bytes: &[u8], is_request: bool) -> Vec<u8> {
+        let message = match Message::parse(bytes) {
+            Ok(x) => x,
+            Err(_) => return self.redact_text(bytes).into_owned(),
+        };
+
+        let start_line = if is_request {
+            self.redact_url(message.start_line)
+        } else {
+            String::from_utf8_lossy(&self.redact_text(message.start_line.as_bytes())).into_owned()
+        };
+
+        let mut body = message.body.to_vec();
+        if message.mime_type().as_deref() == Some("application/x-www-form-urlencoded") {
+            body = self.redact_query(&body);
+        }
+        for regex in self.json_params.iter() {
+            body = regex
+                .replace_all(&body, |caps: &Captures<'_>| {
+                    let mut replaced = caps[1].to_vec();
+                    replaced.extend(format!(r#""{}""#, self.mask).into_bytes());
+                    replaced
+                })
+                .into_owned();
+        }
+        let body = self.redact_text(&body);
+
+        let mut redacted = start_line.into_bytes();
+        redacted.extend(b"\r\n");
+        for header in message.headers.iter() {
+            let name = header.name.to_ascii_lowercase();
+            let value = if name == "content-length" {
+                Cow::Owned(body.len().to_string().into_bytes())
+            } else if self.headers.contains(&name) {
+                Cow::Owned(self.mask_header_value(&name, header.value))
+            } else {
+                self.redact_text(header.value)
+            };
+            redacted.extend(header.name.as_bytes());
+            redacted.extend(b": ");
+            redacted.extend(value.as_ref());
+            redacted.extend(b"\r\n");
+        }
+        redacted.extend(b"\r\n");
+        redacted.extend(body.as_ref());
+        redacted
+    }
+
+    fn mask_header_value(&self, name: &str, value: &[u8]) -> Vec<u8> {
+        let value = String::from_utf8_lossy(value);
+        let masked = match name {
+            "cookie" => value
+                .split(';')
+                .map(|x| match x.trim().split_once('=') {
+                    Some((k, _)) => format!("{k}={}", self.mask),
+                    None => x.trim().to_owned(),
+                })
+                .collect::<Vec<_>>()
+                .join("; "),
+            "set-cookie" => {
+                let (pair, attrs) = value.split_once(';').unwrap_or((&value, ""));
+                let pair = match pair.split_once('=') {
+                    Some((k, _)) => format!("{}={}", k.trim(), self.mask),
+                    None => self.mask.to_owned(),
+                };
+                if attrs.is_empty() {
+                    pair
+                } else {
+                    format!("{pair};{attrs}")
+                }
+            }
+            _ => match value.split_once(' ') {
+                Some((scheme, _)) if scheme.chars().all(|x| x.is_ascii_alphabetic()) => {
+                    format!("{scheme} {}", self.mask)
+                }
+                _ => self.mask.to_owned(),
+            },
+        };
+        masked.into_bytes()
+    }
+
+    fn redact_query(&self, query: &[u8]) -> Vec<u8> {
+        if self.params.is_empty() {
+            return query.to_vec();
+        }
+        query
+            .split(|b| *b == b'&')
+            .map(|pair| {
+                let name = pair.split(|b| *b == b'=').next().unwrap_or_default();
+                if pair.contains(&b'=')
+                    && self.params.contains(String::from_utf8_lossy(name).as_ref())
+                {
+                    [name, b"=", self.mask.as_bytes()].concat()
+                } else {
+                    pair.to_vec()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(&b'&')
+    }
+
+    fn redact_text<'a>(&self, bytes: &'a [u8]) -> Cow<'a, [u8]> {
+        let mut bytes = Cow::Borrowed(bytes);
+        for regex in self.email.iter().chain(self.patterns.iter()) {
+            if regex.is_match(&bytes) {
+                bytes = Cow::Owned(
+                    regex
+                        .replace_all(&bytes, NoExpand(self.mask.as_bytes()))
+                        .into_owned(),
+                );
+            }
+        }
+        bytes
+    }
+}
+
+/// Redact every item and write them out as a Burp XML file that Burp can import.
+pub fn redact_items<R, W>(
+    items: Items<R>,
+    redactor: &mut Redactor,
+    writer: W,
+) -> Result<usize, RedactError>
+where
+    R: BufRead,
+    W: Write,
+{
+    let mut writer = ItemsWriter::new(writer, &items.attr.to_owned())?;
+    let mut n = 0;
+    for item in items {
+        let mut item = item?;
+        redactor.redact_item(&mut item);
+        writer.write_item(&item)?;
+        n += 1;
+    }
+    writer.finish()?;
+    Ok(n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::http_history::item::{ItemRequestAttr, ItemResponseAttr};
+
+    #[test]
+    fn test_redact_item() -> Result<(), Box<dyn std::error::Error>> {
+        let mut redactor = Redactor::new(RedactConfig {
+            params: vec!["token".to_owned(), "password".to_owned()],
+            patterns: vec![r"\b4[0-9]{15}\b".to_owned()],
+            pseudonymise_ip: true,
+            ..Default::default()
+        })?;
+
+        let mut item = Item {
+            url: "https://example.com/login?token=abc&x=1".to_owned(),
+            path: "/login?token=abc&x=1".to_owned(),
+            ..Default::default()
+        };
+        item.host.0.ip = b"1.2.3.4".to_vec();
+        item.set_request_bytes("POST /login?token=abc&x=1 HTTP/1.1\r\nAuthorization: Bearer abc.def\r\nCookie: a=1; b=2\r\nContent-Type: application/json\r\nContent-Length: 56\r\n\r\n{\"password\": \"hunter2\", \"mail\": \"bob@example.com\"}");
+        item.set_response_bytes(
+            "HTTP/1.1 200 OK\r\nSet-Cookie: sid=xyz; Path=/; HttpOnly\r\n\r\ncard 4111111111111111",
+        );
+
+        redactor.redact_item(&mut item);
+
+        assert_eq!(item.url, "https://example.com/login?token=REDACTED&x=1");
+        assert_eq!(item.path, "/login?token=REDACTED&x=1");
+        assert_eq!(item.host.0.ip, b"10.0.0.1");
+
+        let request = item.request_bytes()?;
+        let request = Message::parse(&request)?;
+        assert_eq!(
+            request.start_line,
+            "POST /login?token=REDACTED&x=1 HTTP/1.1"
+        );
+        assert_eq!(request.header_str("authorization"), Some("Bearer REDACTED"));
+        assert_eq!(request.header_str("cookie"), Some("a=REDACTED; b=REDACTED"));
+        assert_eq!(
+            request.body,
+            br#"{"password": "REDACTED", "mail": "REDACTED"}"#
+        );
+        assert_eq!(
+            request.header_str("content-length"),
+            Some(request.body.len().to_string().as_str())
+        );
+
+        let response = item.response_bytes()?;
+        assert_eq!(item.response_length as usize, response.len());
+        let response = Message::parse(&response)?;
+        assert_eq!(
+            response.header_str("set-cookie"),
+            Some("sid=REDACTED; Path=/; HttpOnly")
+        );
+        assert_eq!(response.body, b"card REDACTED");
+
+        let mut other = Item::default();
+        other.host.0.ip = b"1.2.3.4".to_vec();
+        redactor.redact_item(&mut other);
+        assert_eq!(other.host.0.ip, b"10.0.0.1");
+
+        // Flagged base64 but stored as text.
+        let mut malformed = Item {
+            request: (
+                ItemRequestAttr { base64: true },
+                b"GET / HTTP/1.1\r\nAuthorization: Bearer abc.def\r\n\r\n".to_vec(),
+            ),
+            response: (
+                ItemResponseAttr { base64: true },
+                b"HTTP/1.1 200 OK\r\n\r\nbob@example.com".to_vec(),
+            ),
+            ..Default::default()
+        };
+        redactor.redact_item(&mut malformed);
+        let request = malformed.request_bytes()?;
+        assert_eq!(
+            Message::parse(&request)?.header_str("authorization"),
+            Some("Bearer REDACTED")
+        );
+        assert_eq!(
+            Message::parse(&malformed.response_bytes()?)?.body,
+            b"REDACTED"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_redact_items() -> Result<(), Box<dyn std::error::Error>> {
+        let file =
+            std::fs::File::open("tests/http_history_files/burpsuite_community_v2021.3.2.xml")?;
+        let items = Items::from_reader(std::io::BufReader::new(file))?;
+        let mut redactor = Redactor::new(RedactConfig {
+            pseudonymise_ip: true,
+            ..Default::default()
+        })?;
+
+        let mut bytes = vec![];
+        assert_eq!(redact_items(items, &mut redactor, &mut bytes)?, 2);
+
+        let items = Items::from_reader(&bytes[..])?.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(items.len(), 2);
+        assert!(items.iter().all(|x| x.host.0.ip == b"10.0.0.1"));
+
+        Ok(())
+    }
+}
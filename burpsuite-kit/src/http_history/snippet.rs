@@ -0,0 +1,359 @@
+use core::{fmt::Write as _, str};
+
+use base64::DecodeError;
+use strum::{Display, EnumIter, EnumString};
+
+use super::{
+    item::Item,
+    message::{Message, MessageParseError},
+};
+
+/// Request headers left to the HTTP client, which derives them from the URL and body.
+const SKIPPED_HEADERS: &[&str] = &["host", "content-length"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString, EnumIter)]
+#[strum(serialize_all = "snake_case")]
+pub enum Language {
+    /// Python `requests`, laid out like the "Copy as Python-Requests" extension.
+    Python,
+    /// JavaScript `fetch`, as run by Node.js 18+ or Deno.
+    #[strum(to_string = "javascript", serialize = "js")]
+    JavaScript,
+    /// Go `net/http`.
+    Go,
+    /// Rust `reqwest` with the `blocking` feature.
+    Rust,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SnippetError {
+    #[error("RequestDecodeFailed {0}")]
+    RequestDecodeFailed(#[from] DecodeError),
+    #[error("RequestParseFailed {0}")]
+    RequestParseFailed(#[from] MessageParseError),
+}
+
+struct Request<'a> {
+    method: &'a str,
+    url: &'a str,
+    headers: Vec<(&'a str, String)>,
+    cookies: Vec<(String, String)>,
+    body: &'a [u8],
+}
+
+/// Reproduce the request of `item` as a runnable snippet.
+pub fn generate(item: &Item, language: Language) -> Result<String, SnippetError> {
+    let bytes = item.request_bytes()?;
+    let message = Message::parse(&bytes)?;
+
+    let mut request = Request {
+        method: item.method.as_str(),
+        url: &item.url,
+        headers: vec![],
+        cookies: vec![],
+        body: message.body,
+    };
+    for header in message.headers.iter() {
+        if SKIPPED_HEADERS
+            .iter()
+            .any(|x| header.name.eq_ignore_ascii_case(x))
+        {
+            continue;
+        }
+        let value = String::from_utf8_lossy(header.value).into_owned();
+        if language == Language::Python && header.name.eq_ignore_ascii_case("cookie") {
+            request.cookies.extend(value.split(';').filter_map(|x| {
+                x.trim()
+                    .split_once('=')
+                    .map(|(k, v)| (k.to_owned(), v.to_owned()))
+            }));
+            continue;
+        }
+        request.headers.push((header.name, value));
+    }
+
+    Ok(match language {
+        Language::Python => python(&request),
+        Language::JavaScript => javascript(&request),
+        Language::Go => go(&request),
+        Language::Rust => rust(&request),
+    })
+}
+
+/// Double-quoted string literal, valid in Python, JavaScript, Go and Rust.
+fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 || c as u32 == 0x7f => {
+                let _ = write!(quoted, "\\x{:02x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Byte string contents with every non-printable byte as `\xNN`.
+fn escape_bytes(bytes: &[u8]) -> String {
+    let mut escaped = String::with_capacity(bytes.len());
+    for b in bytes {
+        match b {
+            b'"' => escaped.push_str("\\\""),
+            b'\\' => escaped.push_str("\\\\"),
+            0x20..=0x7e => escaped.push(*b as char),
+            _ => {
+                let _ = write!(escaped, "\\x{b:02x}");
+            }
+        }
+    }
+    escaped
+}
+
+fn python(request: &Request<'_>) -> String {
+    let mut s = String::from("import requests\n\n");
+    let _ = writeln!(s, "burp0_url = {}", quote(request.url));
+
+    let mut args = vec!["burp0_url".to_owned()];
+    if !request.cookies.is_empty() {
+        let cookies: Vec<String> = request
+            .cookies
+            .iter()
+            .map(|(k, v)| format!("{}: {}", quote(k), quote(v)))
+            .collect();
+        let _ = writeln!(s, "burp0_cookies = {{{}}}", cookies.join(", "));
+    }
+
+    // A dict keeps insertion order, repeated headers are folded into one.
+    let mut headers: Vec<(String, String)> = vec![];
+    for (name, value) in request.headers.iter() {
+        match headers
+            .iter_mut()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+        {
+            Some((_, v)) => {
+                v.push_str(", ");
+                v.push_str(value);
+            }
+            None => headers.push((name.to_string(), value.to_owned())),
+        }
+    }
+    let headers: Vec<String> = headers
+        .iter()
+        .map(|(k, v)| format!("{}: {}", quote(k), quote(v)))
+        .collect();
+    let _ = writeln!(s, "burp0_headers = {{{}}}", headers.join(", "));
+    args.push("headers=burp0_headers".to_owned());
+    if !request.cookies.is_empty() {
+        args.push("cookies=burp0_cookies".to_owned());
+    }
+
+    if !request.body.is_empty() {
+        // requests sends a `str` body as Latin-1, so only ASCII text stays a `str`.
+        let data = match str::from_utf8(request.body) {
+            Ok(x) if x.is_ascii() => quote(x),
+            _ => format!("b\"{}\"", escape_bytes(request.body)),
+        };
+        let _ = writeln!(s, "burp0_data = {data}");
+        args.push("data=burp0_data".to_owned());
+    }
+
+    let method = request.method.to_ascii_lowercase();
+    if ["get", "post", "put", "patch", "delete", "head", "options"].contains(&method.as_str()) {
+        let _ = writeln!(s, "requests.{method}({})", args.join(", "));
+    } else {
+        let _ = writeln!(
+            s,
+            "requests.request({}, {})",
+            quote(request.method),
+            args.join(", ")
+        );
+    }
+    s
+}
+
+fn javascript(request: &Request<'_>) -> String {
+    let mut s = String::new();
+    let _ = writeln!(s, "const response = await fetch({}, {{", quote(request.url));
+    let _ = writeln!(s, "  method: {},", quote(request.method));
+    let _ = writeln!(s, "  headers: [");
+    for (name, value) in request.headers.iter() {
+        let _ = writeln!(s, "    [{}, {}],", quote(name), quote(value));
+    }
+    let _ = writeln!(s, "  ],");
+    if !request.body.is_empty() {
+        match str::from_utf8(request.body) {
+            Ok(x) => {
+                let _ = writeln!(s, "  body: {},", quote(x));
+            }
+            Err(_) => {
+                let bytes: Vec<String> =
+                    request.body.iter().map(|b| format!("0x{b:02x}")).collect();
+                let _ = writeln!(s, "  body: new Uint8Array([{}]),", bytes.join(", "));
+            }
+        }
+    }
+    let _ = writeln!(s, "}});");
+    let _ = writeln!(s, "console.log(response.status, await response.text());");
+    s
+}
+
+fn go(request: &Request<'_>) -> String {
+    let body = if request.body.is_empty() {
+        None
+    } else {
+        Some(match str::from_utf8(request.body) {
+            Ok(x) => format!("strings.NewReader({})", quote(x)),
+            Err(_) => format!(
+                "bytes.NewReader([]byte(\"{}\"))",
+                escape_bytes(request.body)
+            ),
+        })
+    };
+
+    let mut imports = vec![];
+    if let Some(body) = body.as_ref() {
+        imports.push(if body.starts_with("bytes") {
+            "bytes"
+        } else {
+            "strings"
+        });
+    }
+    imports.extend(["fmt", "io", "net/http"]);
+    imports.sort_unstable();
+
+    let mut s = String::from("package main\n\nimport (\n");
+    for import in imports {
+        let _ = writeln!(s, "\t{}", quote(import));
+    }
+    let _ = writeln!(s, ")\n\nfunc main() {{");
+    let _ = writeln!(
+        s,
+        "\treq, err := http.NewRequest({}, {}, {})",
+        quote(request.method),
+        quote(request.url),
+        body.as_deref().unwrap_or("nil")
+    );
+    let _ = writeln!(s, "\tif err != nil {{\n\t\tpanic(err)\n\t}}");
+    for (name, value) in request.headers.iter() {
+        let _ = writeln!(s, "\treq.Header.Add({}, {})", quote(name), quote(value));
+    }
+    let _ = writeln!(s, "\tresp, err := http.DefaultClient.Do(req)");
+    let _ = writeln!(s, "\tif err != nil {{\n\t\tpanic(err)\n\t}}");
+    let _ = writeln!(s, "\tdefer resp.Body.Close()");
+    let _ = writeln!(s, "\trespBody, _ := io.ReadAll(resp.Body)");
+    let _ = writeln!(s, "\tfmt.Println(resp.Status, string(respBody))");
+    let _ = writeln!(s, "}}");
+    s
+}
+
+fn rust(request: &Request<'_>) -> String {
+    let mut s = String::from("fn main() -> Result<(), Box<dyn std::error::Error>> {\n");
+    let _ = writeln!(s, "    let client = reqwest::blocking::Client::new();");
+    let _ = writeln!(s, "    let response = client");
+    let _ = writeln!(
+        s,
+        "        .request(reqwest::Method::from_bytes(b{})?, {})",
+        quote(request.method),
+        quote(request.url)
+    );
+    for (name, value) in request.headers.iter() {
+        let _ = writeln!(s, "        .header({}, {})", quote(name), quote(value));
+    }
+    if !request.body.is_empty() {
+        match str::from_utf8(request.body) {
+            Ok(x) => {
+                let _ = writeln!(s, "        .body({})", quote(x));
+            }
+            Err(_) => {
+                let _ = writeln!(
+                    s,
+                    "        .body(b\"{}\".to_vec())",
+                    escape_bytes(request.body)
+                );
+            }
+        }
+    }
+    let _ = writeln!(s, "        .send()?;");
+    let _ = writeln!(
+        s,
+        "    println!(\"{{}} {{}}\", response.status(), response.text()?);"
+    );
+    let _ = writeln!(s, "    Ok(())\n}}");
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use http::Method;
+
+    fn item(body: &[u8]) -> Item {
+        let mut item = Item {
+            url: "https://example.com/api?q=1".to_owned(),
+            method: Method::POST,
+            ..Default::default()
+        };
+        let mut request = b"POST /api?q=1 HTTP/1.1\r\nHost: example.com\r\nX-B: 2\r\nX-A: \"1\"\r\nX-B: 3\r\nCookie: sid=abc; theme=dark\r\nContent-Length: 3\r\n\r\n".to_vec();
+        request.extend(body);
+        item.set_request_bytes(request);
+        item
+    }
+
+    #[test]
+    fn test_python() {
+        assert_eq!(
+            generate(&item(b"a\nb"), Language::Python).unwrap(),
+            r#"import requests
+
+burp0_url = "https://example.com/api?q=1"
+burp0_cookies = {"sid": "abc", "theme": "dark"}
+burp0_headers = {"X-B": "2, 3", "X-A": "\"1\""}
+burp0_data = "a\nb"
+requests.post(burp0_url, headers=burp0_headers, cookies=burp0_cookies, data=burp0_data)
+"#
+        );
+        assert!(generate(&item(b"\x00\xff"), Language::Python)
+            .unwrap()
+            .contains(r#"burp0_data = b"\x00\xff""#));
+        assert!(generate(&item("é".as_bytes()), Language::Python)
+            .unwrap()
+            .contains(r#"burp0_data = b"\xc3\xa9""#));
+    }
+
+    #[test]
+    fn test_other_languages() {
+        let js = generate(&item(b"\x00\xff"), Language::JavaScript).unwrap();
+        assert!(js.contains(
+            r#"    ["X-B", "2"],
+    ["X-A", "\"1\""],
+    ["X-B", "3"],
+    ["Cookie", "sid=abc; theme=dark"],"#
+        ));
+        assert!(js.contains("body: new Uint8Array([0x00, 0xff]),"));
+
+        let go = generate(&item(b"{}"), Language::Go).unwrap();
+        assert!(go.contains("\t\"strings\"\n"));
+        assert!(go.contains(
+            r#"req, err := http.NewRequest("POST", "https://example.com/api?q=1", strings.NewReader("{}"))"#
+        ));
+        assert!(go.contains(r#"req.Header.Add("Cookie", "sid=abc; theme=dark")"#));
+
+        let rust = generate(&item(b"\x00\xff"), Language::Rust).unwrap();
+        assert!(rust.contains(
+            r#".request(reqwest::Method::from_bytes(b"POST")?, "https://example.com/api?q=1")"#
+        ));
+        assert!(rust.contains(r#".body(b"\x00\xff".to_vec())"#));
+        assert!(!rust.contains("Content-Length"));
+
+        assert_eq!("js".parse::<Language>().unwrap(), Language::JavaScript);
+    }
+}
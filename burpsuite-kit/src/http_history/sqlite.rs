@@ -0,0 +1,448 @@
+use core::str::FromStr as _;
+use std::{io::BufRead, path::Path};
+
+use chrono::NaiveDateTime;
+use http::{uri::Scheme, Method, StatusCode};
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension as _, Row, ToSql};
+use sha2::{Digest as _, Sha256};
+
+use super::{
+    item::{Item, ItemHostAttr, ItemRequestAttr, ItemResponseAttr},
+    items::{ItemParseError, Items, ItemsAttr},
+    message::{query_pairs, Message},
+};
+
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS exports (
+    id INTEGER PRIMARY KEY,
+    burp_version TEXT NOT NULL,
+    export_time TEXT NOT NULL,
+    source TEXT NOT NULL,
+    UNIQUE (burp_version, export_time)
+);
+CREATE TABLE IF NOT EXISTS items (
+    id INTEGER PRIMARY KEY,
+    export_id INTEGER NOT NULL REFERENCES exports (id),
+    fingerprint TEXT NOT NULL UNIQUE,
+    time TEXT NOT NULL,
+    url TEXT NOT NULL,
+    host TEXT NOT NULL,
+    ip TEXT NOT NULL,
+    port INTEGER NOT NULL,
+    protocol TEXT NOT NULL,
+    method TEXT NOT NULL,
+    path TEXT NOT NULL,
+    extension TEXT,
+    request_base64 INTEGER NOT NULL,
+    request BLOB NOT NULL,
+    status INTEGER NOT NULL,
+    response_length INTEGER NOT NULL,
+    mimetype TEXT NOT NULL,
+    response_base64 INTEGER NOT NULL,
+    response BLOB NOT NULL,
+    comment TEXT
+);
+CREATE INDEX IF NOT EXISTS items_host ON items (host);
+CREATE INDEX IF NOT EXISTS items_status ON items (status);
+CREATE TABLE IF NOT EXISTS headers (
+    item_id INTEGER NOT NULL REFERENCES items (id),
+    direction TEXT NOT NULL,
+    name TEXT NOT NULL,
+    value TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS headers_name_value ON headers (name, value);
+CREATE TABLE IF NOT EXISTS parameters (
+    item_id INTEGER NOT NULL REFERENCES items (id),
+    location TEXT NOT NULL,
+    name TEXT NOT NULL,
+    value TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS parameters_name ON parameters (name);
+CREATE VIRTUAL TABLE IF NOT EXISTS bodies USING fts5 (request_body, response_body);
+"#;
+
+const ITEM_COLUMNS: &str = "items.time, items.url, items.host, items.ip, items.port, items.protocol, items.method, items.path, items.extension, items.request_base64, items.request, items.status, items.response_length, items.mimetype, items.response_base64, items.response, items.comment";
+
+/// Stored as text sortable by SQLite date functions.
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+#[derive(thiserror::Error, Debug)]
+pub enum StoreError {
+    #[error("SqliteError {0}")]
+    SqliteError(#[from] rusqlite::Error),
+    #[error("ItemParseError {0}")]
+    ItemParseError(#[from] ItemParseError),
+    #[error("ColumnInvalid {0} {1}")]
+    ColumnInvalid(&'static str, String),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    pub inserted: usize,
+    /// Items already in the store, from a re-import or an overlapping export.
+    pub skipped: usize,
+}
+
+/// SQLite database of items from any number of exports.
+pub struct Store {
+    conn: Connection,
+}
+
+impl Store {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StoreError> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self, StoreError> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self, StoreError> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    /// Import every item of an export, `source` is recorded as where it came from.
+    pub fn import<R>(&mut self, items: Items<R>, source: &str) -> Result<ImportReport, StoreError>
+    where
+        R: BufRead,
+    {
+        let tx = self.conn.transaction()?;
+        let export_id = export_id(&tx, &items.attr, source)?;
+
+        let mut report = ImportReport::default();
+        for item in items {
+            if insert_item(&tx, export_id, &item?)? {
+                report.inserted += 1;
+            } else {
+                report.skipped += 1;
+            }
+        }
+        tx.commit()?;
+        Ok(report)
+    }
+
+    pub fn count(&self) -> Result<usize, StoreError> {
+        Ok(self
+            .conn
+            .query_row("SELECT COUNT(*) FROM items", [], |row| row.get(0))?)
+    }
+
+    pub fn items_by_host(&self, host: &str) -> Result<Vec<Item>, StoreError> {
+        self.query_items("WHERE items.host = ?1", &[&host])
+    }
+
+    pub fn items_by_status(&self, status: StatusCode) -> Result<Vec<Item>, StoreError> {
+        self.query_items("WHERE items.status = ?1", &[&status.as_u16()])
+    }
+
+    /// Match the path without query against a SQLite `GLOB` pattern, such as `/api/*`.
+    pub fn items_by_path_glob(&self, pattern: &str) -> Result<Vec<Item>, StoreError> {
+        self.query_items(
+            "WHERE (CASE WHEN instr(items.path, '?') > 0 THEN substr(items.path, 1, instr(items.path, '?') - 1) ELSE items.path END) GLOB ?1",
+            &[&pattern],
+        )
+    }
+
+    /// Items with a request or response header of the given case-insensitive name and exact value.
+    pub fn items_by_header(&self, name: &str, value: &str) -> Result<Vec<Item>, StoreError> {
+        self.query_items(
+            "WHERE items.id IN (SELECT item_id FROM headers WHERE name = ?1 AND value = ?2)",
+            &[&name.to_ascii_lowercase(), &value],
+        )
+    }
+
+    /// Items with a URL, body or cookie parameter of the given name.
+    pub fn items_by_parameter(&self, name: &str) -> Result<Vec<Item>, StoreError> {
+        self.query_items(
+            "WHERE items.id IN (SELECT item_id FROM parameters WHERE name = ?1)",
+            &[&name],
+        )
+    }
+
+    /// Full-text search over decoded bodies, `query` is an FTS5 query such as `"api key"`.
+    pub fn search(&self, query: &str) -> Result<Vec<Item>, StoreError> {
+        self.query_items(
+            "WHERE items.id IN (SELECT rowid FROM bodies WHERE bodies MATCH ?1)",
+            &[&query],
+        )
+    }
+
+    fn query_items(&self, filter: &str, params: &[&dyn ToSql]) -> Result<Vec<Item>, StoreError> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {ITEM_COLUMNS} FROM items {filter} ORDER BY items.id"
+        ))?;
+        let mut rows = stmt.query(params_from_iter(params.iter()))?;
+        let mut items = vec![];
+        while let Some(row) = rows.next()? {
+            items.push(row_to_item(row)?);
+        }
+        Ok(items)
+    }
+}
+
+fn export_id(conn: &Connection, attr: &ItemsAttr, source: &str) -> Result<i64, StoreError> {
+    let export_time = attr.export_time.format(TIME_FORMAT).to_string();
+    conn.execute(
+        "INSERT OR IGNORE INTO exports (burp_version, export_time, source) VALUES (?1, ?2, ?3)",
+        params![attr.burp_version, export_time, source],
+    )?;
+    Ok(conn.query_row(
+        "SELECT id FROM exports WHERE burp_version = ?1 AND export_time = ?2",
+        params![attr.burp_version, export_time],
+        |row| row.get(0),
+    )?)
+}
+
+/// Identity of an item across exports, so re-imports are skipped.
+fn fingerprint(item: &Item) -> String {
+    let mut hasher = Sha256::new();
+    for part in [
+        item.time.format(TIME_FORMAT).to_string().as_bytes(),
+        item.url.as_bytes(),
+        &item.request.1,
+        &item.response.1,
+    ] {
+        hasher.update((part.len() as u64).to_be_bytes());
+        hasher.update(part);
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+fn insert_item(conn: &Connection, export_id: i64, item: &Item) -> Result<bool, StoreError> {
+    let fingerprint = fingerprint(item);
+    let exists = conn
+        .query_row(
+            "SELECT 1 FROM items WHERE fingerprint = ?1",
+            [&fingerprint],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_some();
+    if exists {
+        return Ok(false);
+    }
+
+    // Undecodable payloads are stored as is, without headers, parameters or bodies.
+    let request = item.request_bytes().ok();
+    let response = item.response_bytes().ok();
+    let request = request.as_deref().and_then(|x| Message::parse(x).ok());
+    let response = response.as_deref().and_then(|x| Message::parse(x).ok());
+
+    conn.execute(
+        "INSERT INTO items (export_id, fingerprint, time, url, host, ip, port, protocol, method, path, extension, request_base64, request, status, response_length, mimetype, response_base64, response, comment) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)",
+        params![
+            export_id,
+            fingerprint,
+            item.time.format(TIME_FORMAT).to_string(),
+            item.url,
+            item.host.1,
+            String::from_utf8_lossy(&item.host.0.ip),
+            item.port,
+            item.protocol.as_str(),
+            item.method.as_str(),
+            item.path,
+            item.extension,
+            item.request.0.base64,
+            item.request.1,
+            item.status.as_u16(),
+            item.response_length,
+            item.mimetype,
+            item.response.0.base64,
+            item.response.1,
+            item.comment,
+        ],
+    )?;
+    let item_id = conn.last_insert_rowid();
+
+    let mut insert_header = conn.prepare_cached(
+        "INSERT INTO headers (item_id, direction, name, value) VALUES (?1, ?2, ?3, ?4)",
+    )?;
+    for (direction, message) in [("request", &request), ("response", &response)] {
+        for header in message.iter().flat_map(|x| x.headers.iter()) {
+            insert_header.execute(params![
+                item_id,
+                direction,
+                header.name.to_ascii_lowercase(),
+                String::from_utf8_lossy(header.value),
+            ])?;
+        }
+    }
+
+    let mut insert_parameter = conn.prepare_cached(
+        "INSERT INTO parameters (item_id, location, name, value) VALUES (?1, ?2, ?3, ?4)",
+    )?;
+    if let Some((_, query)) = item.path.split_once('?') {
+        for (name, value) in query_pairs(query) {
+            insert_parameter.execute(params![item_id, "url", name, value])?;
+        }
+    }
+    if let Some(request) = request.as_ref() {
+        if request.mime_type().as_deref() == Some("application/x-www-form-urlencoded") {
+            let body = String::from_utf8_lossy(request.body);
+            for (name, value) in query_pairs(&body) {
+                insert_parameter.execute(params![item_id, "body", name, value])?;
+            }
+        }
+        for cookie in request.headers_all("cookie") {
+            let cookie = String::from_utf8_lossy(cookie);
+            for (name, value) in cookie.split(';').filter_map(|x| x.trim().split_once('=')) {
+                insert_parameter.execute(params![item_id, "cookie", name, value])?;
+            }
+        }
+    }
+
+    conn.execute(
+        "INSERT INTO bodies (rowid, request_body, response_body) VALUES (?1, ?2, ?3)",
+        params![
+            item_id,
+            request
+                .as_ref()
+                .map(|x| String::from_utf8_lossy(x.body))
+                .unwrap_or_default(),
+            response
+                .as_ref()
+                .map(|x| String::from_utf8_lossy(x.body))
+                .unwrap_or_default(),
+        ],
+    )?;
+
+    Ok(true)
+}
+
+fn row_to_item(row: &Row<'_>) -> Result<Item, StoreError> {
+    let invalid = |column, err: &dyn ToString| StoreError::ColumnInvalid(column, err.to_string());
+
+    let time: String = row.get(0)?;
+    let protocol: String = row.get(5)?;
+    let method: String = row.get(6)?;
+    let status: u16 = row.get(11)?;
+    let ip: String = row.get(3)?;
+    Ok(Item {
+        time: NaiveDateTime::parse_from_str(&time, TIME_FORMAT)
+            .map_err(|err| invalid("time", &err))?,
+        url: row.get(1)?,
+        host: (
+            ItemHostAttr {
+                ip: ip.into_bytes(),
+            },
+            row.get(2)?,
+        ),
+        port: row.get(4)?,
+        protocol: Scheme::from_str(&protocol).map_err(|err| invalid("protocol", &err))?,
+        method: Method::from_str(&method).map_err(|err| invalid("method", &err))?,
+        path: row.get(7)?,
+        extension: row.get(8)?,
+        request: (
+            ItemRequestAttr {
+                base64: row.get(9)?,
+            },
+            row.get(10)?,
+        ),
+        status: StatusCode::from_u16(status).map_err(|err| invalid("status", &err))?,
+        response_length: row.get(12)?,
+        mimetype: row.get(13)?,
+        response: (
+            ItemResponseAttr {
+                base64: row.get(14)?,
+            },
+            row.get(15)?,
+        ),
+        comment: row.get(16)?,
+        extra: Default::default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{fs::File, io::BufReader};
+
+    use crate::http_history::writer::ItemsWriter;
+
+    fn import(store: &mut Store, name: &str) -> Result<ImportReport, Box<dyn std::error::Error>> {
+        let path = format!("tests/http_history_files/{name}");
+        let items = Items::from_reader(BufReader::new(File::open(&path)?))?;
+        Ok(store.import(items, &path)?)
+    }
+
+    #[test]
+    fn test_store() -> Result<(), Box<dyn std::error::Error>> {
+        let mut store = Store::open_in_memory()?;
+
+        assert_eq!(
+            import(&mut store, "burpsuite_community_v2021.3.2.xml")?,
+            ImportReport {
+                inserted: 2,
+                skipped: 0
+            }
+        );
+        assert_eq!(
+            import(&mut store, "burpsuite_community_v2021.3.2.xml")?,
+            ImportReport {
+                inserted: 0,
+                skipped: 2
+            }
+        );
+        import(&mut store, "burpsuite_community_v2020.12.1.xml")?;
+        assert_eq!(store.count()?, 4);
+
+        assert_eq!(store.items_by_host("httpbin.org")?.len(), 4);
+        assert_eq!(store.items_by_status(StatusCode::OK)?.len(), 4);
+
+        let items = store.items_by_path_glob("/g*")?;
+        assert_eq!(items.len(), 2);
+        assert!(items.iter().all(|x| x.method == Method::GET));
+
+        let items = store.items_by_header("SERVER", "gunicorn/19.9.0")?;
+        assert_eq!(items.len(), 4);
+        assert_eq!(store.items_by_parameter("foo")?.len(), 2);
+
+        let items = store.search("\"foo\"")?;
+        assert!(!items.is_empty());
+        assert!(items.iter().all(|x| x.url.contains("foo=bar")));
+
+        let file = File::open("tests/http_history_files/burpsuite_community_v2021.3.2.xml")?;
+        let original = Items::from_reader(BufReader::new(file))?.next().unwrap()?;
+        let stored = &store.items_by_path_glob("/get")?[0];
+        assert_eq!(stored.request.1, original.request.1);
+        assert_eq!(stored.response.1, original.response.1);
+        assert_eq!(stored.time, original.time);
+        assert_eq!(stored.host.0.ip, original.host.0.ip);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_store_invalid_payload() -> Result<(), Box<dyn std::error::Error>> {
+        let file = File::open("tests/http_history_files/burpsuite_community_v2021.3.2.xml")?;
+        let items = Items::from_reader(BufReader::new(file))?;
+        let attr = items.attr.to_owned();
+        let mut items = items.collect::<Result<Vec<_>, _>>()?;
+        items[0].request.1 = b"not base64".to_vec();
+
+        let mut writer = ItemsWriter::new(vec![], &attr)?;
+        for item in items.iter() {
+            writer.write_item(item)?;
+        }
+        writer.finish()?;
+        let bytes = writer.into_inner();
+
+        let mut store = Store::open_in_memory()?;
+        let report = store.import(Items::from_reader(&bytes[..])?, "memory")?;
+        assert_eq!(report.inserted, 2);
+        let stored = &store.items_by_path_glob("/get*")?[0];
+        assert_eq!(stored.request.1, b"not base64");
+
+        Ok(())
+    }
+}
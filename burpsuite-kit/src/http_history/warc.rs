@@ -0,0 +1,521 @@
+use std::{
+    collections::VecDeque,
+    io::{BufRead, Error as IoError, Read as _, Write},
+};
+
+use base64::DecodeError;
+use chrono::{DateTime, NaiveDateTime, SecondsFormat};
+use sha1::{Digest as _, Sha1};
+
+use super::{
+    item::{self, Item, ItemHostAttr},
+    message::{Message, MessageParseError},
+};
+
+pub const WARC_VERSION: &str = "WARC/1.1";
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+#[derive(thiserror::Error, Debug)]
+pub enum WarcError {
+    #[error("IoError {0:?}")]
+    IoError(#[from] IoError),
+    #[error("PayloadDecodeFailed {0}")]
+    PayloadDecodeFailed(#[from] DecodeError),
+    #[error("MessageParseFailed {0}")]
+    MessageParseFailed(#[from] MessageParseError),
+    #[error("RecordInvalid {0}")]
+    RecordInvalid(String),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct WarcRecord {
+    /// Named fields in file order, `WARC-Type` first when written by `WarcWriter`.
+    pub headers: Vec<(String, String)>,
+    pub block: Vec<u8>,
+}
+
+impl WarcRecord {
+    /// First field named `name`, case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(x, _)| x.eq_ignore_ascii_case(name))
+            .map(|(_, x)| x.as_str())
+    }
+
+    pub fn record_type(&self) -> Option<&str> {
+        self.header("WARC-Type")
+    }
+
+    pub fn record_id(&self) -> Option<&str> {
+        self.header("WARC-Record-ID")
+    }
+
+    /// All `WARC-Concurrent-To` fields, the field may repeat.
+    pub fn concurrent_to(&self) -> impl Iterator<Item = &str> {
+        self.headers
+            .iter()
+            .filter(|(x, _)| x.eq_ignore_ascii_case("WARC-Concurrent-To"))
+            .map(|(_, x)| x.as_str())
+    }
+
+    fn is_linked_to(&self, other: &WarcRecord) -> bool {
+        let linked = |a: &WarcRecord, b: &WarcRecord| {
+            a.record_id()
+                .map(|id| b.concurrent_to().any(|x| x == id))
+                .unwrap_or_default()
+        };
+        linked(self, other) || linked(other, self)
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), IoError> {
+        writer.write_all(WARC_VERSION.as_bytes())?;
+        writer.write_all(b"\r\n")?;
+        for (name, value) in self.headers.iter() {
+            write!(writer, "{name}: {value}\r\n")?;
+        }
+        write!(writer, "Content-Length: {}\r\n\r\n", self.block.len())?;
+        writer.write_all(&self.block)?;
+        writer.write_all(b"\r\n\r\n")
+    }
+}
+
+/// `sha1:` digest in base32, as written by most WARC tools.
+pub fn sha1_digest(bytes: &[u8]) -> String {
+    format!("sha1:{}", base32(&Sha1::digest(bytes)))
+}
+
+fn base32(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity((bytes.len() * 8).div_ceil(5));
+    let mut buffer: u16 = 0;
+    let mut bits = 0;
+    for b in bytes {
+        buffer = (buffer << 8) | *b as u16;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            s.push(BASE32_ALPHABET[((buffer >> bits) & 31) as usize] as char);
+        }
+        buffer &= (1 << bits) - 1;
+    }
+    if bits > 0 {
+        s.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 31) as usize] as char);
+    }
+    s
+}
+
+/// `<urn:uuid:...>` derived from the record content and the item's sequence number,
+/// so writing the same items again gives the same ids while repeated exchanges in a
+/// file get distinct ones.
+fn record_id(parts: &[&[u8]]) -> String {
+    let mut hasher = Sha1::new();
+    for part in parts {
+        hasher.update((part.len() as u64).to_be_bytes());
+        hasher.update(part);
+    }
+    let mut b = hasher.finalize();
+    // Name-based UUID, version 5.
+    b[6] = (b[6] & 0x0f) | 0x50;
+    b[8] = (b[8] & 0x3f) | 0x80;
+    let hex = b[..16]
+        .iter()
+        .map(|x| format!("{x:02x}"))
+        .collect::<String>();
+    format!(
+        "<urn:uuid:{}-{}-{}-{}-{}>",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+fn format_date(time: &NaiveDateTime) -> String {
+    time.and_utc().to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+//
+/// Writes each item as a `response` record followed by its `request` record, the
+/// latter pointing to the former with `WARC-Concurrent-To`.
+pub struct WarcWriter<W>
+where
+    W: Write,
+{
+    writer: W,
+    n: u64,
+}
+
+impl<W> WarcWriter<W>
+where
+    W: Write,
+{
+    pub fn new(writer: W) -> Self {
+        Self { writer, n: 0 }
+    }
+
+    pub fn write_record(&mut self, record: &WarcRecord) -> Result<(), WarcError> {
+        Ok(record.write_to(&mut self.writer)?)
+    }
+
+    pub fn write_item(&mut self, item: &Item) -> Result<(), WarcError> {
+        for record in item_to_records(item, self.n)? {
+            self.write_record(&record)?;
+        }
+        self.n += 1;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// `response` then `request` record of an item, only `request` without a response.
+///
+/// `sequence` is mixed into the record ids, it must differ between items of a file.
+pub fn item_to_records(item: &Item, sequence: u64) -> Result<Vec<WarcRecord>, WarcError> {
+    let date = format_date(&item.time);
+    let ip = String::from_utf8_lossy(&item.host.0.ip).into_owned();
+
+    let record = |record_type: &str, block: Vec<u8>, concurrent_to: Option<&str>| {
+        let id = record_id(&[
+            &sequence.to_be_bytes(),
+            record_type.as_bytes(),
+            item.url.as_bytes(),
+            date.as_bytes(),
+            &block,
+        ]);
+        let mut headers = vec![
+            ("WARC-Type".to_owned(), record_type.to_owned()),
+            ("WARC-Record-ID".to_owned(), id),
+            ("WARC-Date".to_owned(), date.to_owned()),
+            ("WARC-Target-URI".to_owned(), item.url.to_owned()),
+        ];
+        if !ip.is_empty() {
+            headers.push(("WARC-IP-Address".to_owned(), ip.to_owned()));
+        }
+        if let Some(x) = concurrent_to {
+            headers.push(("WARC-Concurrent-To".to_owned(), x.to_owned()));
+        }
+        headers.push((
+            "Content-Type".to_owned(),
+            format!("application/http;msgtype={record_type}"),
+        ));
+        headers.push(("WARC-Block-Digest".to_owned(), sha1_digest(&block)));
+        if let Ok(message) = Message::parse(&block) {
+            headers.push(("WARC-Payload-Digest".to_owned(), sha1_digest(message.body)));
+        }
+        WarcRecord { headers, block }
+    };
+
+    let request = item.request_bytes()?.into_owned();
+    let response = item.response_bytes()?.into_owned();
+    if response.is_empty() {
+        return Ok(vec![record("request", request, None)]);
+    }
+    let response = record("response", response, None);
+    let request = record("request", request, response.record_id());
+    Ok(vec![response, request])
+}
+
+//
+/// Reads `request` and `response` records back into items, pairing them through
+/// `WARC-Concurrent-To` in either direction. Other record types are skipped.
+///
+/// A request without a response gives an item with an empty response, a response
+/// without a request gets a `GET` request synthesized from `WARC-Target-URI`.
+pub struct WarcReader<R>
+where
+    R: BufRead,
+{
+    reader: R,
+    line: Vec<u8>,
+    pending: Vec<WarcRecord>,
+    ready: VecDeque<Result<Item, WarcError>>,
+    is_eof: bool,
+}
+
+impl<R> WarcReader<R>
+where
+    R: BufRead,
+{
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: vec![],
+            pending: vec![],
+            ready: VecDeque::new(),
+            is_eof: false,
+        }
+    }
+
+    fn read_line(&mut self) -> Result<bool, WarcError> {
+        self.line.clear();
+        Ok(self.reader.read_until(b'\n', &mut self.line)? > 0)
+    }
+
+    /// Next record of any type, `None` at end of input.
+    pub fn next_record(&mut self) -> Result<Option<WarcRecord>, WarcError> {
+        // Blank lines between records.
+        loop {
+            if !self.read_line()? {
+                return Ok(None);
+            }
+            if !self.line.trim_ascii().is_empty() {
+                break;
+            }
+        }
+        if !self.line.starts_with(b"WARC/") {
+            return Err(WarcError::RecordInvalid(
+                String::from_utf8_lossy(self.line.trim_ascii()).into_owned(),
+            ));
+        }
+
+        let mut record = WarcRecord::default();
+        let mut content_length = None;
+        loop {
+            if !self.read_line()? {
+                return Err(WarcError::RecordInvalid("truncated header".to_owned()));
+            }
+            let line = String::from_utf8_lossy(&self.line);
+            let line = line.trim_end_matches(['\r', '\n']);
+            if line.is_empty() {
+                break;
+            }
+            if line.starts_with([' ', '\t']) {
+                if let Some((_, value)) = record.headers.last_mut() {
+                    value.push(' ');
+                    value.push_str(line.trim());
+                }
+                continue;
+            }
+            let (name, value) = line
+                .split_once(':')
+                .ok_or_else(|| WarcError::RecordInvalid(line.to_owned()))?;
+            let (name, value) = (name.trim(), value.trim());
+            if name.eq_ignore_ascii_case("Content-Length") {
+                content_length = Some(
+                    value
+                        .parse::<usize>()
+                        .map_err(|_| WarcError::RecordInvalid(line.to_owned()))?,
+                );
+            } else {
+                record.headers.push((name.to_owned(), value.to_owned()));
+            }
+        }
+
+        let content_length = content_length
+            .ok_or_else(|| WarcError::RecordInvalid("Content-Length missing".to_owned()))?;
+        // Read as it comes, the declared length is not trusted for allocation.
+        let len = (&mut self.reader)
+            .take(content_length as u64)
+            .read_to_end(&mut record.block)?;
+        if len < content_length {
+            return Err(WarcError::RecordInvalid("truncated block".to_owned()));
+        }
+        Ok(Some(record))
+    }
+
+    fn add_record(&mut self, record: WarcRecord) {
+        let is_http = record
+            .header("WARC-Target-URI")
+            .map(|x| x.starts_with("http://") || x.starts_with("https://"))
+            == Some(true);
+        if !is_http || !matches!(record.record_type(), Some("request" | "response")) {
+            return;
+        }
+
+        let linked = self
+            .pending
+            .iter()
+            .position(|x| x.record_type() != record.record_type() && x.is_linked_to(&record));
+        match linked {
+            Some(n) => {
+                let other = self.pending.remove(n);
+                let (request, response) = if record.record_type() == Some("request") {
+                    (record, other)
+                } else {
+                    (other, record)
+                };
+                self.ready
+                    .push_back(records_to_item(Some(&request), Some(&response)));
+            }
+            None => self.pending.push(record),
+        }
+    }
+}
+
+impl<R> Iterator for WarcReader<R>
+where
+    R: BufRead,
+{
+    type Item = Result<Item, WarcError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(x) = self.ready.pop_front() {
+                return Some(x);
+            }
+            if self.is_eof {
+                return None;
+            }
+            match self.next_record() {
+                Ok(Some(record)) => self.add_record(record),
+                Ok(None) => {
+                    self.is_eof = true;
+                    for record in core::mem::take(&mut self.pending) {
+                        let item = if record.record_type() == Some("request") {
+                            records_to_item(Some(&record), None)
+                        } else {
+                            records_to_item(None, Some(&record))
+                        };
+                        self.ready.push_back(item);
+                    }
+                }
+                Err(err) => {
+                    self.is_eof = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+fn records_to_item(
+    request: Option<&WarcRecord>,
+    response: Option<&WarcRecord>,
+) -> Result<Item, WarcError> {
+    let records = || request.into_iter().chain(response);
+    let header = |name: &str| records().find_map(|x| x.header(name));
+
+    let url = header("WARC-Target-URI").unwrap_or_default();
+    let (protocol, host, port, target) =
+        item::split_url(url).ok_or_else(|| WarcError::RecordInvalid(url.to_owned()))?;
+
+    let request_bytes = match request {
+        Some(x) => x.block.to_owned(),
+        None => format!("GET {target} HTTP/1.1\r\nHost: {host}\r\n\r\n").into_bytes(),
+    };
+    let response_bytes = response.map(|x| &x.block[..]).unwrap_or_default();
+
+    let mut item = Item::from_messages(protocol, &host, port, &request_bytes, response_bytes)?;
+    if let Some(time) = response
+        .and_then(|x| x.header("WARC-Date"))
+        .or_else(|| header("WARC-Date"))
+        .and_then(|x| DateTime::parse_from_rfc3339(x).ok())
+    {
+        item.time = time.naive_utc();
+    }
+    if let Some(ip) = header("WARC-IP-Address") {
+        item.host.0 = ItemHostAttr {
+            ip: ip.as_bytes().to_vec(),
+        };
+    }
+    Ok(item)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{collections::HashSet, fs::File, io::BufReader};
+
+    use crate::http_history::items::Items;
+
+    #[test]
+    fn test_digest() {
+        assert_eq!(sha1_digest(b""), "sha1:3I42H3S6NNFQ2MSVX7XZKYAYSCX5QBYJ");
+        assert_eq!(base32(b"foobar"), "MZXW6YTBOI");
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+        let file = File::open("tests/http_history_files/burpsuite_community_v2021.3.2.xml")?;
+        let items = Items::from_reader(BufReader::new(file))?.collect::<Result<Vec<_>, _>>()?;
+
+        let mut writer = WarcWriter::new(vec![]);
+        for item in items.iter() {
+            writer.write_item(item)?;
+        }
+        let bytes = writer.into_inner();
+        let text = String::from_utf8_lossy(&bytes);
+        assert!(text.starts_with("WARC/1.1\r\nWARC-Type: response\r\nWARC-Record-ID: <urn:uuid:"));
+        assert!(text.contains("WARC-Target-URI: http://httpbin.org/get?foo=bar\r\n"));
+        assert!(text.contains("WARC-Payload-Digest: sha1:"));
+
+        let records = {
+            let mut reader = WarcReader::new(&bytes[..]);
+            let mut records = vec![];
+            while let Some(record) = reader.next_record()? {
+                records.push(record);
+            }
+            records
+        };
+        assert_eq!(records.len(), items.len() * 2);
+        assert_eq!(records[1].record_type(), Some("request"));
+        assert_eq!(records[1].concurrent_to().next(), records[0].record_id());
+
+        let mut writer = WarcWriter::new(vec![]);
+        writer.write_item(&items[0])?;
+        writer.write_item(&items[0])?;
+        let repeated = writer.into_inner();
+        let mut reader = WarcReader::new(&repeated[..]);
+        let mut ids = HashSet::new();
+        while let Some(record) = reader.next_record()? {
+            assert!(ids.insert(record.record_id().map(|x| x.to_owned())));
+        }
+        assert_eq!(ids.len(), 4);
+        assert_eq!(
+            records[0].header("WARC-Date"),
+            Some(format_date(&items[0].time).as_str())
+        );
+
+        let read = WarcReader::new(&bytes[..]).collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(read.len(), items.len());
+        for (a, b) in items.iter().zip(read.iter()) {
+            assert_eq!(a.time, b.time);
+            assert_eq!(a.url, b.url);
+            assert_eq!(a.host.0.ip, b.host.0.ip);
+            assert_eq!(a.port, b.port);
+            assert_eq!(a.method, b.method);
+            assert_eq!(a.status, b.status);
+            assert_eq!(a.request_bytes()?, b.request_bytes()?);
+            assert_eq!(a.response_bytes()?, b.response_bytes()?);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_unpaired() -> Result<(), Box<dyn std::error::Error>> {
+        let block = "HTTP/1.1 404 Not Found\r\n\r\n";
+        let warc = format!(
+            "WARC/1.0\r\nWARC-Type: warcinfo\r\nContent-Length: 0\r\n\r\n\r\n\r\n\
+             WARC/1.0\r\nWARC-Type: response\r\nWARC-Target-URI: https://example.com:8443/x\r\n\
+             WARC-Date: 2024-01-02T03:04:05Z\r\nContent-Length: {}\r\n\r\n{block}\r\n\r\n",
+            block.len()
+        );
+        let items = WarcReader::new(warc.as_bytes()).collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].port, 8443);
+        assert_eq!(items[0].status.as_u16(), 404);
+        assert_eq!(items[0].time.to_string(), "2024-01-02 03:04:05");
+        assert_eq!(
+            &items[0].request_bytes()?[..],
+            b"GET /x HTTP/1.1\r\nHost: example.com\r\n\r\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_truncated() {
+        let warc = "WARC/1.1\r\nWARC-Type: response\r\nContent-Length: 99999999999999\r\n\r\nabc";
+        match WarcReader::new(warc.as_bytes()).next_record() {
+            Err(WarcError::RecordInvalid(x)) => assert_eq!(x, "truncated block"),
+            x => panic!("{x:?}"),
+        }
+    }
+}
@@ -0,0 +1,259 @@
+use std::io::{Error as IoError, Write};
+
+use chrono::NaiveDateTime;
+use quick_xml::escape::escape;
+
+use super::{
+    compression::{CompressionError, CompressionOptions, Encoder},
+    item::Item,
+    items::ItemsAttr,
+};
+
+pub const DOCTYPE: &str = r#"<!DOCTYPE items [
+<!ELEMENT items (item*)>
+<!ATTLIST items burpVersion CDATA "">
+<!ATTLIST items exportTime CDATA "">
+<!ELEMENT item (time, url, host, port, protocol, method, path, extension, request, status, responselength, mimetype, response, comment)>
+<!ELEMENT time (#PCDATA)>
+<!ELEMENT url (#PCDATA)>
+<!ELEMENT host (#PCDATA)>
+<!ATTLIST host ip CDATA "">
+<!ELEMENT port (#PCDATA)>
+<!ELEMENT protocol (#PCDATA)>
+<!ELEMENT method (#PCDATA)>
+<!ELEMENT path (#PCDATA)>
+<!ELEMENT extension (#PCDATA)>
+<!ELEMENT request (#PCDATA)>
+<!ATTLIST request base64 (true|false) "false">
+<!ELEMENT status (#PCDATA)>
+<!ELEMENT responselength (#PCDATA)>
+<!ELEMENT mimetype (#PCDATA)>
+<!ELEMENT response (#PCDATA)>
+<!ATTLIST response base64 (true|false) "false">
+<!ELEMENT comment (#PCDATA)>
+]>"#;
+
+/// Burp writes the local zone abbreviation, which is not kept on parse.
+const TIME_FORMAT: &str = "%a %b %d %T UTC %Y";
+
+const EOL: &str = "\r\n";
+
+#[derive(thiserror::Error, Debug)]
+pub enum ItemsWriteError {
+    #[error("IoError {0:?}")]
+    IoError(#[from] IoError),
+    #[error("AlreadyFinished")]
+    AlreadyFinished,
+    #[error("PartFinished {0}")]
+    PartFinished(String),
+    #[error("CompressionError {0}")]
+    CompressionError(#[from] CompressionError),
+}
+
+/// Writes items as a Burp Suite "Save items" XML file.
+pub struct ItemsWriter<W>
+where
+    W: Write,
+{
+    writer: W,
+    is_finished: bool,
+}
+
+impl<W> ItemsWriter<W>
+where
+    W: Write,
+{
+    pub fn new(mut writer: W, attr: &ItemsAttr) -> Result<Self, ItemsWriteError> {
+        let doctype = DOCTYPE.replace('\n', EOL);
+        write!(
+            writer,
+            r#"<?xml version="1.0"?>{EOL}{doctype}{EOL}<items burpVersion="{}" exportTime="{}">{EOL}"#,
+            escape(&attr.burp_version),
+            format_time(&attr.export_time),
+        )?;
+
+        Ok(Self {
+            writer,
+            is_finished: false,
+        })
+    }
+
+    pub fn write_item(&mut self, item: &Item) -> Result<(), ItemsWriteError> {
+        if self.is_finished {
+            return Err(ItemsWriteError::AlreadyFinished);
+        }
+
+        let w = &mut self.writer;
+        write!(w, "  <item>{EOL}")?;
+        write!(w, "    <time>{}</time>{EOL}", format_time(&item.time))?;
+        write!(w, "    <url>")?;
+        write_cdata(w, item.url.as_bytes())?;
+        write!(w, "</url>{EOL}")?;
+        write!(
+            w,
+            r#"    <host ip="{}">{}</host>{EOL}"#,
+            escape(&String::from_utf8_lossy(&item.host.0.ip)),
+            escape(&item.host.1)
+        )?;
+        write!(w, "    <port>{}</port>{EOL}", item.port)?;
+        write!(w, "    <protocol>{}</protocol>{EOL}", item.protocol)?;
+        write!(w, "    <method>")?;
+        write_cdata(w, item.method.as_str().as_bytes())?;
+        write!(w, "</method>{EOL}")?;
+        write!(w, "    <path>")?;
+        write_cdata(w, item.path.as_bytes())?;
+        write!(w, "</path>{EOL}")?;
+        write!(
+            w,
+            "    <extension>{}</extension>{EOL}",
+            escape(item.extension.as_deref().unwrap_or("null"))
+        )?;
+        write!(w, r#"    <request base64="{}">"#, item.request.0.base64)?;
+        write_cdata(w, &item.request.1)?;
+        write!(w, "</request>{EOL}")?;
+        write!(w, "    <status>{}</status>{EOL}", item.status.as_u16())?;
+        write!(
+            w,
+            "    <responselength>{}</responselength>{EOL}",
+            item.response_length
+        )?;
+        write!(
+            w,
+            "    <mimetype>{}</mimetype>{EOL}",
+            escape(&item.mimetype)
+        )?;
+        write!(w, r#"    <response base64="{}">"#, item.response.0.base64)?;
+        write_cdata(w, &item.response.1)?;
+        write!(w, "</response>{EOL}")?;
+        write!(
+            w,
+            "    <comment>{}</comment>{EOL}",
+            escape(item.comment.as_deref().unwrap_or_default())
+        )?;
+        write!(w, "  </item>{EOL}")?;
+
+        Ok(())
+    }
+
+    /// Close the `items` element and flush. Must be called once all items are written.
+    pub fn finish(&mut self) -> Result<(), ItemsWriteError> {
+        if self.is_finished {
+            return Err(ItemsWriteError::AlreadyFinished);
+        }
+        write!(self.writer, "</items>{EOL}")?;
+        self.writer.flush()?;
+        self.is_finished = true;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W> ItemsWriter<Encoder<W>>
+where
+    W: Write,
+{
+    /// Like [`ItemsWriter::new`], compressing the document as set by `options`.
+    pub fn with_compression(
+        writer: W,
+        attr: &ItemsAttr,
+        options: &CompressionOptions,
+    ) -> Result<Self, ItemsWriteError> {
+        Self::new(Encoder::new(writer, options)?, attr)
+    }
+
+    /// Finish the document, if not done yet, and the compressed stream, returning the
+    /// inner writer.
+    pub fn finish_compressed(mut self) -> Result<W, ItemsWriteError> {
+        if !self.is_finished {
+            self.finish()?;
+        }
+        Ok(self.writer.finish()?)
+    }
+}
+
+pub fn format_time(time: &NaiveDateTime) -> String {
+    time.format(TIME_FORMAT).to_string()
+}
+
+/// A CDATA section, split where `bytes` contains its terminator `]]>`.
+fn write_cdata(w: &mut impl Write, bytes: &[u8]) -> Result<(), ItemsWriteError> {
+    w.write_all(b"<![CDATA[")?;
+    let mut rest = bytes;
+    while let Some(n) = rest.windows(3).position(|x| x == b"]]>") {
+        w.write_all(&rest[..n + 2])?;
+        w.write_all(b"]]><![CDATA[")?;
+        rest = &rest[n + 2..];
+    }
+    w.write_all(rest)?;
+    w.write_all(b"]]>")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{fs::File, io::BufReader};
+
+    use crate::http_history::items::Items;
+
+    #[test]
+    fn test_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+        let file = File::open("tests/http_history_files/burpsuite_community_v2021.3.2.xml")?;
+        let items = Items::from_reader(BufReader::new(file))?;
+        let attr = items.attr.to_owned();
+        let items = items.collect::<Result<Vec<_>, _>>()?;
+
+        let mut writer = ItemsWriter::new(vec![], &attr)?;
+        for item in items.iter() {
+            writer.write_item(item)?;
+        }
+        writer.finish()?;
+        assert!(writer.write_item(&items[0]).is_err());
+        let bytes = writer.into_inner();
+
+        let written = Items::from_reader(&bytes[..])?;
+        assert_eq!(written.attr.burp_version, attr.burp_version);
+        assert_eq!(written.attr.export_time, attr.export_time);
+        let written = written.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(written.len(), items.len());
+        for (a, b) in written.iter().zip(items.iter()) {
+            assert_eq!(a.time, b.time);
+            assert_eq!(a.url, b.url);
+            assert_eq!(a.host.0.ip, b.host.0.ip);
+            assert_eq!(a.method, b.method);
+            assert_eq!(a.request.1, b.request.1);
+            assert_eq!(a.response.1, b.response.1);
+            assert_eq!(a.response_length, b.response_length);
+            assert_eq!(a.comment, b.comment);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cdata_terminator() -> Result<(), Box<dyn std::error::Error>> {
+        let file = File::open("tests/http_history_files/burpsuite_community_v2021.3.2.xml")?;
+        let items = Items::from_reader(BufReader::new(file))?;
+        let attr = items.attr.to_owned();
+        let mut item = items.into_iter().next().ok_or("item missing")??;
+        item.url = "http://a/?x=]]>&y=]]]]>".to_owned();
+        item.request = (Default::default(), b"GET /]]> HTTP/1.1\r\n\r\n]]>".to_vec());
+
+        let mut writer = ItemsWriter::new(vec![], &attr)?;
+        writer.write_item(&item)?;
+        writer.finish()?;
+        let bytes = writer.into_inner();
+
+        let written = Items::from_reader(&bytes[..])?
+            .next()
+            .ok_or("item missing")??;
+        assert_eq!(written.url, item.url);
+        assert_eq!(written.request.1, item.request.1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,325 @@
+use std::io::{BufRead, Error as IoError, Read};
+
+use base64::{engine::general_purpose, DecodeError, Engine as _};
+use chrono::DateTime;
+use http::uri::Scheme;
+use once_cell::sync::Lazy;
+use regex::bytes::Regex;
+use serde_json::Value;
+
+use super::{
+    item::{self, Item, ItemHostAttr},
+    message::{Message, MessageParseError},
+};
+
+static SEPARATOR_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^==== [0-9]+ ==========\r?\n?$").expect("Invalid regex"));
+
+#[derive(thiserror::Error, Debug)]
+pub enum ZapImportError {
+    #[error("IoError {0:?}")]
+    IoError(#[from] IoError),
+    #[error("JsonError {0}")]
+    JsonError(#[from] serde_json::Error),
+    #[error("FieldMissing {0}")]
+    FieldMissing(&'static str),
+    #[error("UrlInvalid {0}")]
+    UrlInvalid(String),
+    #[error("MessageParseFailed {0}")]
+    MessageParseFailed(#[from] MessageParseError),
+    #[error("Base64Invalid {0}")]
+    Base64Invalid(#[from] DecodeError),
+}
+
+fn split_url(url: &str) -> Result<(Scheme, String, u16, String), ZapImportError> {
+    item::split_url(url).ok_or_else(|| ZapImportError::UrlInvalid(url.to_owned()))
+}
+
+//
+/// Items of a HAR file exported by ZAP, in entry order.
+pub fn items_from_har<R>(reader: R) -> Result<Vec<Item>, ZapImportError>
+where
+    R: Read,
+{
+    let har: Value = serde_json::from_reader(reader)?;
+    har["log"]["entries"]
+        .as_array()
+        .ok_or(ZapImportError::FieldMissing("log.entries"))?
+        .iter()
+        .map(har_entry_to_item)
+        .collect()
+}
+
+fn har_entry_to_item(entry: &Value) -> Result<Item, ZapImportError> {
+    let str_field = |value: &Value, name: &'static str| -> Result<String, ZapImportError> {
+        value[name]
+            .as_str()
+            .map(ToOwned::to_owned)
+            .ok_or(ZapImportError::FieldMissing(name))
+    };
+    let head = |start_line: String, value: &Value| {
+        let mut bytes = start_line.into_bytes();
+        bytes.extend(b"\r\n");
+        for header in value["headers"].as_array().into_iter().flatten() {
+            bytes.extend(header["name"].as_str().unwrap_or_default().as_bytes());
+            bytes.extend(b": ");
+            bytes.extend(header["value"].as_str().unwrap_or_default().as_bytes());
+            bytes.extend(b"\r\n");
+        }
+        bytes.extend(b"\r\n");
+        bytes
+    };
+
+    let request = &entry["request"];
+    let url = str_field(request, "url")?;
+    let (protocol, host, port, target) = split_url(&url)?;
+    let mut request_bytes = head(
+        format!(
+            "{} {target} {}",
+            str_field(request, "method")?,
+            str_field(request, "httpVersion")?
+        ),
+        request,
+    );
+    if let Some(text) = request["postData"]["text"].as_str() {
+        request_bytes.extend(text.as_bytes());
+    }
+
+    let response = &entry["response"];
+    let mut response_bytes = vec![];
+    if let Some(status) = response["status"].as_u64().filter(|x| *x > 0) {
+        response_bytes = head(
+            format!(
+                "{} {status} {}",
+                str_field(response, "httpVersion")?,
+                response["statusText"].as_str().unwrap_or_default()
+            ),
+            response,
+        );
+        let content = &response["content"];
+        if let Some(text) = content["text"].as_str() {
+            if content["encoding"].as_str() == Some("base64") {
+                response_bytes.extend(general_purpose::STANDARD.decode(text)?);
+            } else {
+                response_bytes.extend(text.as_bytes());
+            }
+        }
+    }
+
+    let mut item = Item::from_messages(protocol, &host, port, &request_bytes, &response_bytes)?;
+    if let Some(time) = entry["startedDateTime"]
+        .as_str()
+        .and_then(|x| DateTime::parse_from_rfc3339(x).ok())
+    {
+        item.time = time.naive_utc();
+    }
+    if let Some(ip) = entry["serverIPAddress"].as_str() {
+        item.host.0 = ItemHostAttr {
+            ip: ip.trim_matches(['[', ']']).as_bytes().to_vec(),
+        };
+    }
+    item.comment = entry["comment"]
+        .as_str()
+        .filter(|x| !x.is_empty())
+        .map(ToOwned::to_owned);
+    Ok(item)
+}
+
+//
+/// Reads ZAP's "Export Messages to File" format, one `==== N ==========` block per message.
+///
+/// The format has no time nor address, `time` is left at the epoch and `host.0.ip` empty.
+pub struct ZapRawReader<R>
+where
+    R: BufRead,
+{
+    reader: R,
+    block: Vec<u8>,
+    line: Vec<u8>,
+    is_eof: bool,
+}
+
+impl<R> ZapRawReader<R>
+where
+    R: BufRead,
+{
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            block: vec![],
+            line: vec![],
+            is_eof: false,
+        }
+    }
+
+    /// Bytes between two separators, `None` at end of input.
+    fn next_block(&mut self) -> Result<Option<Vec<u8>>, ZapImportError> {
+        loop {
+            self.line.clear();
+            if self.reader.read_until(b'\n', &mut self.line)? == 0 {
+                self.is_eof = true;
+                let block = core::mem::take(&mut self.block);
+                return Ok(if block.iter().all(u8::is_ascii_whitespace) {
+                    None
+                } else {
+                    Some(block)
+                });
+            }
+            if SEPARATOR_RE.is_match(&self.line) {
+                let block = core::mem::take(&mut self.block);
+                if !block.iter().all(u8::is_ascii_whitespace) {
+                    return Ok(Some(block));
+                }
+            } else {
+                self.block.extend(&self.line);
+            }
+        }
+    }
+}
+
+/// Split a block into request and response, and rewrite the absolute-form request line.
+fn raw_block_to_item(block: &[u8]) -> Result<Item, ZapImportError> {
+    let request = Message::parse(block)?;
+    let (method, url, version) = request
+        .request_line()
+        .ok_or_else(|| MessageParseError::StartLineInvalid(request.start_line.to_owned()))?;
+    let (protocol, host, port, target) = split_url(url)?;
+
+    let rest = request.body;
+    let body_len = match request
+        .header_str("content-length")
+        .and_then(|x| x.trim().parse::<usize>().ok())
+    {
+        Some(n) => n.min(rest.len()),
+        // Without a length, the body runs up to the response less the newlines around it.
+        None => {
+            let end = find_status_line(rest).unwrap_or(rest.len());
+            rest[..end]
+                .iter()
+                .rposition(|b| *b != b'\r' && *b != b'\n')
+                .map(|x| x + 1)
+                .unwrap_or_default()
+        }
+    };
+    let response = &rest[body_len..];
+    let response = match find_status_line(response) {
+        Some(n) => &response[n..],
+        None => &response[response.len()..],
+    };
+    // ZAP ends each block with a newline of its own.
+    let response = response
+        .strip_suffix(b"\r\n")
+        .or_else(|| response.strip_suffix(b"\n"))
+        .unwrap_or(response);
+
+    let mut request_bytes = format!("{method} {target} {version}").into_bytes();
+    request_bytes.extend(&block[request.start_line.len()..request.body_offset + body_len]);
+
+    Ok(Item::from_messages(
+        protocol,
+        &host,
+        port,
+        &request_bytes,
+        response,
+    )?)
+}
+
+/// Offset of the first line starting with `HTTP/`.
+fn find_status_line(bytes: &[u8]) -> Option<usize> {
+    let mut offset = 0;
+    for line in bytes.split_inclusive(|b| *b == b'\n') {
+        if line.starts_with(b"HTTP/") {
+            return Some(offset);
+        }
+        offset += line.len();
+    }
+    None
+}
+
+impl<R> Iterator for ZapRawReader<R>
+where
+    R: BufRead,
+{
+    type Item = Result<Item, ZapImportError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.is_eof {
+            return None;
+        }
+        match self.next_block() {
+            Ok(Some(block)) => Some(raw_block_to_item(&block)),
+            Ok(None) => None,
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use http::{Method, StatusCode};
+
+    #[test]
+    fn test_raw() -> Result<(), Box<dyn std::error::Error>> {
+        let raw = "==== 1 ==========\r\nGET http://example.com/a/b.js?v=1 HTTP/1.1\r\nHost: example.com\r\n\r\n\r\nHTTP/1.1 200 OK\r\nContent-Type: application/javascript\r\nContent-Length: 5\r\n\r\nf();\n\r\n==== 2 ==========\r\nPOST https://example.com:8443/login HTTP/1.1\r\nHost: example.com:8443\r\nContent-Length: 7\r\n\r\nu=a&p=bHTTP/1.1 302 Found\r\nLocation: /\r\n\r\n\r\n";
+
+        let items = ZapRawReader::new(raw.as_bytes()).collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(items.len(), 2);
+
+        let item = &items[0];
+        assert_eq!(item.url, "http://example.com/a/b.js?v=1");
+        assert_eq!(item.path, "/a/b.js?v=1");
+        assert_eq!(item.extension.as_deref(), Some("js"));
+        assert_eq!(item.mimetype, "script");
+        assert_eq!(
+            &item.request_bytes()?[..],
+            b"GET /a/b.js?v=1 HTTP/1.1\r\nHost: example.com\r\n\r\n"
+        );
+        assert!(item.response_bytes()?.ends_with(b"\r\n\r\nf();\n"));
+        assert_eq!(item.response_length, 81);
+
+        let item = &items[1];
+        assert_eq!(item.method, Method::POST);
+        assert_eq!(item.protocol, Scheme::HTTPS);
+        assert_eq!(item.port, 8443);
+        assert_eq!(item.status, StatusCode::FOUND);
+        assert!(item.request_bytes()?.ends_with(b"\r\n\r\nu=a&p=b"));
+        assert_eq!(
+            &item.response_bytes()?[..],
+            b"HTTP/1.1 302 Found\r\nLocation: /\r\n\r\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_har() -> Result<(), Box<dyn std::error::Error>> {
+        let har = r#"{"log": {"version": "1.2", "creator": {"name": "OWASP ZAP", "version": "2.14.0"}, "entries": [{
+            "startedDateTime": "2024-01-02T03:04:05.678+00:00",
+            "serverIPAddress": "93.184.216.34",
+            "request": {"method": "POST", "url": "http://example.com/api", "httpVersion": "HTTP/1.1",
+                "headers": [{"name": "Host", "value": "example.com"}, {"name": "Content-Length", "value": "2"}],
+                "postData": {"mimeType": "application/json", "text": "{}"}},
+            "response": {"status": 201, "statusText": "Created", "httpVersion": "HTTP/1.1",
+                "headers": [{"name": "Content-Type", "value": "image/png"}],
+                "content": {"size": 2, "mimeType": "image/png", "text": "iVA=", "encoding": "base64"}}
+        }]}}"#;
+
+        let items = items_from_har(har.as_bytes())?;
+        assert_eq!(items.len(), 1);
+        let item = &items[0];
+        assert_eq!(item.time.to_string(), "2024-01-02 03:04:05.678");
+        assert_eq!(item.host.0.ip, b"93.184.216.34");
+        assert_eq!(item.status, StatusCode::CREATED);
+        assert_eq!(item.mimetype, "PNG");
+        assert_eq!(item.extension, None);
+        assert!(item
+            .request_bytes()?
+            .ends_with(b"Content-Length: 2\r\n\r\n{}"));
+        assert!(item.response_bytes()?.ends_with(b"\r\n\r\n\x89P"));
+        assert_eq!(item.response_length as usize, item.response_bytes()?.len());
+
+        Ok(())
+    }
+}
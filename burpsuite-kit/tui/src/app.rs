@@ -0,0 +1,533 @@
+use std::{fs::File, io::BufReader, path::Path};
+
+use burpsuite_kit::http_history::{
+    index::{IndexError, ItemsIndex},
+    item::Item,
+};
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Rows moved by PageUp and PageDown.
+const PAGE_LEN: usize = 20;
+
+/// Table columns, in display order, also the sort keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Number,
+    Host,
+    Method,
+    Url,
+    Status,
+    Length,
+    MimeType,
+    Time,
+}
+
+impl Column {
+    pub const ALL: [Self; 8] = [
+        Self::Number,
+        Self::Host,
+        Self::Method,
+        Self::Url,
+        Self::Status,
+        Self::Length,
+        Self::MimeType,
+        Self::Time,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Self::Number => "#",
+            Self::Host => "Host",
+            Self::Method => "Method",
+            Self::Url => "URL",
+            Self::Status => "Status",
+            Self::Length => "Length",
+            Self::MimeType => "MIME type",
+            Self::Time => "Time",
+        }
+    }
+}
+
+/// Table fields of an item, parsed once.
+#[derive(Debug, Clone)]
+pub struct Summary {
+    pub host: String,
+    pub method: String,
+    pub url: String,
+    pub status: u16,
+    pub length: u32,
+    pub mimetype: String,
+    pub time: String,
+}
+
+impl Summary {
+    fn new(item: &Item) -> Self {
+        Self {
+            host: item.host.1.to_owned(),
+            method: item.method.to_string(),
+            url: item.url.to_owned(),
+            status: item.status.as_u16(),
+            length: item.response_length,
+            mimetype: item.mimetype.to_owned(),
+            time: item.time.to_string(),
+        }
+    }
+
+    fn invalid(err: &IndexError) -> Self {
+        Self {
+            host: String::new(),
+            method: String::new(),
+            url: format!("<{err}>"),
+            status: 0,
+            length: 0,
+            mimetype: String::new(),
+            time: String::new(),
+        }
+    }
+
+    pub fn value(&self, column: Column, n: usize) -> String {
+        match column {
+            Column::Number => (n + 1).to_string(),
+            Column::Host => self.host.to_owned(),
+            Column::Method => self.method.to_owned(),
+            Column::Url => self.url.to_owned(),
+            Column::Status => self.status.to_string(),
+            Column::Length => self.length.to_string(),
+            Column::MimeType => self.mimetype.to_owned(),
+            Column::Time => self.time.to_owned(),
+        }
+    }
+}
+
+/// `key:value` terms, all of which must match. Bare words match the URL.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Filter {
+    terms: Vec<(String, String)>,
+}
+
+impl Filter {
+    pub fn parse(s: &str) -> Self {
+        let terms = s
+            .split_whitespace()
+            .map(|term| match term.split_once(':') {
+                Some((k, v)) if ["host", "method", "status", "mime"].contains(&k) => {
+                    (k.to_owned(), v.to_ascii_lowercase())
+                }
+                _ => ("url".to_owned(), term.to_ascii_lowercase()),
+            })
+            .collect();
+        Self { terms }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    pub fn matches(&self, summary: &Summary) -> bool {
+        self.terms.iter().all(|(k, v)| match k.as_str() {
+            "host" => summary.host.to_ascii_lowercase().contains(v.as_str()),
+            "method" => summary.method.eq_ignore_ascii_case(v),
+            "status" => match v.strip_suffix("xx") {
+                Some(class) => summary.status.to_string().starts_with(class),
+                None => summary.status.to_string() == *v,
+            },
+            "mime" => summary.mimetype.eq_ignore_ascii_case(v),
+            _ => summary.url.to_ascii_lowercase().contains(v.as_str()),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Focus {
+    Table,
+    Request,
+    Response,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Prompt {
+    Search(String),
+    Filter(String),
+}
+
+//
+pub struct App {
+    file: File,
+    pub index: ItemsIndex,
+    /// By item number, `None` until the item is first shown, sorted or filtered.
+    summaries: Vec<Option<Summary>>,
+    /// Item numbers shown in the table, in display order.
+    pub view: Vec<usize>,
+    /// Position in `view`.
+    pub selected: usize,
+    pub sort: Option<(Column, bool)>,
+    pub filter: String,
+    pub search: String,
+    pub prompt: Option<Prompt>,
+    pub focus: Focus,
+    pub is_hex: bool,
+    pub request_scroll: u16,
+    pub response_scroll: u16,
+    /// Line counts of the detail panes as last drawn, scrolling stops at the last line.
+    pub request_lines: u16,
+    pub response_lines: u16,
+    pub message: Option<String>,
+    pub should_quit: bool,
+    detail: Option<(usize, Result<Item, String>)>,
+}
+
+impl App {
+    pub fn open(path: &Path) -> Result<Self, IndexError> {
+        let index = ItemsIndex::build(BufReader::new(File::open(path)?))?;
+        Ok(Self {
+            file: File::open(path)?,
+            summaries: vec![None; index.len()],
+            view: (0..index.len()).collect(),
+            index,
+            selected: 0,
+            sort: None,
+            filter: String::new(),
+            search: String::new(),
+            prompt: None,
+            focus: Focus::Table,
+            is_hex: false,
+            request_scroll: 0,
+            response_scroll: 0,
+            request_lines: 0,
+            response_lines: 0,
+            message: None,
+            should_quit: false,
+            detail: None,
+        })
+    }
+
+    pub fn summary(&mut self, n: usize) -> &Summary {
+        if self.summaries[n].is_none() {
+            let summary = match self.index.read_item(&mut self.file, n) {
+                Ok(item) => Summary::new(&item),
+                Err(err) => Summary::invalid(&err),
+            };
+            self.summaries[n] = Some(summary);
+        }
+        self.summaries[n].as_ref().expect("loaded")
+    }
+
+    fn load_all(&mut self) {
+        for n in 0..self.summaries.len() {
+            self.summary(n);
+        }
+    }
+
+    /// Selected item, parsed once per selection.
+    pub fn detail(&mut self) -> Option<&Result<Item, String>> {
+        let n = *self.view.get(self.selected)?;
+        if self.detail.as_ref().map(|(x, _)| *x) != Some(n) {
+            let item = self
+                .index
+                .read_item(&mut self.file, n)
+                .map_err(|err| err.to_string());
+            self.detail = Some((n, item));
+        }
+        self.detail.as_ref().map(|(_, x)| x)
+    }
+
+    /// Rebuild `view` from the filter and the sort order.
+    fn apply_view(&mut self) {
+        let selected = self.view.get(self.selected).copied();
+        let filter = Filter::parse(&self.filter);
+        if !filter.is_empty() || self.sort.is_some() {
+            self.load_all();
+        }
+
+        let summaries = &self.summaries;
+        let summary = |n: usize| summaries[n].as_ref().expect("loaded");
+        let mut view: Vec<usize> = (0..self.summaries.len())
+            .filter(|n| filter.is_empty() || filter.matches(summary(*n)))
+            .collect();
+        if let Some((column, is_descending)) = self.sort {
+            view.sort_by(|a, b| {
+                let (x, y) = (summary(*a), summary(*b));
+                let ordering = match column {
+                    Column::Number => a.cmp(b),
+                    Column::Status => x.status.cmp(&y.status),
+                    Column::Length => x.length.cmp(&y.length),
+                    _ => x.value(column, *a).cmp(&y.value(column, *b)),
+                };
+                if is_descending {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            });
+        }
+
+        self.view = view;
+        self.selected = selected
+            .and_then(|n| self.view.iter().position(|x| *x == n))
+            .unwrap_or_default();
+        self.reset_scroll();
+    }
+
+    /// Select the next row from `start`, wrapping, whose URL contains the search.
+    fn find(&mut self, start: usize, is_forward: bool) -> bool {
+        let needle = self.search.to_ascii_lowercase();
+        let len = self.view.len();
+        if needle.is_empty() || len == 0 {
+            return false;
+        }
+        for i in 0..len {
+            let pos = if is_forward {
+                (start + i) % len
+            } else {
+                (start + len - i % len) % len
+            };
+            let n = self.view[pos];
+            if self
+                .summary(n)
+                .url
+                .to_ascii_lowercase()
+                .contains(needle.as_str())
+            {
+                self.select(pos);
+                return true;
+            }
+        }
+        false
+    }
+
+    fn select(&mut self, pos: usize) {
+        let pos = pos.min(self.view.len().saturating_sub(1));
+        if pos != self.selected {
+            self.selected = pos;
+            self.reset_scroll();
+        }
+    }
+
+    fn reset_scroll(&mut self) {
+        self.request_scroll = 0;
+        self.response_scroll = 0;
+    }
+
+    fn scroll(&mut self, delta: isize) {
+        match self.focus {
+            Focus::Table => {
+                let pos = self.selected as isize + delta;
+                self.select(pos.max(0) as usize);
+            }
+            Focus::Request => {
+                self.request_scroll = scrolled(self.request_scroll, delta, self.request_lines);
+            }
+            Focus::Response => {
+                self.response_scroll = scrolled(self.response_scroll, delta, self.response_lines);
+            }
+        }
+    }
+
+    pub fn on_key(&mut self, key: KeyEvent) {
+        self.message = None;
+        if let Some(prompt) = self.prompt.take() {
+            self.on_prompt_key(prompt, key);
+            return;
+        }
+
+        let page = PAGE_LEN as isize;
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.should_quit = true,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.should_quit = true
+            }
+            KeyCode::Down | KeyCode::Char('j') => self.scroll(1),
+            KeyCode::Up | KeyCode::Char('k') => self.scroll(-1),
+            KeyCode::PageDown => self.scroll(page),
+            KeyCode::PageUp => self.scroll(-page),
+            KeyCode::Home | KeyCode::Char('g') => self.scroll(isize::MIN / 2),
+            KeyCode::End | KeyCode::Char('G') => self.scroll(isize::MAX / 2),
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Focus::Table => Focus::Request,
+                    Focus::Request => Focus::Response,
+                    Focus::Response => Focus::Table,
+                }
+            }
+            KeyCode::Char('x') => self.is_hex = !self.is_hex,
+            KeyCode::Char('s') => {
+                let next = match self.sort {
+                    None => Some(Column::ALL[0]),
+                    Some((column, _)) => Column::ALL
+                        .iter()
+                        .position(|x| *x == column)
+                        .and_then(|i| Column::ALL.get(i + 1))
+                        .copied(),
+                };
+                self.sort = next.map(|x| (x, false));
+                self.apply_view();
+            }
+            KeyCode::Char('r') => {
+                if let Some((column, is_descending)) = self.sort {
+                    self.sort = Some((column, !is_descending));
+                    self.apply_view();
+                }
+            }
+            KeyCode::Char('/') => self.prompt = Some(Prompt::Search(String::new())),
+            KeyCode::Char('f') => self.prompt = Some(Prompt::Filter(self.filter.to_owned())),
+            KeyCode::Char('n') if !self.find(self.selected + 1, true) => {
+                self.message = Some(format!("Not found: {}", self.search));
+            }
+            KeyCode::Char('N') => {
+                let start = (self.selected + self.view.len()).saturating_sub(1);
+                if !self.find(start, false) {
+                    self.message = Some(format!("Not found: {}", self.search));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn on_prompt_key(&mut self, prompt: Prompt, key: KeyEvent) {
+        let (mut text, is_search) = match prompt {
+            Prompt::Search(x) => (x, true),
+            Prompt::Filter(x) => (x, false),
+        };
+        match key.code {
+            KeyCode::Esc => return,
+            KeyCode::Enter => {
+                if !is_search {
+                    self.filter = text;
+                    self.apply_view();
+                }
+                return;
+            }
+            KeyCode::Backspace => {
+                text.pop();
+            }
+            KeyCode::Char(c) => text.push(c),
+            _ => {}
+        }
+        // Search moves the selection as the query is typed.
+        if is_search {
+            self.search = text.to_owned();
+            self.find(self.selected, true);
+            self.prompt = Some(Prompt::Search(text));
+        } else {
+            self.prompt = Some(Prompt::Filter(text));
+        }
+    }
+
+    pub fn loaded_count(&self) -> usize {
+        self.summaries.iter().filter(|x| x.is_some()).count()
+    }
+}
+
+/// Scroll offset moved by `delta`, within the `lines` of a pane.
+pub fn scrolled(scroll: u16, delta: isize, lines: u16) -> u16 {
+    let last = lines.saturating_sub(1) as isize;
+    (scroll as isize).saturating_add(delta).clamp(0, last) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ratatui::{backend::TestBackend, crossterm::event::KeyEventKind, Terminal};
+
+    use crate::ui;
+
+    const FILE: &str = "../tests/http_history_files/burpsuite_community_v2021.3.2.xml";
+
+    fn press(app: &mut App, keys: &str) {
+        for c in keys.chars() {
+            let code = match c {
+                '\n' => KeyCode::Enter,
+                '\t' => KeyCode::Tab,
+                c => KeyCode::Char(c),
+            };
+            let mut key = KeyEvent::new(code, KeyModifiers::NONE);
+            key.kind = KeyEventKind::Press;
+            app.on_key(key);
+        }
+    }
+
+    #[test]
+    fn test_filter() {
+        let filter = Filter::parse("status:2xx method:post httpbin");
+        let mut summary = Summary {
+            host: "httpbin.org".to_owned(),
+            method: "POST".to_owned(),
+            url: "https://httpbin.org/post".to_owned(),
+            status: 200,
+            length: 0,
+            mimetype: "JSON".to_owned(),
+            time: String::new(),
+        };
+        assert!(filter.matches(&summary));
+        summary.status = 404;
+        assert!(!filter.matches(&summary));
+        assert!(Filter::parse("status:404 mime:json").matches(&summary));
+    }
+
+    #[test]
+    fn test_keys() -> Result<(), Box<dyn std::error::Error>> {
+        let mut app = App::open(Path::new(FILE))?;
+        assert_eq!(app.view, vec![0, 1]);
+        assert_eq!(app.loaded_count(), 0);
+
+        press(&mut app, "/post");
+        assert_eq!(app.selected, 1);
+        press(&mut app, "\n");
+        assert_eq!(app.prompt, None);
+        assert!(matches!(app.detail(), Some(Ok(x)) if x.url == "https://httpbin.org/post"));
+
+        press(&mut app, "fmethod:get\n");
+        assert_eq!(app.view, vec![0]);
+        assert_eq!(app.selected, 0);
+
+        press(&mut app, "f");
+        app.prompt = Some(Prompt::Filter(String::new()));
+        press(&mut app, "\nssss");
+        assert_eq!(app.sort, Some((Column::Url, false)));
+        assert_eq!(app.view, vec![0, 1]);
+        press(&mut app, "r");
+        assert_eq!(app.view, vec![1, 0]);
+        assert_eq!(app.loaded_count(), 2);
+
+        press(&mut app, "q");
+        assert!(app.should_quit);
+        Ok(())
+    }
+
+    #[test]
+    fn test_detail_keys() -> Result<(), Box<dyn std::error::Error>> {
+        let mut app = App::open(Path::new(FILE))?;
+        let mut terminal = Terminal::new(TestBackend::new(120, 40))?;
+        let mut draw = |app: &mut App| terminal.draw(|frame| ui::draw(frame, app)).map(|_| ());
+        draw(&mut app)?;
+        assert!(app.request_lines > 1);
+        assert!(app.response_lines > app.request_lines);
+
+        press(&mut app, "\tG");
+        assert_eq!(app.focus, Focus::Request);
+        assert_eq!(app.request_scroll, app.request_lines - 1);
+        press(&mut app, "jjk");
+        assert_eq!(app.request_scroll, app.request_lines - 2);
+        press(&mut app, "g");
+        assert_eq!(app.request_scroll, 0);
+
+        press(&mut app, "\tG");
+        assert_eq!(app.response_scroll, app.response_lines - 1);
+        // Toggling hex changes the line count, the scroll is kept within it.
+        press(&mut app, "x");
+        draw(&mut app)?;
+        let lines = app.response_lines;
+        press(&mut app, "G");
+        assert_eq!(app.response_scroll, lines - 1);
+        press(&mut app, "x");
+        draw(&mut app)?;
+        assert!(app.response_scroll < app.response_lines);
+
+        press(&mut app, "\tj");
+        assert_eq!(app.focus, Focus::Table);
+        assert_eq!(app.selected, 1);
+        assert_eq!((app.request_scroll, app.response_scroll), (0, 0));
+        Ok(())
+    }
+}
@@ -0,0 +1,256 @@
+use std::str;
+
+use burpsuite_kit::http_history::message::Message;
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+use serde_json::Value;
+
+const HEX_ROW_LEN: usize = 16;
+
+/// Bodies larger than this are cut before highlighting.
+const BODY_MAX_LEN: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyKind {
+    Json,
+    Html,
+    Text,
+    Binary,
+}
+
+impl BodyKind {
+    pub fn detect(message: &Message<'_>) -> Self {
+        let body = message.body;
+        let mime_type = message.mime_type().unwrap_or_default();
+        if str::from_utf8(body).is_err() {
+            return Self::Binary;
+        }
+        if (mime_type.contains("json") || body.trim_ascii_start().starts_with(b"{"))
+            && serde_json::from_slice::<Value>(body).is_ok()
+        {
+            return Self::Json;
+        }
+        if mime_type.contains("html") || mime_type.contains("xml") {
+            return Self::Html;
+        }
+        Self::Text
+    }
+}
+
+/// Lines of a raw message, the head as text and the body highlighted by kind, or as
+/// a hex dump when `is_hex` is set or the body is not UTF-8.
+pub fn message_lines(bytes: &[u8], is_hex: bool) -> Vec<Line<'static>> {
+    let message = match Message::parse(bytes) {
+        Ok(x) => x,
+        Err(_) => return hex(bytes),
+    };
+
+    let mut lines = vec![Line::from(Span::styled(
+        message.start_line.to_owned(),
+        Style::default().add_modifier(Modifier::BOLD),
+    ))];
+    for header in message.headers.iter() {
+        lines.push(Line::from(vec![
+            Span::styled(header.name.to_owned(), Style::default().fg(Color::Cyan)),
+            Span::raw(": "),
+            Span::raw(String::from_utf8_lossy(header.value).into_owned()),
+        ]));
+    }
+    lines.push(Line::default());
+
+    let body = &message.body[..message.body.len().min(BODY_MAX_LEN)];
+    if body.is_empty() {
+        return lines;
+    }
+    let kind = if is_hex {
+        BodyKind::Binary
+    } else {
+        BodyKind::detect(&message)
+    };
+    let text = || String::from_utf8_lossy(body);
+    lines.extend(match kind {
+        BodyKind::Json => json(&text()),
+        BodyKind::Html => html(&text()),
+        BodyKind::Text => text().lines().map(|x| Line::raw(x.to_owned())).collect(),
+        BodyKind::Binary => hex(body),
+    });
+    lines
+}
+
+/// Pretty-printed JSON with keys, strings, numbers and literals colored.
+pub fn json(text: &str) -> Vec<Line<'static>> {
+    let pretty = match serde_json::from_str::<Value>(text) {
+        Ok(x) => serde_json::to_string_pretty(&x).unwrap_or_else(|_| text.to_owned()),
+        Err(_) => text.to_owned(),
+    };
+
+    pretty
+        .lines()
+        .map(|line| {
+            let mut spans = vec![];
+            let mut rest = line;
+            while !rest.is_empty() {
+                let c = rest.chars().next().unwrap_or_default();
+                let len = match c {
+                    '"' => string_len(rest),
+                    '-' | '0'..='9' => rest
+                        .find(|x: char| !(x.is_ascii_digit() || "+-.eE".contains(x)))
+                        .unwrap_or(rest.len()),
+                    'a'..='z' => rest
+                        .find(|x: char| !x.is_ascii_lowercase())
+                        .unwrap_or(rest.len()),
+                    _ => c.len_utf8(),
+                };
+                let (token, tail) = rest.split_at(len);
+                let style = match c {
+                    '"' if tail.trim_start().starts_with(':') => Style::default().fg(Color::Blue),
+                    '"' => Style::default().fg(Color::Green),
+                    '-' | '0'..='9' => Style::default().fg(Color::Magenta),
+                    'a'..='z' => Style::default().fg(Color::Yellow),
+                    _ => Style::default(),
+                };
+                spans.push(Span::styled(token.to_owned(), style));
+                rest = tail;
+            }
+            Line::from(spans)
+        })
+        .collect()
+}
+
+/// Length of the JSON string literal at the start of `s`, quotes included.
+fn string_len(s: &str) -> usize {
+    let mut is_escaped = false;
+    for (i, c) in s.char_indices().skip(1) {
+        match c {
+            _ if is_escaped => is_escaped = false,
+            '\\' => is_escaped = true,
+            '"' => return i + 1,
+            _ => {}
+        }
+    }
+    s.len()
+}
+
+/// HTML or XML with tags, attribute values and comments colored.
+pub fn html(text: &str) -> Vec<Line<'static>> {
+    let tag = Style::default().fg(Color::Blue);
+    let value = Style::default().fg(Color::Green);
+    let comment = Style::default().fg(Color::DarkGray);
+
+    let mut is_in_tag = false;
+    let mut is_in_comment = false;
+    text.lines()
+        .map(|line| {
+            let mut spans = vec![];
+            let mut rest = line;
+            while !rest.is_empty() {
+                if is_in_comment {
+                    let len = rest.find("-->").map(|x| x + 3).unwrap_or(rest.len());
+                    is_in_comment = len == rest.len() && !rest.ends_with("-->");
+                    spans.push(Span::styled(rest[..len].to_owned(), comment));
+                    rest = &rest[len..];
+                } else if is_in_tag {
+                    if let Some(quote) = rest.chars().next().filter(|x| *x == '"' || *x == '\'') {
+                        let len = rest[1..].find(quote).map(|x| x + 2).unwrap_or(rest.len());
+                        spans.push(Span::styled(rest[..len].to_owned(), value));
+                        rest = &rest[len..];
+                    } else {
+                        let len = rest
+                            .find(['"', '\'', '>'])
+                            .map(|x| if rest[x..].starts_with('>') { x + 1 } else { x })
+                            .unwrap_or(rest.len());
+                        is_in_tag = !rest[..len].ends_with('>');
+                        spans.push(Span::styled(rest[..len].to_owned(), tag));
+                        rest = &rest[len..];
+                    }
+                } else if rest.starts_with("<!--") {
+                    is_in_comment = true;
+                } else if rest.starts_with('<') {
+                    is_in_tag = true;
+                } else {
+                    let len = rest.find('<').unwrap_or(rest.len());
+                    spans.push(Span::raw(rest[..len].to_owned()));
+                    rest = &rest[len..];
+                }
+            }
+            Line::from(spans)
+        })
+        .collect()
+}
+
+/// Offset, hex bytes and printable ASCII, 16 bytes per line.
+pub fn hex(bytes: &[u8]) -> Vec<Line<'static>> {
+    bytes
+        .chunks(HEX_ROW_LEN)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let hex = chunk
+                .iter()
+                .map(|b| format!("{b:02x}"))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let ascii: String = chunk
+                .iter()
+                .map(|b| {
+                    if b.is_ascii_graphic() || *b == b' ' {
+                        *b as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            Line::from(vec![
+                Span::styled(
+                    format!("{:08x}  ", i * HEX_ROW_LEN),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::raw(format!("{hex:<47}  ")),
+                Span::styled(format!("|{ascii}|"), Style::default().fg(Color::Yellow)),
+            ])
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(line: &Line<'_>) -> String {
+        line.spans.iter().map(|x| x.content.as_ref()).collect()
+    }
+
+    #[test]
+    fn test_json() {
+        let lines = json(r#"{"a":"x\"y","b":[1,-2.5e3,true,null]}"#);
+        assert_eq!(text(&lines[1]), r#"  "a": "x\"y","#);
+        assert_eq!(lines[1].spans[2].style.fg, Some(Color::Blue));
+        assert_eq!(lines[1].spans[5].style.fg, Some(Color::Green));
+        assert_eq!(text(&lines[4]), "    -2500.0,");
+        assert_eq!(lines[4].spans[4].style.fg, Some(Color::Magenta));
+    }
+
+    #[test]
+    fn test_html() {
+        let lines = html("<a href=\"/x\">y</a><!-- z\n-->");
+        assert_eq!(text(&lines[0]), "<a href=\"/x\">y</a><!-- z");
+        assert_eq!(lines[0].spans[1].style.fg, Some(Color::Green));
+        assert_eq!(lines[0].spans[3].content, "y");
+        assert_eq!(lines[1].spans[0].style.fg, Some(Color::DarkGray));
+    }
+
+    #[test]
+    fn test_message_lines() {
+        let lines = message_lines(
+            b"HTTP/1.1 200 OK\r\nContent-Type: image/png\r\n\r\n\x89PNG",
+            false,
+        );
+        assert_eq!(text(&lines[0]), "HTTP/1.1 200 OK");
+        assert_eq!(text(&lines[1]), "Content-Type: image/png");
+        assert_eq!(
+            text(&lines[3]),
+            format!("00000000  89 50 4e 47{}  |.PNG|", " ".repeat(36))
+        );
+    }
+}
@@ -0,0 +1,59 @@
+/*
+cargo run -p burpsuite-kit-tui -- burpsuite-kit/tests/http_history_files/burpsuite_community_v2021.3.2.xml
+*/
+
+mod app;
+mod highlight;
+mod ui;
+
+use std::{env, io, path::PathBuf, process::ExitCode};
+
+use ratatui::{
+    crossterm::event::{self, Event, KeyEventKind},
+    DefaultTerminal,
+};
+
+use crate::app::App;
+
+fn main() -> ExitCode {
+    let path = match env::args_os().nth(1) {
+        Some(x) => PathBuf::from(x),
+        None => {
+            eprintln!("Usage: burpsuite-kit-tui <FILE.xml>");
+            return ExitCode::from(2);
+        }
+    };
+
+    eprintln!("Indexing {}", path.display());
+    let mut app = match App::open(&path) {
+        Ok(x) => x,
+        Err(err) => {
+            eprintln!("burpsuite-kit-tui: {err}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &mut app);
+    ratatui::restore();
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("burpsuite-kit-tui: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
+    while !app.should_quit {
+        terminal.draw(|frame| ui::draw(frame, app))?;
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press {
+                app.on_key(key);
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,167 @@
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Text},
+    widgets::{Block, Paragraph, Row, Table, TableState},
+    Frame,
+};
+
+use crate::{
+    app::{self, App, Column, Focus, Prompt},
+    highlight,
+};
+
+const HELP: &str =
+    "q quit  j/k move  Tab focus  / search  n/N next  f filter  s sort  r reverse  x hex";
+
+pub fn draw(frame: &mut Frame<'_>, app: &mut App) {
+    let [table_area, detail_area, status_area] = Layout::vertical([
+        Constraint::Percentage(40),
+        Constraint::Min(3),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    let [request_area, response_area] =
+        Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .areas(detail_area);
+
+    draw_table(frame, app, table_area);
+    draw_detail(frame, app, request_area, response_area);
+    draw_status(frame, app, status_area);
+}
+
+fn border_style(app: &App, focus: Focus) -> Style {
+    if app.focus == focus {
+        Style::default().fg(Color::Yellow)
+    } else {
+        Style::default()
+    }
+}
+
+/// Only the visible rows are built, summaries are loaded as rows come into view.
+fn draw_table(frame: &mut Frame<'_>, app: &mut App, area: Rect) {
+    let height = area.height.saturating_sub(3) as usize;
+    let top = app
+        .selected
+        .saturating_sub(height.saturating_sub(1))
+        .min(app.view.len().saturating_sub(height.max(1)));
+
+    let rows: Vec<Row<'_>> = (top..(top + height).min(app.view.len()))
+        .map(|pos| {
+            let n = app.view[pos];
+            let summary = app.summary(n).to_owned();
+            Row::new(Column::ALL.iter().map(|x| summary.value(*x, n)))
+        })
+        .collect();
+
+    let header = Row::new(Column::ALL.iter().map(|column| match app.sort {
+        Some((x, is_descending)) if x == *column => {
+            format!(
+                "{}{}",
+                column.title(),
+                if is_descending { " ▼" } else { " ▲" }
+            )
+        }
+        _ => column.title().to_owned(),
+    }))
+    .style(Style::default().add_modifier(Modifier::BOLD));
+
+    let widths = [
+        Constraint::Length(6),
+        Constraint::Length(20),
+        Constraint::Length(7),
+        Constraint::Fill(1),
+        Constraint::Length(7),
+        Constraint::Length(8),
+        Constraint::Length(10),
+        Constraint::Length(19),
+    ];
+    let title = if app.filter.is_empty() {
+        format!(" {} items ", app.view.len())
+    } else {
+        format!(
+            " {} of {} items, filter: {} ",
+            app.view.len(),
+            app.index.len(),
+            app.filter
+        )
+    };
+    let table = Table::new(rows, widths)
+        .header(header)
+        .block(
+            Block::bordered()
+                .title(title)
+                .border_style(border_style(app, Focus::Table)),
+        )
+        .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+    let mut state = TableState::default();
+    if !app.view.is_empty() {
+        state.select(Some(app.selected - top));
+    }
+    frame.render_stateful_widget(table, area, &mut state);
+}
+
+fn draw_detail(frame: &mut Frame<'_>, app: &mut App, request_area: Rect, response_area: Rect) {
+    let is_hex = app.is_hex;
+    let (request, response) = match app.detail() {
+        Some(Ok(item)) => (
+            item.request_bytes()
+                .map(|x| highlight::message_lines(&x, is_hex))
+                .unwrap_or_else(|err| vec![Line::raw(err.to_string())]),
+            item.response_bytes()
+                .map(|x| highlight::message_lines(&x, is_hex))
+                .unwrap_or_else(|err| vec![Line::raw(err.to_string())]),
+        ),
+        Some(Err(err)) => (vec![Line::raw(err.to_owned())], vec![]),
+        None => (vec![], vec![]),
+    };
+
+    app.request_lines = request.len().min(u16::MAX as usize) as u16;
+    app.response_lines = response.len().min(u16::MAX as usize) as u16;
+    app.request_scroll = app::scrolled(app.request_scroll, 0, app.request_lines);
+    app.response_scroll = app::scrolled(app.response_scroll, 0, app.response_lines);
+
+    for (lines, title, focus, scroll, area) in [
+        (
+            request,
+            " Request ",
+            Focus::Request,
+            app.request_scroll,
+            request_area,
+        ),
+        (
+            response,
+            " Response ",
+            Focus::Response,
+            app.response_scroll,
+            response_area,
+        ),
+    ] {
+        let paragraph = Paragraph::new(Text::from(lines))
+            .block(
+                Block::bordered()
+                    .title(title)
+                    .border_style(border_style(app, focus)),
+            )
+            .scroll((scroll, 0));
+        frame.render_widget(paragraph, area);
+    }
+}
+
+fn draw_status(frame: &mut Frame<'_>, app: &App, area: Rect) {
+    let line = match (&app.prompt, &app.message) {
+        (Some(Prompt::Search(x)), _) => format!("/{x}"),
+        (Some(Prompt::Filter(x)), _) => format!("filter (host: method: status: mime: url): {x}"),
+        (None, Some(x)) => x.to_owned(),
+        (None, None) => format!(
+            "{HELP}  [{}/{} parsed]",
+            app.loaded_count(),
+            app.index.len()
+        ),
+    };
+    frame.render_widget(
+        Paragraph::new(line).style(Style::default().fg(Color::DarkGray)),
+        area,
+    );
+}